@@ -0,0 +1,629 @@
+// Command line debugger for when there's no window to open (over ssh, on ci).
+// Drives the emulator core directly, without sdl video or audio.
+//
+//   frosty-dbg <rom>
+//
+// Commands are read from stdin, so a session can be scripted by piping them in.
+
+use std::{env, fs, io::{self, BufRead, Write}, panic::{self, AssertUnwindSafe}, process};
+
+use gameboy_rs::gameboy::{GameBoy, cpu::Flag, crash, debugger::{BreakReason, expr::{Condition, Expr}, memory::MemoryBanks, watchpoint::{Access, ValueCondition, WatchKind, Watchpoint}}, trace::{TraceFormat, TraceOptions, TraceStart, compare::CompareOptions}};
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 144;
+
+// how long `c` runs for without hitting anything before giving up
+const CONTINUE_FRAME_LIMIT: u32 = 60 * 60;
+
+const HELP: &str = "\
+Addresses are hex (0150, 0x0150 or $0150) or labels from a .sym file
+
+  b [bank:]addr [if cond]   add a breakpoint, e.g. b 03:4000 if a == 0x3C or b Main.loop
+  info b                    list breakpoints
+  d [n]                     delete breakpoint n, or all of them
+  watch [r|w|rw] addr[-end] [cond]
+                            add a watchpoint, cond is =42, !=42, set 80 or clear 80
+  info w                    list watchpoints
+  dw [n]                    delete watchpoint n, or all of them
+  c [frames]                continue until something stops us (or 3600 frames)
+  s [n]                     step n instructions
+  n                         step over calls
+  fin                       run until the current function returns
+  bt                        show the call stack
+  history [n]               show the last n instructions that ran (default 20)
+  report [path]             write a crash report (history, registers and io registers)
+  frame n                   run for n frames
+  regs                      show the registers
+  x[/nb|/nw] addr           show n bytes or words of memory
+  disas [addr] [n]          disassemble n instructions from addr (default pc)
+  p expr                    print the value of an expression
+  screenshot path           save the screen as a ppm
+  trace path [format] [start]
+                            log every instruction to path, format is doctor (default),
+                            readable or binary, start is boot (default), pc:XXXX or frame:N
+  trace off                 stop tracing
+  doctor-ly [on|off]        make LY always read 0x90, like Gameboy Doctor's logs expect
+  compare path [start] [history n]
+                            run until the emulator stops matching a reference trace (e.g. from
+                            Gameboy Doctor), start defaults to the first pc in the reference
+  sym path                  load labels from a .sym file (game.sym is loaded automatically)
+  q                         quit
+
+An empty line repeats the last command";
+
+fn main() {
+    let rom_path = match env::args().nth(1) {
+        Some(rom_path) => rom_path,
+        None => {
+            println!("Usage: frosty-dbg <rom>");
+            process::exit(1);
+        }
+    };
+
+    let mut gb = GameBoy::new(&rom_path, None);
+
+    // stop before the first instruction
+    gb.debugger_mut().pause();
+    run_until_paused(&mut gb, CONTINUE_FRAME_LIMIT);
+    print_location(&gb);
+
+    let stdin = io::stdin();
+    let mut last_command = String::new();
+
+    loop {
+        print!("(frosty) ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                println!("Unable to read command: {}", err);
+                break;
+            }
+        }
+
+        let mut command = line.trim().to_owned();
+        if command.is_empty() {
+            command = last_command.clone();
+        }
+
+        if command == "q" || command == "quit" {
+            break;
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(|| run_command(&mut gb, &command))) {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => println!("{}", err),
+            Err(payload) => {
+                let reason = crash::panic_message(&*payload);
+                let path = crash::report_file_name();
+
+                println!("The emulator crashed: {}", reason);
+                match gb.write_crash_report(&path, &reason) {
+                    Ok(()) => println!("Crash report saved to {}", path),
+                    Err(err) => println!("Unable to write crash report to {}: {}", path, err)
+                }
+            }
+        }
+
+        last_command = command;
+    }
+
+    gb.save();
+}
+
+fn run_command(gb: &mut GameBoy, command: &str) -> Result<(), String> {
+    let (name, args) = match command.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (command, "")
+    };
+
+    match name {
+        "" => {}
+        "help" | "h" => println!("{}", HELP),
+
+        "b" | "break" => add_breakpoint(gb, args)?,
+        "d" | "delete" => match args {
+            "" => gb.debugger_mut().clear_breakpoints(),
+            index => gb.debugger_mut().remove_breakpoint(parse_index(index)?)
+        },
+
+        "watch" => add_watchpoint(gb, args)?,
+        "dw" => match args {
+            "" => gb.watchpoints_mut().clear(),
+            index => gb.watchpoints_mut().remove(parse_index(index)?)
+        },
+
+        "info" => match args {
+            "b" | "break" => print_breakpoints(gb),
+            "w" | "watch" => print_watchpoints(gb),
+            "r" | "regs" => print_registers(gb),
+            _ => return Err(String::from("info b, info w or info r"))
+        },
+
+        "c" | "continue" => {
+            let max_frames = match args {
+                "" => CONTINUE_FRAME_LIMIT,
+                frames => parse_count(frames)?
+            };
+
+            gb.debugger_mut().resume();
+            run_and_report(gb, max_frames);
+        }
+
+        "s" | "step" => {
+            let count = match args {
+                "" => 1,
+                count => parse_count(count)?
+            };
+
+            for _ in 0..count {
+                gb.step_instruction();
+                if !run_until_paused(gb, CONTINUE_FRAME_LIMIT) || gb.debugger().break_reason() != Some(BreakReason::Step) {
+                    break;
+                }
+            }
+
+            print_location(gb);
+        }
+
+        "n" | "next" => {
+            gb.step_over();
+            run_and_report(gb, CONTINUE_FRAME_LIMIT);
+        }
+
+        "fin" | "finish" => {
+            gb.step_out();
+            run_and_report(gb, CONTINUE_FRAME_LIMIT);
+        }
+
+        "frame" => {
+            let frames = parse_count(args)?;
+            gb.debugger_mut().resume();
+            run_and_report(gb, frames);
+        }
+
+        "regs" => print_registers(gb),
+        "bt" | "backtrace" => print_call_stack(gb),
+        "history" => {
+            let count = match args {
+                "" => 20,
+                _ => parse_index(args)?
+            };
+
+            print_history(gb, count);
+        }
+
+        "report" => {
+            let path = match args {
+                "" => crash::report_file_name(),
+                _ => args.to_owned()
+            };
+
+            gb.write_crash_report(&path, "Requested from frosty-dbg").map_err(|err| format!("Unable to write {}: {}", path, err))?;
+            println!("Saved {}", path);
+        }
+        "sym" => {
+            let count = gb.load_symbols(args).map_err(|err| format!("Unable to read {}: {}", args, err))?;
+            println!("Loaded {} symbols", count);
+        }
+
+        "trace" => match args {
+            "" => match gb.trace() {
+                Some(trace) => println!("Tracing to {}, {} instructions logged", trace.options().path.display(), trace.instructions_logged()),
+                None => println!("Not tracing")
+            },
+            "off" => gb.stop_trace(),
+            _ => start_trace(gb, args)?
+        },
+
+        "doctor-ly" => {
+            match args {
+                "" => {}
+                "on" => gb.set_doctor_ly(true),
+                "off" => gb.set_doctor_ly(false),
+                _ => return Err(format!("doctor-ly takes on or off, not {}", args))
+            }
+
+            println!("LY reads {}", if gb.doctor_ly() { "0x90" } else { "normally" });
+        }
+
+        "compare" => {
+            start_trace_compare(gb, args)?;
+            gb.debugger_mut().resume();
+
+            // no frame limit, the reference decides how long this takes
+            run_and_report(gb, u32::MAX);
+        }
+
+        "p" | "print" => {
+            let expr = Expr::parse(args).map_err(|err| err.to_string())?;
+            let value = gb.evaluate(&expr);
+            println!("{} ({:#X})", value, value);
+        }
+
+        "disas" => {
+            let mut args = args.split_whitespace();
+            let addr = match args.next() {
+                Some(addr) => parse_address(gb, addr)?,
+                None => gb.registers().pc
+            };
+
+            let count = match args.next() {
+                Some(count) => parse_count(count)?,
+                None => 10
+            };
+
+            print_disassembly(gb, addr, count);
+        }
+
+        "screenshot" => {
+            if args.is_empty() {
+                return Err(String::from("screenshot needs a path"));
+            }
+
+            save_screenshot(gb, args)?;
+            println!("Saved {}", args);
+        }
+
+        _ if name == "x" || name.starts_with("x/") => examine(gb, name, args)?,
+
+        _ => return Err(format!("Unknown command {}, try help", name))
+    }
+
+    Ok(())
+}
+
+// Ticks until the debugger pauses. If that hasn't happened after max_frames
+// it stops at the next instruction instead, returns if the debugger paused by itself
+fn run_until_paused(gb: &mut GameBoy, max_frames: u32) -> bool {
+    let mut frames = 0;
+
+    loop {
+        // it'll never get to another instruction
+        if gb.is_cpu_locked() {
+            return false;
+        }
+
+        if gb.tick() {
+            if gb.debugger().is_paused() {
+                return true;
+            }
+
+            println!("The cpu is stopped");
+            return false;
+        }
+
+        if gb.get_draw_flag() {
+            gb.clear_draw_flag();
+            frames += 1;
+
+            if frames >= max_frames {
+                break;
+            }
+        }
+    }
+
+    gb.debugger_mut().pause();
+    while !gb.tick() && !gb.is_cpu_locked() {}
+    false
+}
+
+fn run_and_report(gb: &mut GameBoy, max_frames: u32) {
+    if !run_until_paused(gb, max_frames) && !gb.is_cpu_locked() {
+        println!("Ran for {} frames", max_frames);
+    }
+
+    print_location(gb);
+}
+
+fn print_location(gb: &GameBoy) {
+    if gb.is_cpu_locked() {
+        println!("The cpu is locked up");
+    }
+
+    match gb.debugger().break_reason() {
+        Some(BreakReason::Breakpoint(i)) => println!("Breakpoint {}", i),
+        Some(BreakReason::Watchpoint(hit)) => {
+            let access = match hit.access {
+                Access::Read => "Read from",
+                Access::Write => "Write to",
+                Access::MbcWrite => "MBC register write to"
+            };
+
+            println!(
+                "Watchpoint {}: {} {:04X} ({:02X}) by {:04X} at cycle {}",
+                hit.index, access, hit.addr, hit.value, hit.pc, hit.cycle
+            );
+        }
+
+        Some(BreakReason::TraceCompare) => {
+            if let Some(result) = gb.trace_compare().and_then(|compare| compare.result()) {
+                println!("{}", result);
+            }
+        }
+
+        Some(BreakReason::RunToCursor) | Some(BreakReason::Step) | None => {}
+    }
+
+    let pc = gb.registers().pc;
+    let instr = gb.format_instruction(&gb.disassemble_instruction(pc));
+
+    let location = gb.describe_address(pc);
+    if location.starts_with('$') {
+        println!("{:04X}: {}", pc, instr);
+    } else {
+        println!("{:04X} in {}: {}", pc, location, instr);
+    }
+}
+
+fn print_call_stack(gb: &GameBoy) {
+    let frames = gb.debugger().call_stack().frames();
+    let pc = gb.registers().pc;
+
+    println!("#0 {:04X} in {}", pc, gb.describe_address(pc));
+    for (i, frame) in frames.iter().rev().enumerate() {
+        let kind = if frame.interrupt { "interrupted at" } else { "called from" };
+        println!("#{} {:04X} in {}, {} {}", i + 1, frame.target, gb.describe_address(frame.target), kind, gb.describe_address(frame.from));
+    }
+}
+
+fn print_history(gb: &GameBoy, count: usize) {
+    let history = gb.debugger().history();
+
+    for entry in history.iter().skip(history.len().saturating_sub(count)) {
+        let r = &entry.registers;
+        println!(
+            "{:>12}  {:02X}:{:04X}  {:<24} AF:{:04X} BC:{:04X} DE:{:04X} HL:{:04X} SP:{:04X}",
+            entry.cycle, entry.bank, entry.pc(), gb.format_instruction(&entry.decode()),
+            r.af(), r.bc(), r.de(), r.hl(), r.sp
+        );
+    }
+}
+
+fn add_breakpoint(gb: &mut GameBoy, args: &str) -> Result<(), String> {
+    let (location, condition) = match args.split_once(" if ") {
+        Some((location, condition)) => (location.trim(), Some(condition)),
+        None => (args, None)
+    };
+
+    let (addr, bank) = match (gb.debugger().symbols().resolve(location), location.split_once(':')) {
+        (Some(resolved), _) => resolved,
+        (None, Some((bank, addr))) => (parse_hex(addr)?, Some(parse_hex(bank)?)),
+        (None, None) => (parse_hex(location)?, None)
+    };
+
+    let condition = match condition {
+        Some(condition) => Some(Condition::parse(condition).map_err(|err| err.to_string())?),
+        None => None
+    };
+
+    let index = gb.debugger_mut().add_breakpoint(addr, bank);
+    if let Some(bp) = gb.debugger_mut().breakpoint_mut(index) {
+        bp.condition = condition;
+    }
+
+    println!("Breakpoint {} at {}", index, location);
+    Ok(())
+}
+
+fn add_watchpoint(gb: &mut GameBoy, args: &str) -> Result<(), String> {
+    let mut words: Vec<&str> = args.split_whitespace().collect();
+
+    let kind = match words.first() {
+        Some(&"r") => Some(WatchKind::Read),
+        Some(&"w") => Some(WatchKind::Write),
+        Some(&"rw") => Some(WatchKind::ReadWrite),
+        _ => None
+    };
+
+    if kind.is_some() {
+        words.remove(0);
+    }
+
+    let range = match words.first() {
+        Some(range) => *range,
+        None => return Err(String::from("watch needs an address"))
+    };
+
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (parse_address(gb, start)?, parse_address(gb, end)?),
+        None => (parse_address(gb, range)?, parse_address(gb, range)?)
+    };
+
+    let mut watchpoint = Watchpoint::new(start, end, kind.unwrap_or(WatchKind::Write));
+
+    let condition = words[1..].join(" ");
+    if !condition.is_empty() {
+        match ValueCondition::parse(&condition) {
+            Some(condition) => watchpoint.condition = Some(condition),
+            None => return Err(format!("Invalid condition {}", condition))
+        }
+    }
+
+    let index = gb.watchpoints_mut().add(watchpoint);
+    println!("Watchpoint {} on {}", index, range);
+    Ok(())
+}
+
+fn print_breakpoints(gb: &GameBoy) {
+    for (i, bp) in gb.debugger().breakpoints().iter().enumerate() {
+        let mut line = match bp.bank {
+            Some(bank) => format!("{}: {:02X}:{:04X}", i, bank, bp.addr),
+            None => format!("{}: {:04X}", i, bp.addr)
+        };
+
+        if let Some(condition) = &bp.condition {
+            line.push_str(&format!(" if {}", condition.text()));
+        }
+
+        if !bp.enabled {
+            line.push_str(" (disabled)");
+        }
+
+        println!("{}, hit {} times", line, bp.hit_count);
+    }
+}
+
+fn print_watchpoints(gb: &GameBoy) {
+    for (i, watchpoint) in gb.watchpoints().list().iter().enumerate() {
+        let kind = match watchpoint.kind {
+            WatchKind::Read => "r",
+            WatchKind::Write => "w",
+            WatchKind::ReadWrite => "rw"
+        };
+
+        let condition = match watchpoint.condition {
+            Some(ValueCondition::Equals(value)) => format!(" ={:02X}", value),
+            Some(ValueCondition::NotEquals(value)) => format!(" !={:02X}", value),
+            Some(ValueCondition::BitsSet(mask)) => format!(" set {:02X}", mask),
+            Some(ValueCondition::BitsClear(mask)) => format!(" clear {:02X}", mask),
+            None => String::new()
+        };
+
+        println!("{}: {} {:04X}-{:04X}{}", i, kind, watchpoint.start, watchpoint.end, condition);
+    }
+}
+
+fn print_registers(gb: &GameBoy) {
+    let registers = gb.registers();
+
+    let flag = |flag: Flag, name: char| if registers.is_flag_set(flag) { name } else { '-' };
+
+    println!(
+        "AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={:04X} {}{}{}{}",
+        registers.af(), registers.bc(), registers.de(), registers.hl(), registers.sp, registers.pc,
+        flag(Flag::Z, 'Z'), flag(Flag::N, 'N'), flag(Flag::H, 'H'), flag(Flag::C, 'C')
+    );
+}
+
+fn print_disassembly(gb: &GameBoy, mut addr: u16, count: u32) {
+    let pc = gb.registers().pc;
+
+    for _ in 0..count {
+        if let Some(label) = gb.label_at(addr) {
+            println!("{}:", label);
+        }
+
+        let instr = gb.disassemble_instruction(addr);
+        let marker = if addr == pc { '>' } else if gb.debugger().has_breakpoint(addr) { '*' } else { ' ' };
+
+        println!("{} {:04X}: {}", marker, addr, gb.format_instruction(&instr));
+        addr = addr.wrapping_add(instr.length);
+    }
+}
+
+// x/16b C000 or x/4w FF40
+fn examine(gb: &GameBoy, name: &str, args: &str) -> Result<(), String> {
+    let format = name.strip_prefix("x").unwrap_or("").trim_start_matches('/');
+
+    let (count, size) = match format.strip_suffix('w') {
+        Some(count) => (count, 2),
+        None => (format.strip_suffix('b').unwrap_or(format), 1)
+    };
+
+    let count = match count {
+        "" => 16,
+        count => parse_count(count)?
+    };
+
+    // what's really in memory, even if the ppu has it locked
+    let banks = MemoryBanks::default();
+    let mut addr = parse_address(gb, args)?;
+    let per_line = 16 / size;
+
+    for line in 0..(count + per_line - 1) / per_line {
+        let mut text = format!("{:04X}:", addr);
+
+        for _ in 0..per_line.min(count - line * per_line) {
+            if size == 1 {
+                text.push_str(&format!(" {:02X}", gb.debug_read(addr, banks)));
+            } else {
+                let word = ((gb.debug_read(addr.wrapping_add(1), banks) as u16) << 8) | gb.debug_read(addr, banks) as u16;
+                text.push_str(&format!(" {:04X}", word));
+            }
+
+            addr = addr.wrapping_add(size as u16);
+        }
+
+        println!("{}", text);
+    }
+
+    Ok(())
+}
+
+fn start_trace(gb: &mut GameBoy, args: &str) -> Result<(), String> {
+    let mut args = args.split_whitespace();
+    let mut options = TraceOptions::new(args.next().unwrap_or_default());
+
+    for arg in args {
+        if let Some(format) = TraceFormat::parse(arg) {
+            options.format = format;
+        } else if let Some(start) = TraceStart::parse(arg) {
+            options.start = start;
+        } else {
+            return Err(format!("Unknown trace option {}", arg));
+        }
+    }
+
+    gb.start_trace(options.clone()).map_err(|err| format!("Unable to create {}: {}", options.path.display(), err))?;
+    println!("Tracing to {}", options.path.display());
+    Ok(())
+}
+
+fn start_trace_compare(gb: &mut GameBoy, args: &str) -> Result<(), String> {
+    let mut args = args.split_whitespace();
+    let mut options = CompareOptions::new(args.next().ok_or("compare needs the path to a reference trace")?);
+
+    while let Some(arg) = args.next() {
+        if arg == "history" {
+            options.history = parse_index(args.next().unwrap_or_default())?;
+        } else if let Some(start) = TraceStart::parse(arg) {
+            options.start = Some(start);
+        } else {
+            return Err(format!("Unknown compare option {}", arg));
+        }
+    }
+
+    gb.start_trace_compare(options.clone()).map_err(|err| format!("Unable to read {}: {}", options.path.display(), err))?;
+    println!("Comparing against {}", options.path.display());
+    Ok(())
+}
+
+fn save_screenshot(gb: &GameBoy, path: &str) -> Result<(), String> {
+    let frame_buffer = gb.get_frame_buffer();
+
+    let mut ppm = format!("P6\n{} {}\n255\n", WIDTH, HEIGHT).into_bytes();
+    for rgba in frame_buffer.chunks(4).take(WIDTH * HEIGHT) {
+        ppm.extend_from_slice(&rgba[..3]);
+    }
+
+    fs::write(path, ppm).map_err(|err| format!("Unable to write {}: {}", path, err))
+}
+
+// a label or a hex address
+fn parse_address(gb: &GameBoy, text: &str) -> Result<u16, String> {
+    match gb.debugger().symbols().lookup(text) {
+        Some((_, addr)) => Ok(addr),
+        None => parse_hex(text)
+    }
+}
+
+fn parse_hex(text: &str) -> Result<u16, String> {
+    let digits = text.strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("{} is not a hex address", text))
+}
+
+fn parse_count(text: &str) -> Result<u32, String> {
+    text.parse().map_err(|_| format!("{} is not a number", text))
+}
+
+fn parse_index(text: &str) -> Result<usize, String> {
+    text.parse().map_err(|_| format!("{} is not a number", text))
+}
@@ -0,0 +1,121 @@
+use super::{Image, channels, yuv};
+
+// hq2x. Each of the 8 neighbours is compared with the pixel in YUV (with
+// hqx's thresholds) to get a pattern, and the pattern says how each quarter
+// of the 2x2 block is blended from the pixel and its neighbours. The pattern
+// table is worked out for the top left quarter, the other three use it with
+// the neighbours turned round (the same way as bsnes's hq2x).
+//
+//     A B C
+//     D E F
+//     G H I
+
+const Y_THRESHOLD: i32 = 48;
+const U_THRESHOLD: i32 = 7;
+const V_THRESHOLD: i32 = 6;
+
+// the blending rule for each pattern, bit 0 is A being different, then B, C,
+// D, F, G, H and I
+const RULES: [u8; 256] = [
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 15, 12, 5,  3, 17, 13,
+    4, 4, 6, 18, 4, 4, 6, 18, 5,  3, 12, 12, 5,  3,  1, 12,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 17, 13, 5,  3, 16, 14,
+    4, 4, 6, 18, 4, 4, 6, 18, 5,  3, 16, 12, 5,  3,  1, 14,
+    4, 4, 6,  2, 4, 4, 6,  2, 5, 19, 12, 12, 5, 19, 16, 12,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 16, 12, 5,  3, 16, 12,
+    4, 4, 6,  2, 4, 4, 6,  2, 5, 19,  1, 12, 5, 19,  1, 14,
+    4, 4, 6,  2, 4, 4, 6, 18, 5,  3, 16, 12, 5, 19,  1, 14,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 15, 12, 5,  3, 17, 13,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 16, 12, 5,  3, 16, 12,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 17, 13, 5,  3, 16, 14,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 16, 13, 5,  3,  1, 14,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 16, 12, 5,  3, 16, 13,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 16, 12, 5,  3,  1, 12,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3, 16, 12, 5,  3,  1, 14,
+    4, 4, 6,  2, 4, 4, 6,  2, 5,  3,  1, 12, 5,  3,  1, 14
+];
+
+fn different(a: u32, b: u32) -> bool {
+    if a == b { return false }
+
+    let (a, b) = (yuv(a), yuv(b));
+    (a[0] - b[0]).abs() > Y_THRESHOLD || (a[1] - b[1]).abs() > U_THRESHOLD || (a[2] - b[2]).abs() > V_THRESHOLD
+}
+
+// colours weighted weight_e:weight_a:weight_b
+fn blend(e: u32, a: u32, b: u32, weight_e: u32, weight_a: u32, weight_b: u32) -> u32 {
+    let (e, a, b) = (channels(e), channels(a), channels(b));
+    let total = weight_e + weight_a + weight_b;
+
+    let mut blended = 0;
+    for c in 0..4 {
+        blended = (blended << 8) | ((e[c] * weight_e + a[c] * weight_a + b[c] * weight_b) / total);
+    }
+
+    blended
+}
+
+pub(super) fn hq2x(src: &Image) -> Image {
+    let mut out = Image::new(src.width * 2, src.height * 2);
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let mut block = [0; 4];
+
+            // a quarter turn for each corner going round, so the top right is
+            // one turn and the bottom left three
+            for (pixel, turns) in block.iter_mut().zip([0, 1, 3, 2].iter()) {
+                *pixel = quarter(src, x as isize, y as isize, *turns);
+            }
+
+            out.set_block(x, y, 2, &block);
+        }
+    }
+
+    out
+}
+
+// worked out for the top left, with the neighbours turned so the corner
+// being worked out is at A
+fn quarter(src: &Image, x: isize, y: isize, turns: usize) -> u32 {
+    let at = |u: isize, v: isize| {
+        let (mut u, mut v) = (u, v);
+        for _ in 0..turns {
+            let turned = (-v, u);
+            u = turned.0;
+            v = turned.1;
+        }
+
+        src.get(x + u, y + v)
+    };
+
+    let e = at(0, 0);
+    let neighbours = [at(-1, -1), at(0, -1), at(1, -1), at(-1, 0), at(1, 0), at(-1, 1), at(0, 1), at(1, 1)];
+    let [a, b, _, d, f, _, h, _] = neighbours;
+
+    let mut pattern = 0;
+    for (bit, neighbour) in neighbours.iter().enumerate() {
+        if different(e, *neighbour) {
+            pattern |= 1 << bit;
+        }
+    }
+
+    let same = |p: u32, q: u32| !different(p, q);
+    match RULES[pattern] {
+        1 => blend(e, a, a, 3, 1, 0),
+        2 => blend(e, d, d, 3, 1, 0),
+        3 => blend(e, b, b, 3, 1, 0),
+        4 => blend(e, d, b, 2, 1, 1),
+        5 => blend(e, a, b, 2, 1, 1),
+        6 => blend(e, a, d, 2, 1, 1),
+        12 => if same(b, d) { blend(e, d, b, 2, 1, 1) } else { e },
+        13 => if same(b, d) { blend(e, d, b, 14, 1, 1) } else { e },
+        14 => if same(b, d) { blend(e, d, b, 6, 1, 1) } else { e },
+        15 => if same(b, d) { blend(e, d, b, 2, 1, 1) } else { blend(e, a, a, 3, 1, 0) },
+        16 => if same(b, d) { blend(e, d, b, 2, 3, 3) } else { blend(e, a, a, 3, 1, 0) },
+        17 => if same(b, d) { blend(e, d, b, 6, 1, 1) } else { blend(e, a, a, 3, 1, 0) },
+        18 => if same(b, f) { blend(e, b, d, 5, 2, 1) } else { blend(e, d, d, 3, 1, 0) },
+        19 => if same(d, h) { blend(e, d, b, 5, 2, 1) } else { blend(e, b, b, 3, 1, 0) },
+        _ => e
+    }
+}
@@ -0,0 +1,276 @@
+use std::mem;
+
+mod hqx;
+mod xbr;
+
+// Filters for drawing the screen bigger, done on the cpu so there's no need
+// for shaders. They take an RGBA8888 image (like GameBoy::get_frame_buffer)
+// and give back one Filter::scale times as wide and as high.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    None,
+    Scale2x,
+    Scale3x,
+    Hq2x,
+    Xbr,
+    LcdGrid
+}
+
+impl Filter {
+    pub const ALL: [Filter; 6] = [
+        Filter::None,
+        Filter::Scale2x,
+        Filter::Scale3x,
+        Filter::Hq2x,
+        Filter::Xbr,
+        Filter::LcdGrid
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::None => "None",
+            Filter::Scale2x => "Scale2x",
+            Filter::Scale3x => "Scale3x",
+            Filter::Hq2x => "HQ2x",
+            Filter::Xbr => "xBR",
+            Filter::LcdGrid => "LCD grid"
+        }
+    }
+
+    pub fn scale(&self) -> usize {
+        match self {
+            Filter::None => 1,
+            Filter::Scale2x | Filter::Hq2x | Filter::Xbr => 2,
+            Filter::Scale3x | Filter::LcdGrid => 3
+        }
+    }
+
+    pub fn apply(&self, rgba: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let image = Image::from_rgba(rgba, width, height);
+        let scaled = match self {
+            Filter::None => return rgba.to_vec(),
+            Filter::Scale2x => scale2x(&image),
+            Filter::Scale3x => scale3x(&image),
+            Filter::Hq2x => hqx::hq2x(&image),
+            Filter::Xbr => xbr::xbr2x(&image),
+            Filter::LcdGrid => lcd_grid(&image)
+        };
+
+        scaled.into_rgba()
+    }
+}
+
+// pixels packed as 0xRRGGBBAA so they can be compared
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![0; width * height] }
+    }
+
+    fn from_rgba(rgba: &[u8], width: usize, height: usize) -> Self {
+        let pixels = rgba.chunks_exact(4)
+            .map(|pixel| u32::from_be_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect();
+
+        Self { width, height, pixels }
+    }
+
+    fn into_rgba(self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in self.pixels.iter() {
+            rgba.extend_from_slice(&pixel.to_be_bytes());
+        }
+
+        rgba
+    }
+
+    // past the edges is the nearest pixel
+    fn get(&self, x: isize, y: isize) -> u32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    // the size x size block a source pixel turns into, row by row
+    fn set_block(&mut self, x: usize, y: usize, size: usize, block: &[u32]) {
+        for (i, pixel) in block.iter().enumerate() {
+            let out_x = x * size + i % size;
+            let out_y = y * size + i / size;
+            self.pixels[out_y * self.width + out_x] = *pixel;
+        }
+    }
+}
+
+fn channels(pixel: u32) -> [u32; 4] {
+    [pixel >> 24, (pixel >> 16) & 0xFF, (pixel >> 8) & 0xFF, pixel & 0xFF]
+}
+
+// a and b weighted weight_a:weight_b
+fn mix(a: u32, b: u32, weight_a: u32, weight_b: u32) -> u32 {
+    let (a, b) = (channels(a), channels(b));
+    let mut mixed = 0;
+    for c in 0..4 {
+        mixed = (mixed << 8) | ((a[c] * weight_a + b[c] * weight_b) / (weight_a + weight_b));
+    }
+
+    mixed
+}
+
+// brightness and colour, which is closer to what looks alike than rgb is
+fn yuv(pixel: u32) -> [i32; 3] {
+    let [r, g, b, _] = channels(pixel);
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+
+    [
+        (299 * r + 587 * g + 114 * b) / 1000,
+        (-169 * r - 331 * g + 500 * b) / 1000 + 128,
+        (500 * r - 419 * g - 81 * b) / 1000 + 128
+    ]
+}
+
+// AdvMAME2x/EPX, each pixel becomes 2x2 and a corner takes the colour of the
+// two neighbours on either side of it when they match, unless the edge
+// carries on past the pixel
+fn scale2x(src: &Image) -> Image {
+    let mut out = Image::new(src.width * 2, src.height * 2);
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (xi, yi) = (x as isize, y as isize);
+            let b = src.get(xi, yi - 1);
+            let d = src.get(xi - 1, yi);
+            let e = src.get(xi, yi);
+            let f = src.get(xi + 1, yi);
+            let h = src.get(xi, yi + 1);
+
+            let mut block = [e; 4];
+            if b != h && d != f {
+                if d == b { block[0] = d; }
+                if b == f { block[1] = f; }
+                if d == h { block[2] = d; }
+                if h == f { block[3] = f; }
+            }
+
+            out.set_block(x, y, 2, &block);
+        }
+    }
+
+    out
+}
+
+// AdvMAME3x, the same idea with the edges of the 3x3 block as well
+fn scale3x(src: &Image) -> Image {
+    let mut out = Image::new(src.width * 3, src.height * 3);
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (xi, yi) = (x as isize, y as isize);
+            let a = src.get(xi - 1, yi - 1);
+            let b = src.get(xi, yi - 1);
+            let c = src.get(xi + 1, yi - 1);
+            let d = src.get(xi - 1, yi);
+            let e = src.get(xi, yi);
+            let f = src.get(xi + 1, yi);
+            let g = src.get(xi - 1, yi + 1);
+            let h = src.get(xi, yi + 1);
+            let i = src.get(xi + 1, yi + 1);
+
+            let mut block = [e; 9];
+            if b != h && d != f {
+                if d == b { block[0] = d; }
+                if (d == b && e != c) || (b == f && e != a) { block[1] = b; }
+                if b == f { block[2] = f; }
+                if (d == b && e != g) || (d == h && e != a) { block[3] = d; }
+                if (b == f && e != i) || (h == f && e != c) { block[5] = f; }
+                if d == h { block[6] = d; }
+                if (d == h && e != i) || (h == f && e != g) { block[7] = h; }
+                if h == f { block[8] = f; }
+            }
+
+            out.set_block(x, y, 3, &block);
+        }
+    }
+
+    out
+}
+
+// the grid lines are this much of the pixel's brightness (out of 4)
+const GRID_BRIGHTNESS: u32 = 3;
+
+// each pixel as a 3x3 dot with a darker line along the right and bottom,
+// like the gaps between the dots on a DMG screen
+fn lcd_grid(src: &Image) -> Image {
+    let mut out = Image::new(src.width * 3, src.height * 3);
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let e = src.get(x as isize, y as isize);
+            let line = mix(e, 0x0000_00FF, GRID_BRIGHTNESS, 4 - GRID_BRIGHTNESS);
+
+            out.set_block(x, y, 3, &[
+                e, e, line,
+                e, e, line,
+                line, line, line
+            ]);
+        }
+    }
+
+    out
+}
+
+// The DMG's lcd is slow to change, so each frame is shown half and half with
+// the one before it. Games that flicker things every other frame for
+// transparency (or to get around the 10 sprite limit) look the way they do
+// on hardware instead of flashing.
+pub struct FrameBlender {
+    previous: Vec<u8>,
+    current: Vec<u8>
+}
+
+impl Default for FrameBlender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameBlender {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new()
+        }
+    }
+
+    // a new frame mixed with the last one, the first frame comes back as it is
+    pub fn blend(&mut self, frame: &[u8]) -> Vec<u8> {
+        self.previous = mem::replace(&mut self.current, frame.to_vec());
+        self.mixed()
+    }
+
+    // the same frame again with changes (a new palette), without moving on a frame
+    pub fn reblend(&mut self, frame: &[u8]) -> Vec<u8> {
+        self.current = frame.to_vec();
+        self.mixed()
+    }
+
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    fn mixed(&self) -> Vec<u8> {
+        if self.previous.len() != self.current.len() {
+            return self.current.clone();
+        }
+
+        self.previous.iter().zip(self.current.iter())
+            .map(|(previous, current)| ((*previous as u16 + *current as u16) / 2) as u8)
+            .collect()
+    }
+}
@@ -0,0 +1,65 @@
+use super::{Image, mix, yuv};
+
+// xBR at 2x with the level 1 rules. For each corner it adds up colour
+// distances around it to see whether an edge runs across the corner (F to H
+// for the bottom right) or through it (E to I), and if it's across, the
+// corner is mixed half and half with whichever of F and H is closer.
+//
+//        A1 B1 C1
+//     A0 A  B  C  C4
+//     D0 D  E  F  F4
+//     G0 G  H  I  I4
+//        G5 H5 I5
+
+fn distance(a: u32, b: u32) -> i32 {
+    let (a, b) = (yuv(a), yuv(b));
+    48 * (a[0] - b[0]).abs() + 7 * (a[1] - b[1]).abs() + 6 * (a[2] - b[2]).abs()
+}
+
+pub(super) fn xbr2x(src: &Image) -> Image {
+    let mut out = Image::new(src.width * 2, src.height * 2);
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let mut block = [0; 4];
+            for (pixel, (dx, dy)) in block.iter_mut().zip([(-1, -1), (1, -1), (-1, 1), (1, 1)].iter()) {
+                *pixel = corner(src, x as isize, y as isize, *dx, *dy);
+            }
+
+            out.set_block(x, y, 2, &block);
+        }
+    }
+
+    out
+}
+
+// worked out for the bottom right, the other corners are the same flipped
+fn corner(src: &Image, x: isize, y: isize, dx: isize, dy: isize) -> u32 {
+    let at = |u: isize, v: isize| src.get(x + u * dx, y + v * dy);
+
+    let e = at(0, 0);
+    let f = at(1, 0);
+    let h = at(0, 1);
+    if e == f || e == h {
+        return e;
+    }
+
+    let b = at(0, -1);
+    let c = at(1, -1);
+    let d = at(-1, 0);
+    let g = at(-1, 1);
+    let i = at(1, 1);
+    let f4 = at(2, 0);
+    let i4 = at(2, 1);
+    let h5 = at(0, 2);
+    let i5 = at(1, 2);
+
+    let across = distance(e, c) + distance(e, g) + distance(i, f4) + distance(i, h5) + 4 * distance(h, f);
+    let through = distance(h, d) + distance(h, i5) + distance(f, i4) + distance(f, b) + 4 * distance(e, i);
+    if across >= through {
+        return e;
+    }
+
+    let nearer = if distance(e, f) <= distance(e, h) { f } else { h };
+    mix(e, nearer, 1, 1)
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+
+// https://gbdev.io/pandocs/The_Cartridge_Header.html
+
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83,
+    0x00, 0x0C, 0x00, 0x0D, 0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E,
+    0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99, 0xBB, 0xBB, 0x67, 0x63,
+    0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E
+];
+
+pub struct CartridgeHeader {
+    pub title: String,
+
+    // 0x134-0x143 as they are, the cgb boot rom picks colours for dmg games from these
+    pub raw_title: [u8; 16],
+    pub manufacturer_code: Option<String>,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    pub new_licensee_code: String,
+    pub old_licensee_code: u8,
+    pub cartridge_type: u8,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub destination_code: u8,
+    pub version: u8,
+
+    pub header_checksum: u8,
+    pub header_checksum_valid: bool,
+    pub global_checksum: u16,
+    pub global_checksum_valid: bool,
+    pub logo_valid: bool
+}
+
+impl CartridgeHeader {
+    // None if the rom is too small to even contain a header
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        if rom.len() < 0x150 {
+            return None;
+        }
+
+        let cgb_flag = rom[0x143];
+
+        // Newer carts shortened the title to make room for the manufacturer code
+        // and the cgb flag. There isn't a reliable way to tell if the code is
+        // there, but it's always 4 uppercase chars on the carts that have one.
+        let mut title_end = if cgb_flag & 0x80 != 0 { 0x143 } else { 0x144 };
+        let mut manufacturer_code = None;
+        if cgb_flag & 0x80 != 0 {
+            let code = &rom[0x13F..0x143];
+            if code.iter().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+                manufacturer_code = Some(String::from_utf8_lossy(code).into_owned());
+                title_end = 0x13F;
+            }
+        }
+
+        let title: String = rom[0x134..title_end].iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as char)
+            .collect();
+
+        let mut raw_title = [0u8; 16];
+        raw_title.copy_from_slice(&rom[0x134..0x144]);
+
+        let mut header_checksum: u8 = 0;
+        for byte in &rom[0x134..0x14D] {
+            header_checksum = header_checksum.wrapping_sub(*byte).wrapping_sub(1);
+        }
+
+        let mut global_checksum: u16 = 0;
+        for (i, byte) in rom.iter().enumerate() {
+            if i != 0x14E && i != 0x14F {
+                global_checksum = global_checksum.wrapping_add(*byte as u16);
+            }
+        }
+
+        let stored_global_checksum = ((rom[0x14E] as u16) << 8) | rom[0x14F] as u16;
+
+        Some(Self {
+            title: title.trim_end().to_owned(),
+            raw_title,
+            manufacturer_code,
+            cgb_flag,
+            sgb_flag: rom[0x146],
+            new_licensee_code: String::from_utf8_lossy(&rom[0x144..0x146]).into_owned(),
+            old_licensee_code: rom[0x14B],
+            cartridge_type: rom[0x147],
+            rom_size_code: rom[0x148],
+            ram_size_code: rom[0x149],
+            destination_code: rom[0x14A],
+            version: rom[0x14C],
+
+            header_checksum: rom[0x14D],
+            header_checksum_valid: header_checksum == rom[0x14D],
+            global_checksum: stored_global_checksum,
+            global_checksum_valid: global_checksum == stored_global_checksum,
+            logo_valid: rom[0x104..0x134] == NINTENDO_LOGO
+        })
+    }
+
+    pub fn is_cgb_only(&self) -> bool {
+        self.cgb_flag == 0xC0
+    }
+
+    pub fn has_battery(&self) -> bool {
+        matches!(self.cartridge_type, 0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF)
+    }
+
+    pub fn mapper_name(&self) -> &'static str {
+        match self.cartridge_type {
+            0x00 => "ROM ONLY",
+            0x01 => "MBC1",
+            0x02 => "MBC1+RAM",
+            0x03 => "MBC1+RAM+BATTERY",
+            0x05 => "MBC2",
+            0x06 => "MBC2+BATTERY",
+            0x08 => "ROM+RAM",
+            0x09 => "ROM+RAM+BATTERY",
+            0x0B => "MMM01",
+            0x0C => "MMM01+RAM",
+            0x0D => "MMM01+RAM+BATTERY",
+            0x0F => "MBC3+TIMER+BATTERY",
+            0x10 => "MBC3+TIMER+RAM+BATTERY",
+            0x11 => "MBC3",
+            0x12 => "MBC3+RAM",
+            0x13 => "MBC3+RAM+BATTERY",
+            0x19 => "MBC5",
+            0x1A => "MBC5+RAM",
+            0x1B => "MBC5+RAM+BATTERY",
+            0x1C => "MBC5+RUMBLE",
+            0x1D => "MBC5+RUMBLE+RAM",
+            0x1E => "MBC5+RUMBLE+RAM+BATTERY",
+            0x20 => "MBC6",
+            0x22 => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            0xFC => "POCKET CAMERA",
+            0xFD => "BANDAI TAMA5",
+            0xFE => "HuC3",
+            0xFF => "HuC1+RAM+BATTERY",
+            _ => "UNKNOWN"
+        }
+    }
+
+    // This includes rom bank 0
+    pub fn num_rom_banks(&self) -> Option<u16> {
+        match self.rom_size_code {
+            0x00 => Some(2),   // 32KB
+            0x01 => Some(4),   // 64KB
+            0x02 => Some(8),   // 128KB
+            0x03 => Some(16),  // 256KB
+            0x04 => Some(32),  // 512KB
+            0x05 => Some(64),  // 1MB
+            0x06 => Some(128), // 2MB
+            0x07 => Some(256), // 4MB
+            0x08 => Some(512), // 8MB
+
+            // pandocs says there are some other special codes
+            // but is not sure if they are legit
+            // lets define them anyway
+            0x52 => Some(72),  // 1.1MB
+            0x53 => Some(80),  // 1.2MB
+            0x54 => Some(96),  // 1.5MB
+
+            _ => None
+        }
+    }
+
+    pub fn num_ram_banks(&self) -> Option<u16> {
+        match self.ram_size_code {
+            0x00 => Some(0),
+            0x02 => Some(1),
+            0x03 => Some(4),
+            0x04 => Some(16),
+            0x05 => Some(8),
+
+            _ => None
+        }
+    }
+
+    pub fn licensee(&self) -> String {
+        // 0x33 means the new licensee code should be used instead
+        if self.old_licensee_code == 0x33 {
+            self.new_licensee_code.clone()
+        } else {
+            format!("{:02X}", self.old_licensee_code)
+        }
+    }
+}
+
+impl fmt::Display for CartridgeHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let valid_str = |valid: bool| if valid { "ok" } else { "INVALID" };
+
+        let cgb_str = match self.cgb_flag {
+            0xC0 => "CGB only",
+            0x80 => "CGB enhanced",
+            _ => "DMG"
+        };
+
+        let rom_size_str = match self.num_rom_banks() {
+            Some(banks) => format!("{} KiB, {} banks", banks as u32 * 16, banks),
+            None => String::from("unknown")
+        };
+
+        let ram_size_str = match self.num_ram_banks() {
+            Some(banks) => format!("{} KiB, {} banks", banks as u32 * 8, banks),
+            None => String::from("unknown")
+        };
+
+        writeln!(f, "Title:            {}", self.title)?;
+        writeln!(f, "Manufacturer:     {}", self.manufacturer_code.as_deref().unwrap_or("-"))?;
+        writeln!(f, "CGB flag:         {:#04X} ({})", self.cgb_flag, cgb_str)?;
+        writeln!(f, "SGB flag:         {:#04X} ({})", self.sgb_flag, if self.sgb_flag == 0x03 { "SGB supported" } else { "no SGB" })?;
+        writeln!(f, "Old licensee:     {:#04X}", self.old_licensee_code)?;
+        writeln!(f, "New licensee:     {}", self.new_licensee_code)?;
+        writeln!(f, "Licensee:         {}", self.licensee())?;
+        writeln!(f, "Cartridge type:   {:#04X} ({})", self.cartridge_type, self.mapper_name())?;
+        writeln!(f, "ROM size:         {:#04X} ({})", self.rom_size_code, rom_size_str)?;
+        writeln!(f, "RAM size:         {:#04X} ({})", self.ram_size_code, ram_size_str)?;
+        writeln!(f, "Destination:      {:#04X} ({})", self.destination_code, if self.destination_code == 0 { "Japan" } else { "Overseas" })?;
+        writeln!(f, "Version:          {:#04X}", self.version)?;
+        writeln!(f, "Header checksum:  {:#04X} ({})", self.header_checksum, valid_str(self.header_checksum_valid))?;
+        writeln!(f, "Global checksum:  {:#06X} ({})", self.global_checksum, valid_str(self.global_checksum_valid))?;
+        write!(f, "Nintendo logo:    {}", valid_str(self.logo_valid))
+    }
+}
@@ -0,0 +1,102 @@
+use std::{fs::{self, File}, io::{self, Read}, path::Path};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+pub struct LoadedRom {
+    pub data: Vec<u8>,
+
+    // file name of the rom itself, so for archives this is the name
+    // of the rom inside of it rather than the archive
+    pub file_name: String
+}
+
+impl LoadedRom {
+    pub fn file_stem(&self) -> &str {
+        Path::new(&self.file_name).file_stem().and_then(|stem| stem.to_str()).unwrap_or("rom")
+    }
+}
+
+// Loads a rom from disk, unpacking it first if it's in a .zip or .gz
+pub fn load_rom_file<P: AsRef<Path>>(path: P) -> io::Result<LoadedRom> {
+    let path = path.as_ref();
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("zip") => load_zip(path),
+        Some("gz") => load_gz(path),
+        _ => {
+            Ok(LoadedRom {
+                data: fs::read(path)?,
+                file_name: file_name_of(path)
+            })
+        }
+    }
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn is_rom_file_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".gb") || name.ends_with(".gbc")
+}
+
+fn load_zip(path: &Path) -> io::Result<LoadedRom> {
+    let mut archive = ZipArchive::new(File::open(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    // use the first rom we find in the archive
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        if entry.is_dir() || !is_rom_file_name(entry.name()) {
+            continue;
+        }
+
+        let file_name = file_name_of(Path::new(entry.name()));
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+
+        return Ok(LoadedRom {
+            data,
+            file_name
+        });
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} does not contain a .gb or .gbc file", path.display())
+    ))
+}
+
+fn load_gz(path: &Path) -> io::Result<LoadedRom> {
+    let mut decoder = GzDecoder::new(File::open(path)?);
+
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data)?;
+
+    // prefer the original name stored in the gzip header, otherwise
+    // just drop the .gz (game.gb.gz -> game.gb)
+    let stored_name = decoder.header()
+        .and_then(|header| header.filename())
+        .map(|name| String::from_utf8_lossy(name).into_owned());
+
+    let file_name = match stored_name {
+        Some(name) => file_name_of(Path::new(&name)),
+        None => path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    Ok(LoadedRom {
+        data,
+        file_name
+    })
+}
@@ -1,6 +1,4 @@
-use std::{fs::File, io::{Read, Write}, path::{Path, PathBuf}};
-
-use super::{Cartridge, get_save_file_path_from_rom_path, try_read_save_file};
+use super::{Cartridge, SaveFile, split_rom_banks};
 
 
 pub struct MBC5 {
@@ -16,34 +14,18 @@ pub struct MBC5 {
     rom_banks: Vec<[u8; 0x4000]>,
     ram_banks: Vec<[u8; 0x2000]>,
 
-    save_file_path: PathBuf
+    save_file: SaveFile
 }
 
 impl MBC5 {
     pub fn new(
-        mut file: File,
-        path: &Path,
-        rom_bank_0: [u8; 0x4000],
-        cartridge_type_code: u8, 
+        rom: &[u8],
+        mut save_file: SaveFile,
         num_rom_banks: u16, 
         num_ram_banks: u16
     ) -> Self {
-        let mut rom_banks = Vec::new();
-        rom_banks.push(rom_bank_0);
-
-        for _ in 0..num_rom_banks - 1 {
-            let mut bank = [0; 0x4000];
-            file.read_exact(&mut bank).ok();
-            rom_banks.push(bank);
-        }
-
-        let mut ram_banks = Vec::new();
-
-        let save_file_path = get_save_file_path_from_rom_path(path);
-
-        // try to open save file
-        let sav_file = File::open(&save_file_path);
-        try_read_save_file(sav_file, num_ram_banks, &mut ram_banks);
+        let rom_banks = split_rom_banks(rom, num_rom_banks);
+        let ram_banks = save_file.load_ram_banks(num_ram_banks);
 
         Self {
             is_ram_enabled: false,
@@ -57,20 +39,14 @@ impl MBC5 {
             rom_banks,
             ram_banks,
 
-            save_file_path
+            save_file
         }
     }
 }
 
 impl Drop for MBC5 {
     fn drop(&mut self) {
-        // create save file
-        let mut sav_file = File::create(&self.save_file_path).unwrap();
-        for bank in &self.ram_banks {
-            sav_file.write_all(bank).unwrap();
-        }
-        println!("Save file written!");
-
+        self.save();
     }
 }
 
@@ -121,12 +97,71 @@ impl Cartridge for MBC5 {
     fn read_ram(&self, addr: u16) -> u8 {
         if !self.is_ram_enabled { return 0xFF; }
 
-        self.ram_banks[self.current_ram_bank][addr as usize]
+        // open bus if the cart doesn't have this much ram
+        match self.ram_banks.get(self.current_ram_bank) {
+            Some(bank) => bank[addr as usize],
+            None => 0xFF
+        }
     }
 
     fn write_ram(&mut self, addr: u16, value: u8) {
         if !self.is_ram_enabled { return }
 
-        self.ram_banks[self.current_ram_bank][addr as usize] = value;
+        if let Some(bank) = self.ram_banks.get_mut(self.current_ram_bank) {
+            bank[addr as usize] = value;
+            self.save_file.mark_dirty();
+        }
+    }
+
+    fn current_rom_bank(&self) -> u16 {
+        self.current_rom_bank as u16
+    }
+
+    fn save(&mut self) -> bool {
+        self.save_file.flush(&self.ram_banks)
     }
-}
\ No newline at end of file
+
+    fn mark_ram_dirty(&mut self) {
+        self.save_file.mark_dirty();
+    }
+
+    fn poke_ram(&mut self, bank: Option<usize>, addr: u16, value: u8) {
+        let bank = bank.unwrap_or(self.current_ram_bank);
+        if let Some(ram_bank) = self.ram_banks.get_mut(bank) {
+            ram_bank[addr as usize] = value;
+        }
+    }
+
+    fn rom_bank_count(&self) -> usize {
+        self.rom_banks.len()
+    }
+
+    fn ram_bank_count(&self) -> usize {
+        self.ram_banks.len()
+    }
+
+    fn current_ram_bank(&self) -> usize {
+        self.current_ram_bank
+    }
+
+    fn peek_rom(&self, bank: usize, addr: u16) -> u8 {
+        match self.rom_banks.get(bank) {
+            Some(rom_bank) => rom_bank[addr as usize],
+            None => 0xFF
+        }
+    }
+
+    fn peek_ram(&self, bank: Option<usize>, addr: u16) -> u8 {
+        let bank = bank.unwrap_or(self.current_ram_bank);
+        match self.ram_banks.get(bank) {
+            Some(ram_bank) => ram_bank[addr as usize],
+            None => 0xFF
+        }
+    }
+
+    fn poke_rom(&mut self, bank: usize, addr: u16, value: u8) {
+        if let Some(rom_bank) = self.rom_banks.get_mut(bank) {
+            rom_bank[addr as usize] = value;
+        }
+    }
+}
@@ -1,15 +1,19 @@
-use std::{fs::File, io::{Error, Read}, path::{Path, PathBuf}};
+use std::{fs, path::{Path, PathBuf}};
 
-use crate::gameboy::cartridge::{mbc1::MBC1, mbc3::MBC3, mbc5::MBC5, rom::ROM};
+use crate::gameboy::cartridge::{header::CartridgeHeader, loader::load_rom_file, mbc1::MBC1, mbc3::MBC3, mbc5::MBC5, patch::{apply_patch, find_patch_for_rom}, rom::ROM, save_storage::{FileSaveStorage, SaveStorage}};
 
 // https://gbdev.io/pandocs/#the-cartridge-header
 // http://marc.rawer.de/Gameboy/Docs/GBCPUman.pdf Section 2.6 (page 13)
 
 
+pub mod header;
+pub mod loader;
+pub mod patch;
 pub mod rom;
 pub mod mbc1;
 pub mod mbc3;
 pub mod mbc5;
+pub mod save_storage;
 
 pub trait Cartridge {
     fn read_rom(&self, addr: u16) -> u8;
@@ -17,72 +21,118 @@ pub trait Cartridge {
 
     fn read_ram(&self, addr: u16) -> u8;
     fn write_ram(&mut self, addr: u16, value: u8);
+
+    // bank switched into 0x4000-0x7FFF
+    fn current_rom_bank(&self) -> u16 { 1 }
+
+    // flush the cartridge ram to the save storage if it has changed,
+    // true if it was written
+    fn save(&mut self) -> bool { false }
+
+    // write straight into a ram bank (or the current one), even if the ram is disabled.
+    // used by cheats, doesn't mark the save as changed
+    fn poke_ram(&mut self, _bank: Option<usize>, _addr: u16, _value: u8) { }
+
+    // the ram was changed by poke_ram and should be saved (memory viewer edits)
+    fn mark_ram_dirty(&mut self) { }
+
+    // the rest are for the memory viewer, they look at any bank
+    // no matter what's switched in or if the ram is enabled
+    fn rom_bank_count(&self) -> usize { 2 }
+    fn ram_bank_count(&self) -> usize { 0 }
+    fn current_ram_bank(&self) -> usize { 0 }
+
+    // addr is the offset into the bank
+    fn peek_rom(&self, bank: usize, addr: u16) -> u8;
+    fn peek_ram(&self, _bank: Option<usize>, _addr: u16) -> u8 { 0xFF }
+
+    // patches the rom in memory only, the file isn't touched
+    fn poke_rom(&mut self, _bank: usize, _addr: u16, _value: u8) { }
 }
 
-pub fn create(rom_path: &str) -> Box<dyn Cartridge> {
-    let mut rom_bank_0 = [0u8; 0x4000];
+#[derive(Default)]
+pub struct LoadOptions {
+    // defaults to saving next to the rom
+    pub save_storage: Option<Box<dyn SaveStorage>>,
 
+    // an ips/ups/bps patch to apply, otherwise one with the same
+    // name as the rom is used if there is one
+    pub patch_path: Option<PathBuf>
+}
+
+// errors are messages to show whoever picked the rom. Also gives back the
+// name saves and the like go by, the rom's own name even if it's in an archive
+pub fn create(rom_path: &str, options: LoadOptions) -> Result<(Box<dyn Cartridge>, CartridgeHeader, String), String> {
     let path = Path::new(rom_path);
-    let file = File::open(path);
-    let mut file = match file {
-        Ok(f) => f,
-        Err(err) => panic!("Something went wrong reading the ROM: {}", err)
+    let loaded_rom = match load_rom_file(path) {
+        Ok(loaded_rom) => loaded_rom,
+        Err(err) => return Err(format!("Something went wrong reading the ROM: {}", err))
     };
 
-    file.read_exact(&mut rom_bank_0).ok();
+    let patched_rom = match options.patch_path {
+        Some(patch_path) => match patch_rom(&loaded_rom.data, &patch_path) {
+            Ok(patched_rom) => Some(patched_rom),
+            Err(err) => return Err(format!("Unable to apply patch {}: {}", patch_path.display(), err))
+        },
+
+        // don't refuse to load the game if a patch we found ourselves doesn't fit
+        None => find_patch_for_rom(path).and_then(|patch_path| {
+            match patch_rom(&loaded_rom.data, &patch_path) {
+                Ok(patched_rom) => Some(patched_rom),
+                Err(err) => {
+                    println!("Not applying patch {}: {}", patch_path.display(), err);
+                    None
+                }
+            }
+        })
+    };
+
+    let rom = patched_rom.as_ref().unwrap_or(&loaded_rom.data);
 
     // parse cart header
-    // CGB flag
-    if rom_bank_0[0x143] == 0xC0 {
-        panic!("This rom is only supported for game boy color");
+    let header = match CartridgeHeader::parse(rom) {
+        Some(header) => header,
+        None => return Err(String::from("The ROM is too small to contain a cartridge header"))
+    };
+
+    if header.is_cgb_only() {
+        return Err(String::from("This rom is only supported for game boy color"));
     }
 
-    let cartridge_type_code = rom_bank_0[0x147];
-    let rom_size_code = rom_bank_0[0x148];
-    let ram_size_code = rom_bank_0[0x149];
-
-    // This includes rom bank 0
-    let num_rom_banks: u16 = match rom_size_code {
-        0x00 => 2,   // 32KB
-        0x01 => 4,   // 64KB
-        0x02 => 8,   // 128KB
-        0x03 => 16,  // 256KB
-        0x04 => 32,  // 512KB
-        0x05 => 64,  // 1MB
-        0x06 => 128, // 2MB
-        0x07 => 256, // 4MB
-        0x08 => 512, // 8MB
-
-        // pandocs says there are some other special codes
-        // but is not sure if they are legit
-        // lets define them anyway
-        0x52 => 72,  // 1.1MB
-        0x53 => 80,  // 1.2MB
-        0x54 => 96,  // 1.5MB
-        
-        _ => panic!("Cartridge has invalid ROM size code? Code: {:#04X}", rom_size_code)
+    let cartridge_type_code = header.cartridge_type;
+
+    let num_rom_banks = match header.num_rom_banks() {
+        Some(num_rom_banks) => num_rom_banks,
+        None => return Err(format!("Cartridge has invalid ROM size code? Code: {:#04X}", header.rom_size_code))
     };
 
-    let num_ram_banks: u16 = match ram_size_code {
-        0x00 => 0,
-        0x02 => 1,
-        0x03 => 4,
-        0x04 => 16,
-        0x05 => 8,
+    let num_ram_banks = match header.num_ram_banks() {
+        Some(num_ram_banks) => num_ram_banks,
+        None => return Err(format!("Cartridge has invalid RAM size code? Code: {:#04X}", header.ram_size_code))
+    };
 
-        _ => panic!("Cartridge has invalid RAM size code? Code: {:#04X}", ram_size_code)
+    let save_storage = match options.save_storage {
+        Some(save_storage) => save_storage,
+        None => {
+            let rom_dir = path.parent().unwrap_or(Path::new(""));
+            Box::new(FileSaveStorage::new(rom_dir))
+        }
     };
 
-    match cartridge_type_code {
-        0x00 => Box::new(ROM::new(file, rom_bank_0)),
+    let save_file = SaveFile::new(
+        save_storage,
+        format!("{}.sav", loaded_rom.file_stem()),
+        header.has_battery()
+    );
+
+    let cartridge: Box<dyn Cartridge> = match cartridge_type_code {
+        0x00 => Box::new(ROM::new(rom)),
         
         0x01 | 0x02 | 0x03 => {
             println!("MBC1 cart created!");
             Box::new(MBC1::new(
-                file,
-                path,
-                rom_bank_0,
-                cartridge_type_code, 
+                rom,
+                save_file,
                 num_rom_banks, 
                 num_ram_banks
             ))
@@ -91,10 +141,8 @@ pub fn create(rom_path: &str) -> Box<dyn Cartridge> {
         0x0F..=0x13 => {
             println!("MBC3 cart created!");
             Box::new(MBC3::new(
-                file,
-                path,
-                rom_bank_0,
-                cartridge_type_code,
+                rom,
+                save_file,
                 num_rom_banks,
                 num_ram_banks
             ))
@@ -103,74 +151,112 @@ pub fn create(rom_path: &str) -> Box<dyn Cartridge> {
         0x1A..=0x1E => {
             println!("MBC5 cart created!");
             Box::new(MBC5::new(
-                file,
-                path,
-                rom_bank_0,
-                cartridge_type_code,
+                rom,
+                save_file,
                 num_rom_banks,
                 num_ram_banks
             ))
         }
 
-        _ => unimplemented!("Unable to handle cartridge type: {:#04X}", cartridge_type_code)
+        _ => return Err(format!("Unable to handle cartridge type: {:#04X}", cartridge_type_code))
+    };
+
+    Ok((cartridge, header, loaded_rom.file_stem().to_owned()))
+}
+
+fn patch_rom(rom: &[u8], patch_path: &Path) -> Result<Vec<u8>, String> {
+    let patch = fs::read(patch_path).map_err(|err| err.to_string())?;
+    let patched_rom = apply_patch(rom, &patch).map_err(|err| err.to_string())?;
+
+    println!("Applied patch {}", patch_path.display());
+    Ok(patched_rom)
+}
+
+// Splits the rom up into 16KB banks. Missing data (the rom is smaller than
+// the header says) is left as 0.
+fn split_rom_banks(rom: &[u8], num_rom_banks: u16) -> Vec<[u8; 0x4000]> {
+    let mut rom_banks = vec![[0; 0x4000]; num_rom_banks as usize];
+    for (bank, data) in rom_banks.iter_mut().zip(rom.chunks(0x4000)) {
+        bank[..data.len()].copy_from_slice(data);
     }
+
+    rom_banks
 }
 
-fn get_save_file_path_from_rom_path(path: &Path) -> PathBuf {
-    let mut save_file_path = PathBuf::from(path);
-    save_file_path.pop();
-    let rom_name = path.file_name().unwrap().to_str().unwrap();
-    let mut save_name = rom_name[..3].to_owned();
-    save_name.push_str(".sav");
-    save_file_path.push(save_name);
-    save_file_path
+// Handles loading/storing the ram banks of a cartridge. Carts without a
+// battery never get written out.
+pub struct SaveFile {
+    storage: Box<dyn SaveStorage>,
+    name: String,
+    has_battery: bool,
+    dirty: bool
 }
 
-fn try_read_save_file(sav_file: Result<File, Error>, num_ram_banks: u16, ram_banks: &mut Vec<[u8; 0x2000]>,) {
-    match sav_file {
-        Ok(mut file) => {
-            let mut buf: Vec<u8> = Vec::new();
-            let read_result = file.read_to_end(&mut buf);
-            match read_result {
-                Ok(_) => {
-                    let bytes_read = buf.len();
-                    if bytes_read != num_ram_banks as usize * 0x2000 {
-                        println!(
-                            "Save file was an unexpected length. Expected {}, actual: {}",
-                            num_ram_banks as usize * 0x2000,
-                            bytes_read
-                        );
-                    }
-                    else {
-                        // load save file
-                        load_new_ram(ram_banks, num_ram_banks);
-                        let mut index: usize = 0;
-                        for bank in ram_banks {
-                            for i in 0..0x2000 {
-                                bank[i] = buf[index];
-                                index += 1;
-                            }
-                        }
-                        println!("Save file loaded!");
-                    }
-                }
+impl SaveFile {
+    pub fn new(storage: Box<dyn SaveStorage>, name: String, has_battery: bool) -> Self {
+        Self {
+            storage,
+            name,
+            has_battery,
+            dirty: false
+        }
+    }
 
-                Err(_) => {
-                    load_new_ram(ram_banks, num_ram_banks);
-                }
-            }
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn load_ram_banks(&mut self, num_ram_banks: u16) -> Vec<[u8; 0x2000]> {
+        let mut ram_banks = new_ram_banks(num_ram_banks);
+        if !self.has_battery { return ram_banks }
+
+        let buf = match self.storage.load(&self.name) {
+            Some(buf) => buf,
+            None => return ram_banks
+        };
+
+        if buf.len() != num_ram_banks as usize * 0x2000 {
+            println!(
+                "Save file was an unexpected length. Expected {}, actual: {}",
+                num_ram_banks as usize * 0x2000,
+                buf.len()
+            );
+
+            return ram_banks;
+        }
+
+        for (bank, data) in ram_banks.iter_mut().zip(buf.chunks_exact(0x2000)) {
+            bank.copy_from_slice(data);
+        }
+
+        println!("Save file loaded!");
+        ram_banks
+    }
+
+    // quiet about it when it works, this runs for every autosave
+    pub fn flush(&mut self, ram_banks: &[[u8; 0x2000]]) -> bool {
+        if !self.has_battery || !self.dirty { return false }
+
+        let mut buf = Vec::with_capacity(ram_banks.len() * 0x2000);
+        for bank in ram_banks {
+            buf.extend_from_slice(bank);
         }
 
-        Err(_) => {
-            load_new_ram(ram_banks, num_ram_banks);
+        match self.storage.store(&self.name, &buf) {
+            Ok(_) => {
+                self.dirty = false;
+                true
+            }
+
+            Err(err) => {
+                println!("Unable to write save file {}: {}", self.name, err);
+                false
+            }
         }
     }
 }
 
-fn load_new_ram(ram_banks: &mut Vec<[u8; 0x2000]>, num_ram_banks: u16) {
+fn new_ram_banks(num_ram_banks: u16) -> Vec<[u8; 0x2000]> {
     // fill ram banks with blank memory
-    for _ in 0..num_ram_banks {
-        let bank = [0; 0x2000];
-        ram_banks.push(bank);
-    }
+    vec![[0; 0x2000]; num_ram_banks as usize]
 }
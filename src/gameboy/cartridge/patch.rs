@@ -0,0 +1,335 @@
+use std::{fmt, path::{Path, PathBuf}};
+
+use flate2::Crc;
+
+// IPS: https://zerosoft.zophar.net/ips.php
+// UPS: https://www.romhacking.net/documents/392/
+// BPS: https://www.romhacking.net/documents/746/
+
+pub const PATCH_EXTENSIONS: [&str; 3] = ["ips", "ups", "bps"];
+
+#[derive(Debug, PartialEq)]
+pub enum PatchError {
+    UnknownFormat,
+    Truncated,
+    Invalid(&'static str),
+    SourceSizeMismatch { expected: usize, actual: usize },
+    SourceChecksumMismatch { expected: u32, actual: u32 },
+    TargetChecksumMismatch { expected: u32, actual: u32 },
+    PatchChecksumMismatch { expected: u32, actual: u32 }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "not an IPS, UPS or BPS patch"),
+            PatchError::Truncated => write!(f, "patch ends unexpectedly"),
+            PatchError::Invalid(reason) => write!(f, "invalid patch: {}", reason),
+            PatchError::SourceSizeMismatch { expected, actual } =>
+                write!(f, "patch is for a {} byte rom, this rom is {} bytes", expected, actual),
+            PatchError::SourceChecksumMismatch { expected, actual } =>
+                write!(f, "patch is for a different rom (expected crc32 {:08X}, rom is {:08X})", expected, actual),
+            PatchError::TargetChecksumMismatch { expected, actual } =>
+                write!(f, "patched rom has the wrong checksum (expected crc32 {:08X}, got {:08X})", expected, actual),
+            PatchError::PatchChecksumMismatch { expected, actual } =>
+                write!(f, "patch is corrupt (expected crc32 {:08X}, got {:08X})", expected, actual)
+        }
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+// Looks for game.ips/ups/bps next to game.gb
+pub fn find_patch_for_rom(rom_path: &Path) -> Option<PathBuf> {
+    PATCH_EXTENSIONS.iter()
+        .map(|ext| rom_path.with_extension(ext))
+        .find(|patch_path| patch_path.is_file())
+}
+
+// Works out the patch format from the magic bytes at the start
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(b"PATCH") {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(b"UPS1") {
+        apply_ups(rom, patch)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = PatchReader::new(patch);
+    if reader.read_slice(5)? != b"PATCH" {
+        return Err(PatchError::UnknownFormat);
+    }
+
+    let mut target = rom.to_vec();
+    loop {
+        let record = reader.read_slice(3)?;
+        if record == b"EOF" {
+            break;
+        }
+
+        let offset = ((record[0] as usize) << 16) | ((record[1] as usize) << 8) | record[2] as usize;
+        let size = reader.read_u16_be()? as usize;
+
+        // size 0 means it's an RLE record, a run of the same byte
+        if size == 0 {
+            let run_size = reader.read_u16_be()? as usize;
+            let value = reader.read_u8()?;
+            resize_for_write(&mut target, offset + run_size);
+            target[offset..offset + run_size].iter_mut().for_each(|b| *b = value);
+        } else {
+            let data = reader.read_slice(size)?;
+            resize_for_write(&mut target, offset + size);
+            target[offset..offset + size].copy_from_slice(data);
+        }
+    }
+
+    // some patchers add a truncation offset after the EOF marker
+    if reader.remaining() >= 3 {
+        let truncate = reader.read_slice(3)?;
+        let size = ((truncate[0] as usize) << 16) | ((truncate[1] as usize) << 8) | truncate[2] as usize;
+        target.truncate(size);
+    }
+
+    Ok(target)
+}
+
+pub fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let footer = check_footer(patch, b"UPS1")?;
+
+    let mut reader = PatchReader::new(&patch[..patch.len() - 12]);
+    reader.read_slice(4)?;
+
+    let source_size = reader.read_varint()?;
+    let target_size = reader.read_varint()?;
+    check_source(rom, source_size, footer.source_crc)?;
+
+    let mut target = vec![0; target_size];
+    let copy_size = source_size.min(target_size);
+    target[..copy_size].copy_from_slice(&rom[..copy_size]);
+
+    // each hunk skips ahead some bytes and then xors the rom with the patch until a 0
+    let mut offset = 0;
+    while reader.remaining() > 0 {
+        offset += reader.read_varint()?;
+
+        loop {
+            let xor = reader.read_u8()?;
+            if xor == 0 {
+                break;
+            }
+
+            if offset >= target_size {
+                return Err(PatchError::Invalid("hunk writes past the end of the rom"));
+            }
+
+            let source_byte = rom.get(offset).copied().unwrap_or(0);
+            target[offset] = source_byte ^ xor;
+            offset += 1;
+        }
+
+        // the terminating 0 counts as a byte too
+        offset += 1;
+    }
+
+    check_target(&target, footer.target_crc)?;
+    Ok(target)
+}
+
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let footer = check_footer(patch, b"BPS1")?;
+
+    let mut reader = PatchReader::new(&patch[..patch.len() - 12]);
+    reader.read_slice(4)?;
+
+    let source_size = reader.read_varint()?;
+    let target_size = reader.read_varint()?;
+    let metadata_size = reader.read_varint()?;
+    reader.read_slice(metadata_size)?;
+    check_source(rom, source_size, footer.source_crc)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+
+    while reader.remaining() > 0 {
+        let data = reader.read_varint()?;
+        let length = (data >> 2) + 1;
+
+        match data & 3 {
+            // SourceRead: copy from the same position in the rom
+            0 => {
+                let start = target.len();
+                let bytes = rom.get(start..start + length).ok_or(PatchError::Invalid("source read out of bounds"))?;
+                target.extend_from_slice(bytes);
+            }
+
+            // TargetRead: copy straight out of the patch
+            1 => target.extend_from_slice(reader.read_slice(length)?),
+
+            // SourceCopy: copy from anywhere in the rom
+            2 => {
+                source_offset = offset_by(source_offset, reader.read_varint()?)?;
+                let bytes = rom.get(source_offset..source_offset + length).ok_or(PatchError::Invalid("source copy out of bounds"))?;
+                target.extend_from_slice(bytes);
+                source_offset += length;
+            }
+
+            // TargetCopy: copy from what's been written so far, byte by byte
+            // since the ranges are allowed to overlap
+            _ => {
+                target_offset = offset_by(target_offset, reader.read_varint()?)?;
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or(PatchError::Invalid("target copy out of bounds"))?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+
+        if target.len() > target_size {
+            return Err(PatchError::Invalid("patch writes past the end of the rom"));
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(PatchError::Invalid("patched rom is the wrong size"));
+    }
+
+    check_target(&target, footer.target_crc)?;
+    Ok(target)
+}
+
+struct Footer {
+    source_crc: u32,
+    target_crc: u32
+}
+
+// UPS and BPS both end with the crc32s of the source, target and the patch itself
+fn check_footer(patch: &[u8], magic: &[u8]) -> Result<Footer, PatchError> {
+    if !patch.starts_with(magic) {
+        return Err(PatchError::UnknownFormat);
+    }
+
+    if patch.len() < magic.len() + 12 {
+        return Err(PatchError::Truncated);
+    }
+
+    let read_u32_le = |offset: usize| {
+        u32::from_le_bytes([patch[offset], patch[offset + 1], patch[offset + 2], patch[offset + 3]])
+    };
+
+    let footer_start = patch.len() - 12;
+    let patch_crc = read_u32_le(footer_start + 8);
+    let actual_patch_crc = crc32(&patch[..footer_start + 8]);
+    if patch_crc != actual_patch_crc {
+        return Err(PatchError::PatchChecksumMismatch { expected: patch_crc, actual: actual_patch_crc });
+    }
+
+    Ok(Footer {
+        source_crc: read_u32_le(footer_start),
+        target_crc: read_u32_le(footer_start + 4)
+    })
+}
+
+fn check_source(rom: &[u8], source_size: usize, source_crc: u32) -> Result<(), PatchError> {
+    if rom.len() != source_size {
+        return Err(PatchError::SourceSizeMismatch { expected: source_size, actual: rom.len() });
+    }
+
+    let actual = crc32(rom);
+    if actual != source_crc {
+        return Err(PatchError::SourceChecksumMismatch { expected: source_crc, actual });
+    }
+
+    Ok(())
+}
+
+fn check_target(target: &[u8], target_crc: u32) -> Result<(), PatchError> {
+    let actual = crc32(target);
+    if actual != target_crc {
+        return Err(PatchError::TargetChecksumMismatch { expected: target_crc, actual });
+    }
+
+    Ok(())
+}
+
+fn resize_for_write(target: &mut Vec<u8>, end: usize) {
+    if target.len() < end {
+        target.resize(end, 0);
+    }
+}
+
+// BPS relative offsets, lowest bit is the sign
+fn offset_by(offset: usize, data: usize) -> Result<usize, PatchError> {
+    let delta = data >> 1;
+    if data & 1 != 0 {
+        offset.checked_sub(delta).ok_or(PatchError::Invalid("relative offset before the start of the rom"))
+    } else {
+        Ok(offset + delta)
+    }
+}
+
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        if self.remaining() < len {
+            return Err(PatchError::Truncated);
+        }
+
+        let slice = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, PatchError> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    fn read_u16_be(&mut self) -> Result<u16, PatchError> {
+        let bytes = self.read_slice(2)?;
+        Ok(((bytes[0] as u16) << 8) | bytes[1] as u16)
+    }
+
+    // UPS/BPS variable length numbers
+    fn read_varint(&mut self) -> Result<usize, PatchError> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.read_u8()?;
+            value = ((byte & 0x7F) as usize).checked_mul(shift)
+                .and_then(|part| value.checked_add(part))
+                .ok_or(PatchError::Invalid("number too large"))?;
+
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+
+            shift = shift.checked_mul(0x80).ok_or(PatchError::Invalid("number too large"))?;
+            value = value.checked_add(shift).ok_or(PatchError::Invalid("number too large"))?;
+        }
+    }
+}
@@ -1,6 +1,4 @@
-use std::{fs::File, io::Read};
-
-use super::Cartridge;
+use super::{Cartridge, split_rom_banks};
 
 pub struct ROM {
     rom_bank_0: [u8; 0x4000],
@@ -8,13 +6,12 @@ pub struct ROM {
 }
 
 impl ROM {
-    pub fn new(mut file: File, rom_bank_0: [u8; 0x4000]) -> Self {
-        let mut rom_bank_1: [u8; 0x4000] = [0; 0x4000];
-        file.read_exact(&mut rom_bank_1).ok();
+    pub fn new(rom: &[u8]) -> Self {
+        let rom_banks = split_rom_banks(rom, 2);
 
         Self {
-            rom_bank_0,
-            rom_bank_1
+            rom_bank_0: rom_banks[0],
+            rom_bank_1: rom_banks[1]
         }
     }
 }
@@ -38,13 +35,29 @@ impl Cartridge for ROM {
         // NOP
     }
 
-    // This cart has no ram?
+    // This cart has no ram, so it's open bus
 
     fn read_ram(&self, _addr: u16) -> u8 {
-        0
+        0xFF
     }
 
     fn write_ram(&mut self, _addr: u16, _value: u8) {
         
     }
-}
\ No newline at end of file
+
+    fn peek_rom(&self, bank: usize, addr: u16) -> u8 {
+        match bank {
+            0 => self.rom_bank_0[addr as usize],
+            1 => self.rom_bank_1[addr as usize],
+            _ => 0xFF
+        }
+    }
+
+    fn poke_rom(&mut self, bank: usize, addr: u16, value: u8) {
+        match bank {
+            0 => self.rom_bank_0[addr as usize] = value,
+            1 => self.rom_bank_1[addr as usize] = value,
+            _ => { }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{cell::RefCell, collections::HashMap, fs::{self, File}, io::{self, Read, Write}, path::PathBuf, rc::Rc};
+
+// Where battery backed cartridge ram ends up. Saves are identified by name
+// (the rom's file stem + ".sav"), it's up to the storage where that goes.
+pub trait SaveStorage {
+    fn load(&mut self, name: &str) -> Option<Vec<u8>>;
+    fn store(&mut self, name: &str, data: &[u8]) -> io::Result<()>;
+}
+
+pub struct FileSaveStorage {
+    save_dir: PathBuf
+}
+
+impl FileSaveStorage {
+    pub fn new<P: Into<PathBuf>>(save_dir: P) -> Self {
+        Self {
+            save_dir: save_dir.into()
+        }
+    }
+}
+
+impl SaveStorage for FileSaveStorage {
+    fn load(&mut self, name: &str) -> Option<Vec<u8>> {
+        let mut file = File::open(self.save_dir.join(name)).ok()?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).ok()?;
+        Some(buf)
+    }
+
+    fn store(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.save_dir)?;
+
+        // write everything to a temp file first and then swap it in, so a crash
+        // half way through a write can't leave us with a truncated save
+        let save_path = self.save_dir.join(name);
+        let tmp_path = self.save_dir.join(format!("{}.tmp", name));
+
+        {
+            let mut tmp_file = File::create(&tmp_path)?;
+            tmp_file.write_all(data)?;
+            tmp_file.sync_all()?;
+        }
+
+        fs::rename(&tmp_path, &save_path)
+    }
+}
+
+// Keeps saves around in memory only. Clones share the same saves, so a copy
+// can be kept around to look at what the cartridge wrote.
+#[derive(Clone, Default)]
+pub struct MemorySaveStorage {
+    saves: Rc<RefCell<HashMap<String, Vec<u8>>>>
+}
+
+impl MemorySaveStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Vec<u8>> {
+        self.saves.borrow().get(name).cloned()
+    }
+
+    pub fn insert(&self, name: &str, data: Vec<u8>) {
+        self.saves.borrow_mut().insert(name.to_owned(), data);
+    }
+}
+
+impl SaveStorage for MemorySaveStorage {
+    fn load(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.get(name)
+    }
+
+    fn store(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.insert(name, data.to_vec());
+        Ok(())
+    }
+}
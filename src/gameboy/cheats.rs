@@ -0,0 +1,251 @@
+use std::{fmt, fs, io, path::Path};
+
+// Game Genie: ABC-DEF or ABC-DEF-GHI, patches reads from the rom
+//   AB  = new value
+//   FCDE = address, with F xored by 0xF
+//   GI  = compare value, rotated right by 2 and xored by 0xBA. H is unused
+//
+// GameShark: TTVVLLHH, writes VV to HHLL once per frame
+//   TT = 01 writes to whatever cart ram bank is switched in,
+//        8X writes to cart ram bank X
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheatCode {
+    GameGenie { addr: u16, value: u8, compare: Option<u8> },
+    GameShark { ram_bank: Option<u8>, addr: u16, value: u8 }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CheatError {
+    InvalidCode(String),
+    InvalidAddress(u16),
+    UnsupportedType(u8)
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheatError::InvalidCode(code) => write!(f, "{} is not a Game Genie or GameShark code", code),
+            CheatError::InvalidAddress(addr) => write!(f, "code can't be applied to address {:#06X}", addr),
+            CheatError::UnsupportedType(code_type) => write!(f, "GameShark code type {:02X} is not supported", code_type)
+        }
+    }
+}
+
+impl CheatCode {
+    pub fn parse(code: &str) -> Result<Self, CheatError> {
+        let digits: Option<Vec<u8>> = code.chars()
+            .filter(|c| *c != '-')
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect();
+
+        let digits = match digits {
+            Some(digits) => digits,
+            None => return Err(CheatError::InvalidCode(code.to_owned()))
+        };
+
+        match digits.len() {
+            6 | 9 => Self::parse_game_genie(&digits),
+            8 => Self::parse_game_shark(&digits),
+            _ => Err(CheatError::InvalidCode(code.to_owned()))
+        }
+    }
+
+    fn parse_game_genie(d: &[u8]) -> Result<Self, CheatError> {
+        let value = (d[0] << 4) | d[1];
+        let addr = (((d[5] ^ 0xF) as u16) << 12) | ((d[2] as u16) << 8) | ((d[3] as u16) << 4) | d[4] as u16;
+
+        // game genie can only patch the rom
+        if addr >= 0x8000 {
+            return Err(CheatError::InvalidAddress(addr));
+        }
+
+        let compare = if d.len() == 9 {
+            Some(((d[6] << 4) | d[8]).rotate_right(2) ^ 0xBA)
+        } else {
+            None
+        };
+
+        Ok(CheatCode::GameGenie { addr, value, compare })
+    }
+
+    fn parse_game_shark(d: &[u8]) -> Result<Self, CheatError> {
+        let code_type = (d[0] << 4) | d[1];
+        let value = (d[2] << 4) | d[3];
+        let addr = ((((d[6] << 4) | d[7]) as u16) << 8) | ((d[4] << 4) | d[5]) as u16;
+
+        let ram_bank = match code_type {
+            0x00 | 0x01 => None,
+            0x80..=0x8F => {
+                if !(0xA000..=0xBFFF).contains(&addr) {
+                    return Err(CheatError::InvalidAddress(addr));
+                }
+
+                Some(code_type & 0x0F)
+            }
+
+            _ => return Err(CheatError::UnsupportedType(code_type))
+        };
+
+        if addr < 0x8000 {
+            return Err(CheatError::InvalidAddress(addr));
+        }
+
+        Ok(CheatCode::GameShark { ram_bank, addr, value })
+    }
+}
+
+// A named cheat, made up of one or more codes (separated by + or spaces)
+#[derive(Clone)]
+pub struct Cheat {
+    pub name: String,
+    pub code: String,
+    pub enabled: bool,
+    codes: Vec<CheatCode>
+}
+
+impl Cheat {
+    pub fn new(name: &str, code: &str) -> Result<Self, CheatError> {
+        let parts: Vec<String> = code.split(|c: char| c == '+' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.to_ascii_uppercase())
+            .collect();
+
+        if parts.is_empty() {
+            return Err(CheatError::InvalidCode(code.to_owned()));
+        }
+
+        let codes = parts.iter()
+            .map(|part| CheatCode::parse(part))
+            .collect::<Result<Vec<CheatCode>, CheatError>>()?;
+
+        Ok(Self {
+            name: name.trim().replace('\t', " "),
+            code: parts.join("+"),
+            enabled: true,
+            codes
+        })
+    }
+
+    pub fn codes(&self) -> &[CheatCode] {
+        &self.codes
+    }
+}
+
+#[derive(Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+
+    // enabled game genie codes, kept separately since they get
+    // checked on every single rom read
+    game_genie_codes: Vec<(u16, u8, Option<u8>)>
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn add(&mut self, name: &str, code: &str) -> Result<(), CheatError> {
+        let cheat = Cheat::new(name, code)?;
+        self.cheats.push(cheat);
+        self.update_game_genie_codes();
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.cheats.len() {
+            self.cheats.remove(index);
+            self.update_game_genie_codes();
+        }
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.enabled = enabled;
+            self.update_game_genie_codes();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cheats.clear();
+        self.update_game_genie_codes();
+    }
+
+    fn update_game_genie_codes(&mut self) {
+        self.game_genie_codes = self.enabled_codes()
+            .filter_map(|code| match code {
+                CheatCode::GameGenie { addr, value, compare } => Some((addr, value, compare)),
+                _ => None
+            })
+            .collect();
+    }
+
+    fn enabled_codes(&self) -> impl Iterator<Item = CheatCode> + '_ {
+        self.cheats.iter()
+            .filter(|cheat| cheat.enabled)
+            .flat_map(|cheat| cheat.codes.iter().copied())
+    }
+
+    pub fn patch_rom_read(&self, addr: u16, value: u8) -> u8 {
+        for (code_addr, new_value, compare) in &self.game_genie_codes {
+            if *code_addr == addr && (compare.is_none() || *compare == Some(value)) {
+                return *new_value;
+            }
+        }
+
+        value
+    }
+
+    // (ram bank, address, value) for every enabled gameshark code
+    pub fn game_shark_writes(&self) -> Vec<(Option<u8>, u16, u8)> {
+        self.enabled_codes()
+            .filter_map(|code| match code {
+                CheatCode::GameShark { ram_bank, addr, value } => Some((ram_bank, addr, value)),
+                _ => None
+            })
+            .collect()
+    }
+
+    // Cheat files have one cheat per line: enabled (0/1), code and name separated by tabs
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut cheats = Self::new();
+
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.splitn(3, '\t');
+            let enabled = fields.next() == Some("1");
+            let code = fields.next().unwrap_or("");
+            let name = fields.next().unwrap_or("");
+
+            match Cheat::new(name, code) {
+                Ok(mut cheat) => {
+                    cheat.enabled = enabled;
+                    cheats.cheats.push(cheat);
+                }
+
+                Err(err) => println!("Skipping cheat \"{}\": {}", name, err)
+            }
+        }
+
+        cheats.update_game_genie_codes();
+        Ok(cheats)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut contents = String::new();
+        for cheat in &self.cheats {
+            contents.push_str(&format!("{}\t{}\t{}\n", if cheat.enabled { 1 } else { 0 }, cheat.code, cheat.name));
+        }
+
+        fs::write(path, contents)
+    }
+}
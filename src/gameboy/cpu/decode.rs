@@ -0,0 +1,346 @@
+use std::fmt;
+
+// Turns bytes into instructions without running anything, unlike
+// disassembler::disassemble which builds the steps the cpu executes.
+// Text comes out in RGBDS syntax, e.g. `ld a, [$C000]` or `bit 7, [hl]`.
+//
+// Uses the same decoding as the disassembler:
+// https://gb-archive.github.io/salvage/decoding_gbz80_opcodes/Decoding%20Gamboy%20Z80%20Opcodes.html
+
+const R: [&str; 8] = ["b", "c", "d", "e", "h", "l", "hl", "a"];
+const RP: [&str; 4] = ["bc", "de", "hl", "sp"];
+const RP2: [&str; 4] = ["bc", "de", "hl", "af"];
+const CC: [&str; 4] = ["nz", "z", "nc", "c"];
+const ROT: [&str; 8] = ["rlc", "rrc", "rl", "rr", "sla", "sra", "swap", "srl"];
+
+// Anywhere instructions can be read from: the gameboy's memory, a rom file...
+pub trait MemoryView {
+    fn read(&self, addr: u16) -> u8;
+}
+
+// a plain slice is treated as starting at address 0
+impl MemoryView for [u8] {
+    fn read(&self, addr: u16) -> u8 {
+        self.get(addr as usize).copied().unwrap_or(0)
+    }
+}
+
+impl MemoryView for Vec<u8> {
+    fn read(&self, addr: u16) -> u8 {
+        self.as_slice().read(addr)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operand {
+    Register(&'static str),
+    Condition(&'static str),
+
+    // [hl], [bc], [hl+], [c]...
+    Indirect(&'static str),
+    Imm8(u8),
+    Imm16(u16),
+
+    // [$C000]
+    Address(u16),
+
+    // ldh [$FF40], a
+    HighAddress(u8),
+
+    // jr/jp/call destination
+    Target(u16),
+
+    // add sp, -2
+    Signed(i8),
+
+    // ld hl, sp+$02
+    SpOffset(i8),
+    Bit(u8),
+    Rst(u8)
+}
+
+impl Operand {
+    // the address this operand refers to, for swapping in a label
+    pub fn address(&self) -> Option<u16> {
+        match *self {
+            Operand::Address(addr) | Operand::Target(addr) => Some(addr),
+            Operand::HighAddress(addr) => Some(0xFF00 | addr as u16),
+            Operand::Imm16(value) => Some(value),
+            _ => None
+        }
+    }
+
+    // formats the operand, using name in place of the address if there is one
+    pub fn format_with_name(&self, name: Option<&str>) -> String {
+        match (self, name) {
+            (Operand::Address(_), Some(name)) | (Operand::HighAddress(_), Some(name)) => format!("[{}]", name),
+            (Operand::Target(_), Some(name)) | (Operand::Imm16(_), Some(name)) => name.to_owned(),
+            _ => self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Operand::Register(name) | Operand::Condition(name) => write!(f, "{}", name),
+            Operand::Indirect(name) => write!(f, "[{}]", name),
+            Operand::Imm8(value) => write!(f, "${:02X}", value),
+            Operand::Imm16(value) | Operand::Target(value) => write!(f, "${:04X}", value),
+            Operand::Address(addr) => write!(f, "[${:04X}]", addr),
+            Operand::HighAddress(addr) => write!(f, "[$FF{:02X}]", addr),
+            Operand::Signed(value) => write!(f, "{}", value),
+            Operand::SpOffset(value) if value < 0 => write!(f, "sp-${:02X}", (value as i16).abs()),
+            Operand::SpOffset(value) => write!(f, "sp+${:02X}", value),
+            Operand::Bit(bit) => write!(f, "{}", bit),
+            Operand::Rst(addr) => write!(f, "${:02X}", addr)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedInstr {
+    pub addr: u16,
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+
+    // in bytes
+    pub length: u16,
+
+    // clock cycles, and how many if a conditional branch is taken
+    pub cycles: u8,
+    pub branch_cycles: Option<u8>,
+
+    // where jumps, calls and rsts can go (not including the next instruction)
+    pub branch_targets: Vec<u16>
+}
+
+impl DecodedInstr {
+    pub fn is_illegal(&self) -> bool {
+        self.mnemonic == "db"
+    }
+
+    // formats the instruction, asking name_for for a name for each address operand
+    pub fn format_with_names<F: Fn(u16) -> Option<String>>(&self, name_for: F) -> String {
+        let operands: Vec<String> = self.operands.iter()
+            .map(|operand| {
+                let name = operand.address().and_then(&name_for);
+                operand.format_with_name(name.as_deref())
+            })
+            .collect();
+
+        if operands.is_empty() {
+            self.mnemonic.to_owned()
+        } else {
+            format!("{} {}", self.mnemonic, operands.join(", "))
+        }
+    }
+}
+
+impl fmt::Display for DecodedInstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format_with_names(|_| None))
+    }
+}
+
+// Decodes the instruction at the start of bytes, which came from addr (needed
+// for relative jumps). Missing bytes are read as 0.
+pub fn decode(bytes: &[u8], addr: u16) -> DecodedInstr {
+    let byte = |i: usize| bytes.get(i).copied().unwrap_or(0);
+
+    let opcode = byte(0);
+    let n = byte(1);
+    let nn = ((byte(2) as u16) << 8) | n as u16;
+    let relative_target = addr.wrapping_add(2).wrapping_add(n as i8 as u16);
+
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 7;
+    let z = opcode & 7;
+    let p = (y >> 1) as usize;
+    let q = y & 1;
+
+    let mut instr = DecodedInstr {
+        addr,
+        opcode,
+        mnemonic: "nop",
+        operands: Vec::new(),
+        length: 1,
+        cycles: 4,
+        branch_cycles: None,
+        branch_targets: Vec::new()
+    };
+
+    // reading or writing [hl] takes an extra memory access
+    let r = |i: u8| if i == 6 { Operand::Indirect("hl") } else { Operand::Register(R[i as usize]) };
+    let hl_cycles = |i: u8, cycles: u8, hl: u8| if i == 6 { hl } else { cycles };
+
+    {
+        let mut set = |mnemonic: &'static str, operands: Vec<Operand>, length: u16, cycles: u8| {
+            instr.mnemonic = mnemonic;
+            instr.operands = operands;
+            instr.length = length;
+            instr.cycles = cycles;
+        };
+
+        match (x, z) {
+            (0, 0) => match y {
+                0 => set("nop", vec![], 1, 4),
+                1 => set("ld", vec![Operand::Address(nn), Operand::Register("sp")], 3, 20),
+                2 => set("stop", vec![], 2, 4),
+                3 => set("jr", vec![Operand::Target(relative_target)], 2, 12),
+                _ => set("jr", vec![Operand::Condition(CC[y as usize - 4]), Operand::Target(relative_target)], 2, 8)
+            },
+
+            (0, 1) if q == 0 => set("ld", vec![Operand::Register(RP[p]), Operand::Imm16(nn)], 3, 12),
+            (0, 1) => set("add", vec![Operand::Register("hl"), Operand::Register(RP[p])], 1, 8),
+
+            (0, 2) => {
+                let indirect = ["bc", "de", "hl+", "hl-"][p];
+                if q == 0 {
+                    set("ld", vec![Operand::Indirect(indirect), Operand::Register("a")], 1, 8)
+                } else {
+                    set("ld", vec![Operand::Register("a"), Operand::Indirect(indirect)], 1, 8)
+                }
+            }
+
+            (0, 3) => set(if q == 0 { "inc" } else { "dec" }, vec![Operand::Register(RP[p])], 1, 8),
+            (0, 4) => set("inc", vec![r(y)], 1, hl_cycles(y, 4, 12)),
+            (0, 5) => set("dec", vec![r(y)], 1, hl_cycles(y, 4, 12)),
+            (0, 6) => set("ld", vec![r(y), Operand::Imm8(n)], 2, hl_cycles(y, 8, 12)),
+            (0, _) => set(["rlca", "rrca", "rla", "rra", "daa", "cpl", "scf", "ccf"][y as usize], vec![], 1, 4),
+
+            (1, 6) if y == 6 => set("halt", vec![], 1, 4),
+            (1, _) => set("ld", vec![r(y), r(z)], 1, if y == 6 || z == 6 { 8 } else { 4 }),
+
+            (2, _) => {
+                let (mnemonic, operands) = alu(y, r(z));
+                set(mnemonic, operands, 1, hl_cycles(z, 4, 8))
+            }
+
+            (_, 0) => match y {
+                0..=3 => set("ret", vec![Operand::Condition(CC[y as usize])], 1, 8),
+                4 => set("ldh", vec![Operand::HighAddress(n), Operand::Register("a")], 2, 12),
+                5 => set("add", vec![Operand::Register("sp"), Operand::Signed(n as i8)], 2, 16),
+                6 => set("ldh", vec![Operand::Register("a"), Operand::HighAddress(n)], 2, 12),
+                _ => set("ld", vec![Operand::Register("hl"), Operand::SpOffset(n as i8)], 2, 12)
+            },
+
+            (_, 1) if q == 0 => set("pop", vec![Operand::Register(RP2[p])], 1, 12),
+            (_, 1) => match p {
+                0 => set("ret", vec![], 1, 16),
+                1 => set("reti", vec![], 1, 16),
+                2 => set("jp", vec![Operand::Register("hl")], 1, 4),
+                _ => set("ld", vec![Operand::Register("sp"), Operand::Register("hl")], 1, 8)
+            },
+
+            (_, 2) => match y {
+                0..=3 => set("jp", vec![Operand::Condition(CC[y as usize]), Operand::Target(nn)], 3, 12),
+                4 => set("ldh", vec![Operand::Indirect("c"), Operand::Register("a")], 1, 8),
+                5 => set("ld", vec![Operand::Address(nn), Operand::Register("a")], 3, 16),
+                6 => set("ldh", vec![Operand::Register("a"), Operand::Indirect("c")], 1, 8),
+                _ => set("ld", vec![Operand::Register("a"), Operand::Address(nn)], 3, 16)
+            },
+
+            (_, 3) => match y {
+                0 => set("jp", vec![Operand::Target(nn)], 3, 16),
+                1 => {
+                    let (mnemonic, operands, cycles) = decode_cb(n);
+                    set(mnemonic, operands, 2, cycles)
+                }
+                6 => set("di", vec![], 1, 4),
+                7 => set("ei", vec![], 1, 4),
+                _ => set("db", vec![Operand::Imm8(opcode)], 1, 4)
+            },
+
+            (_, 4) if y <= 3 => set("call", vec![Operand::Condition(CC[y as usize]), Operand::Target(nn)], 3, 12),
+            (_, 5) if q == 0 => set("push", vec![Operand::Register(RP2[p])], 1, 16),
+            (_, 5) if p == 0 => set("call", vec![Operand::Target(nn)], 3, 24),
+            (_, 4) | (_, 5) => set("db", vec![Operand::Imm8(opcode)], 1, 4),
+
+            (_, 6) => {
+                let (mnemonic, operands) = alu(y, Operand::Imm8(n));
+                set(mnemonic, operands, 2, 8)
+            }
+
+            (_, _) => set("rst", vec![Operand::Rst(y * 8)], 1, 16)
+        }
+    }
+
+    instr.branch_cycles = match opcode {
+        0x20 | 0x28 | 0x30 | 0x38 => Some(12),
+        0xC0 | 0xC8 | 0xD0 | 0xD8 => Some(20),
+        0xC2 | 0xCA | 0xD2 | 0xDA => Some(16),
+        0xC4 | 0xCC | 0xD4 | 0xDC => Some(24),
+        _ => None
+    };
+
+    instr.branch_targets = instr.operands.iter()
+        .filter_map(|operand| match operand {
+            Operand::Target(target) => Some(*target),
+            Operand::Rst(target) => Some(*target as u16),
+            _ => None
+        })
+        .collect();
+
+    instr
+}
+
+fn alu(y: u8, operand: Operand) -> (&'static str, Vec<Operand>) {
+    let a = Operand::Register("a");
+    match y {
+        0 => ("add", vec![a, operand]),
+        1 => ("adc", vec![a, operand]),
+        2 => ("sub", vec![operand]),
+        3 => ("sbc", vec![a, operand]),
+        4 => ("and", vec![operand]),
+        5 => ("xor", vec![operand]),
+        6 => ("or", vec![operand]),
+        _ => ("cp", vec![operand])
+    }
+}
+
+fn decode_cb(opcode: u8) -> (&'static str, Vec<Operand>, u8) {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 7;
+    let z = opcode & 7;
+
+    let operand = if z == 6 { Operand::Indirect("hl") } else { Operand::Register(R[z as usize]) };
+    let cycles = match (x, z) {
+        (1, 6) => 12,
+        (_, 6) => 16,
+        _ => 8
+    };
+
+    match x {
+        0 => (ROT[y as usize], vec![operand], cycles),
+        1 => ("bit", vec![Operand::Bit(y), operand], cycles),
+        2 => ("res", vec![Operand::Bit(y), operand], cycles),
+        _ => ("set", vec![Operand::Bit(y), operand], cycles)
+    }
+}
+
+// Reads and decodes the instruction at addr
+pub fn decode_at<M: MemoryView + ?Sized>(memory: &M, addr: u16) -> DecodedInstr {
+    let bytes = [
+        memory.read(addr),
+        memory.read(addr.wrapping_add(1)),
+        memory.read(addr.wrapping_add(2))
+    ];
+
+    decode(&bytes, addr)
+}
+
+// Every instruction starting between start and end (inclusive)
+pub fn disassemble_range<M: MemoryView + ?Sized>(memory: &M, start: u16, end: u16) -> Vec<DecodedInstr> {
+    let mut instructions = Vec::new();
+    let mut addr = start as u32;
+
+    while addr <= end as u32 {
+        let instr = decode_at(memory, addr as u16);
+        addr += instr.length as u32;
+        instructions.push(instr);
+    }
+
+    instructions
+}
@@ -27,7 +27,14 @@ pub enum InstructionStep {
     Instant(Box<dyn Fn(&mut Cpu)>)
 }
 
-pub(super) fn disassemble(opcode: u8) -> Instruction {
+// these don't do anything except hang the cpu
+const ILLEGAL_OPCODES: [u8; 11] = [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
+pub(crate) fn disassemble(opcode: u8) -> Instruction {
+    if ILLEGAL_OPCODES.contains(&opcode) {
+        return disassemble_illegal(opcode);
+    }
+
     let x = opcode >> 6;                // bits 6 - 7
     let y = (opcode & 0b00111000) >> 3; // bits 5 - 3
     let z = opcode & 0b00000111;        // bits 2 - 0
@@ -48,6 +55,21 @@ pub(super) fn disassemble(opcode: u8) -> Instruction {
     instruction 
 }
 
+fn disassemble_illegal(opcode: u8) -> Instruction {
+    let mut steps: VecDeque<InstructionStep> = VecDeque::new();
+    steps.push_back(InstructionStep::Standard(Box::new(|_cpu| { }))); // opcode fetch
+    steps.push_back(InstructionStep::Instant(Box::new(move |cpu: &mut Cpu| {
+        cpu.lock_up(opcode);
+    })));
+
+    Instruction {
+        opcode_val: opcode,
+        human_readable: format!("ILLEGAL {:#04X}", opcode),
+        length: 1,
+        steps
+    }
+}
+
 fn push_fetch_operand8_closure(queue: &mut VecDeque<InstructionStep>) {
     let step = InstructionStep::Standard(Box::new(|cpu: &mut Cpu| {
         cpu.operand8 = cpu.fetch();
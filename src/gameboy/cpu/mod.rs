@@ -1,18 +1,66 @@
-use std::{cell::RefCell, fmt, fs::File, io::Write, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+use std::{cell::RefCell, fmt, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
 use crate::gameboy::cpu::disassembler::disassemble_cb_prefix_op;
 
 use self::disassembler::{Instruction, InstructionStep, disassemble};
-use super::mmu::Mmu;
+use super::{events::EmulatorEvent, mmu::Mmu};
 
+pub mod decode;
 pub mod disassembler;
 
-enum Flag {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flag {
     Z = 0b10000000,
     N = 0b01000000, // N = last math op was subtract
     H = 0b00100000,
     C = 0b00010000
 }
 
+// A copy of the cpu registers, for debuggers and other tools
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Registers {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+
+    pub sp: u16,
+    pub pc: u16
+}
+
+impl Registers {
+    pub fn af(&self) -> u16 {
+        ((self.a as u16) << 8) | (self.f as u16)
+    }
+
+    pub fn bc(&self) -> u16 {
+        ((self.b as u16) << 8) | (self.c as u16)
+    }
+
+    pub fn de(&self) -> u16 {
+        ((self.d as u16) << 8) | (self.e as u16)
+    }
+
+    pub fn hl(&self) -> u16 {
+        ((self.h as u16) << 8) | (self.l as u16)
+    }
+
+    pub fn is_flag_set(&self, flag: Flag) -> bool {
+        self.f & (flag as u8) != 0
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, set: bool) {
+        if set {
+            self.f |= flag as u8;
+        } else {
+            self.f &= !(flag as u8);
+        }
+    }
+}
+
 pub struct Cpu {
     pub mmu: Rc<RefCell<Mmu>>,
 
@@ -35,6 +83,11 @@ pub struct Cpu {
 
     pub is_fetching: bool,
     instruction: Option<Instruction>,
+
+    // where the current instruction started, and if it was fetched this tick
+    instruction_pc: u16,
+    pub new_instruction_fetched: bool,
+
     machine_cycles_taken_for_current_step: u8,
 
     pub stopped: bool,
@@ -45,8 +98,9 @@ pub struct Cpu {
     ei_delay_cycles: u8,
 
     debug: bool,
-    pub start_log: bool,
-    log: Option<File>
+
+    // hung by an illegal opcode, nothing but a reset gets it going again
+    pub locked: bool
 }
 
 impl fmt::Debug for Cpu {
@@ -66,8 +120,6 @@ impl fmt::Debug for Cpu {
 
 impl Cpu {
     pub fn new(mmu: Rc<RefCell<Mmu>>) -> Self {
-        let file: Option<File> = None;
-
         Self {
             mmu,
 
@@ -90,6 +142,10 @@ impl Cpu {
 
             is_fetching: false,
             instruction: None,
+
+            instruction_pc: 0,
+            new_instruction_fetched: false,
+
             machine_cycles_taken_for_current_step: 0,
 
             stopped: false,
@@ -100,11 +156,73 @@ impl Cpu {
             ei_delay_cycles: 0,
 
             debug: true,
-            start_log: false,
-            log: file
+            locked: false
+        }
+    }
+
+    pub fn instruction_pc(&self) -> u16 {
+        self.instruction_pc
+    }
+
+    // Right after a fetch pc has already moved past the opcode, so report
+    // the start of the instruction instead
+    pub fn registers(&self) -> Registers {
+        let pc = if self.is_fetching && self.instruction.is_some() {
+            self.instruction_pc
+        } else {
+            self.pc
+        };
+
+        Registers {
+            a: self.a,
+            f: self.f,
+            b: self.b,
+            c: self.c,
+            d: self.d,
+            e: self.e,
+            h: self.h,
+            l: self.l,
+
+            sp: self.sp,
+            pc
         }
     }
 
+    pub fn set_registers(&mut self, registers: Registers) {
+        self.a = registers.a;
+        self.f = registers.f & 0xF0;
+        self.b = registers.b;
+        self.c = registers.c;
+        self.d = registers.d;
+        self.e = registers.e;
+        self.h = registers.h;
+        self.l = registers.l;
+        self.sp = registers.sp;
+
+        if self.is_fetching && self.instruction.is_some() {
+            if registers.pc != self.instruction_pc {
+                // swap the instruction we just fetched for the one at the new pc
+                self.pc = registers.pc;
+                self.instruction_pc = registers.pc;
+
+                let opcode = self.fetch();
+                self.instruction = Some(match opcode {
+                    0xCB => disassemble_cb_prefix_op(self.fetch()),
+                    _ => disassemble(opcode)
+                });
+            }
+        } else {
+            self.pc = registers.pc;
+        }
+    }
+
+    // An interrupt can take over from a fetched instruction right up until
+    // its first step runs, which is the next tick once this is true
+    pub fn fetch_completes_this_tick(&self) -> bool {
+        self.is_fetching && self.instruction.is_some() && !self.halted && !self.locked
+            && self.machine_cycles_taken_for_current_step == 3
+    }
+
     pub fn is_processing_instruction(&self) -> bool {
         self.instruction.is_some()
     }
@@ -477,9 +595,17 @@ impl Cpu {
         self.pc = pc;
     }
 
+    pub(super) fn lock_up(&mut self, opcode: u8) {
+        self.locked = true;
+        (*self.mmu).borrow().push_event(EmulatorEvent::CpuLocked { pc: self.instruction_pc, opcode });
+    }
+
     // CYCLE FUNCTIONS
 
     pub fn tick(&mut self) {
+        self.new_instruction_fetched = false;
+        if self.locked { return }
+
         if self.ei_delay {
             self.ei_delay_cycles -= 1;
 
@@ -507,6 +633,9 @@ impl Cpu {
 
         if self.instruction.is_none() {
             self.is_fetching = true;
+            self.instruction_pc = self.pc;
+            self.new_instruction_fetched = true;
+            self.mmu.borrow_mut().current_pc = self.pc;
             let opcode = self.fetch();
             
             {
@@ -526,37 +655,6 @@ impl Cpu {
                 _ => disassemble(opcode)
             };
 
-            if self.start_log {
-                if self.log.is_none() {
-                    self.log = Some(File::create("I:\\Dev\\gameboy-rs\\log.txt").unwrap());
-                }
-
-                let mut instr_human_readable = instruction.human_readable.clone();
-
-                if instr_human_readable.contains("u8") {
-                    let op8 = (*self.mmu).borrow().read_byte(self.pc);
-                    instr_human_readable = instr_human_readable.replace("u8", format!("{:#04X}", op8).as_ref());
-                }
-                else if instr_human_readable.contains("i8") {
-                    let op8 = (*self.mmu).borrow().read_byte(self.pc) as i8;
-                    instr_human_readable = instr_human_readable.replace("i8", format!("{:#04X}", op8).as_ref());
-                } 
-                else if instr_human_readable.contains("u16") {
-                    let op16 = (*self.mmu).borrow().read_word(self.pc);
-                    instr_human_readable = instr_human_readable.replace("u16", format!("{:#06X}", op16).as_ref());
-                }
-
-                
-                let s = format!("PC:{:#06X} OP:{:#04X} {}", self.pc - 1, opcode, instr_human_readable);
-                // println!("{}", s);
-                let ly = self.mmu.borrow_mut().io[0x44];
-                if self.log.is_some() {
-                    self.log.as_ref().unwrap().write(format!("{} \n", s).as_ref()).unwrap();
-                    self.log.as_ref().unwrap().write(format!("LY: {:#04X}, {:?} \n", ly, self).as_ref()).unwrap();
-                    self.log.as_ref().unwrap().write("\n".as_ref()).unwrap();
-                }
-            }
-
             self.machine_cycles_taken_for_current_step += 1;
             self.instruction = Some(instruction);
             return;
@@ -0,0 +1,114 @@
+use std::{any::Any, fmt::Write, time::{SystemTime, UNIX_EPOCH}};
+
+use super::{cartridge::header::CartridgeHeader, cpu::Registers, debugger::Debugger, mmu::Mmu};
+
+// Crash reports: what the cpu was doing (the debugger's execution history),
+// the registers and the io registers, written out when the core panics so
+// there's something to go on.
+
+const IO_NAMES: [(u16, &str); 17] = [
+    (0xFF00, "JOYP"), (0xFF04, "DIV"), (0xFF05, "TIMA"), (0xFF06, "TMA"), (0xFF07, "TAC"),
+    (0xFF0F, "IF"), (0xFF40, "LCDC"), (0xFF41, "STAT"), (0xFF42, "SCY"), (0xFF43, "SCX"),
+    (0xFF44, "LY"), (0xFF45, "LYC"), (0xFF47, "BGP"), (0xFF48, "OBP0"), (0xFF49, "OBP1"),
+    (0xFF4A, "WY"), (0xFF4B, "WX")
+];
+
+// the message from panic!, as given to catch_unwind
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("Unknown panic")
+    }
+}
+
+// frosty-crash-<unix time>.txt
+pub fn report_file_name() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    format!("frosty-crash-{}.txt", secs)
+}
+
+pub(super) fn format_report(
+    reason: &str,
+    header: &CartridgeHeader,
+    registers: Registers,
+    frames: u64,
+    mmu: &Mmu,
+    debugger: &Debugger
+) -> String {
+    let mut report = String::new();
+
+    // writing to a String can't fail
+    let _ = write_report(&mut report, reason, header, registers, frames, mmu, debugger);
+    report
+}
+
+fn write_report(
+    out: &mut String,
+    reason: &str,
+    header: &CartridgeHeader,
+    r: Registers,
+    frames: u64,
+    mmu: &Mmu,
+    debugger: &Debugger
+) -> std::fmt::Result {
+    writeln!(out, "Frosty crash report")?;
+    writeln!(out, "Reason: {}", reason)?;
+    writeln!(out, "ROM: {} (cartridge type {:#04X}, version {})", header.title, header.cartridge_type, header.version)?;
+    writeln!(out, "Cycle: {}, frame: {}", mmu.clock, frames)?;
+    writeln!(out)?;
+
+    writeln!(out, "Registers")?;
+    writeln!(
+        out, "  AF:{:04X} BC:{:04X} DE:{:04X} HL:{:04X} SP:{:04X} PC:{:04X}",
+        r.af(), r.bc(), r.de(), r.hl(), r.sp, r.pc
+    )?;
+    writeln!(out, "  ROM bank: {}, boot rom: {}", mmu.rom_bank_at(0x4000).unwrap_or(0), mmu.bios_enabled)?;
+    writeln!(out)?;
+
+    writeln!(out, "IO registers")?;
+    for (addr, name) in IO_NAMES.iter() {
+        writeln!(out, "  {:<5} ({:04X}) = {:02X}", name, addr, mmu.peek_byte(*addr))?;
+    }
+    writeln!(out, "  IE    (FFFF) = {:02X}", mmu.interupts.enable)?;
+    writeln!(out)?;
+
+    for row in (0xFF00..=0xFF70u16).step_by(16) {
+        write!(out, "  {:04X}:", row)?;
+        for addr in row..row + 16 {
+            write!(out, " {:02X}", mmu.peek_byte(addr))?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out)?;
+
+    let frames = debugger.call_stack().frames();
+    if !frames.is_empty() {
+        writeln!(out, "Call stack (innermost first)")?;
+        for frame in frames.iter().rev() {
+            let kind = if frame.interrupt { "interrupt" } else { "call" };
+            writeln!(out, "  {:02X}:{:04X} {} from {:04X}", frame.bank, frame.target, kind, frame.from)?;
+        }
+        writeln!(out)?;
+    }
+
+    let history = debugger.history();
+    writeln!(out, "Last {} instructions (oldest first)", history.len())?;
+    for entry in history.iter() {
+        let symbols = debugger.symbols();
+        let instr = entry.decode().format_with_names(|addr| {
+            let bank = if addr < 0x8000 { Some(entry.bank) } else { None };
+            symbols.name_at(bank, addr).map(|name| name.to_owned())
+        });
+
+        let r = &entry.registers;
+        writeln!(
+            out, "  {:>12}  {:02X}:{:04X}  {:<24} AF:{:04X} BC:{:04X} DE:{:04X} HL:{:04X} SP:{:04X}",
+            entry.cycle, entry.bank, entry.pc(), instr, r.af(), r.bc(), r.de(), r.hl(), r.sp
+        )?;
+    }
+
+    Ok(())
+}
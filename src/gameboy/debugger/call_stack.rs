@@ -0,0 +1,63 @@
+// Keeps track of calls by watching the stack pointer: a call, rst or interrupt
+// pushes a frame, and it's gone once sp moves back above where the return
+// address was pushed (a ret, or the game tidying the stack up itself).
+
+// the stack can get reset without returning, don't let it grow forever
+const MAX_DEPTH: usize = 256;
+
+const INTERRUPT_VECTORS: [u16; 5] = [0x40, 0x48, 0x50, 0x58, 0x60];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    // the instruction that made the call, or the one before an interrupt
+    pub from: u16,
+    pub target: u16,
+    pub bank: u16,
+    pub interrupt: bool,
+
+    // sp with the return address on the stack
+    sp: u16
+}
+
+#[derive(Default)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+    last_pc: u16,
+    last_sp: u16,
+    last_opcode: u8
+}
+
+impl CallStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // outermost first
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub(super) fn on_instruction(&mut self, pc: u16, bank: u16, sp: u16, opcode: u8) {
+        while let Some(frame) = self.frames.last() {
+            if sp <= frame.sp {
+                break;
+            }
+
+            self.frames.pop();
+        }
+
+        if sp == self.last_sp.wrapping_sub(2) {
+            let called = matches!(self.last_opcode, 0xC4 | 0xCC | 0xCD | 0xD4 | 0xDC | 0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF);
+            let pushed = matches!(self.last_opcode, 0xC5 | 0xD5 | 0xE5 | 0xF5);
+            let interrupt = !called && !pushed && INTERRUPT_VECTORS.contains(&pc);
+
+            if (called || interrupt) && self.frames.len() < MAX_DEPTH {
+                self.frames.push(Frame { from: self.last_pc, target: pc, bank, interrupt, sp });
+            }
+        }
+
+        self.last_pc = pc;
+        self.last_sp = sp;
+        self.last_opcode = opcode;
+    }
+}
@@ -0,0 +1,538 @@
+use std::fmt;
+
+use crate::gameboy::{cpu::{Flag, Registers}, debugger::memory::MemoryBanks, mmu::Mmu};
+
+// Expressions for breakpoint conditions and log-points, e.g.
+//   a == 0x3C && [hl] != 0 && ly >= 144
+//
+// Numbers are decimal, or hex with 0x or $ in front. Names are the
+// registers (a, f, b, c, d, e, h, l, af, bc, de, hl, sp, pc), the flags
+// (zf, nf, hf, cf), ly, mode (the ppu mode) and bank (the rom bank at
+// 0x4000-0x7FFF). [addr] reads a byte and w[addr] a little endian word.
+// Operators work like in C, comparisons and && / || give 1 or 0.
+
+#[derive(Debug, PartialEq)]
+pub enum ExprError {
+    InvalidCharacter(char),
+    InvalidNumber(String),
+    UnknownName(String),
+    Expected(&'static str),
+    UnexpectedEnd,
+    TooDeep
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::InvalidCharacter(c) => write!(f, "unexpected character '{}'", c),
+            ExprError::InvalidNumber(number) => write!(f, "{} is not a number", number),
+            ExprError::UnknownName(name) => write!(f, "unknown name {}", name),
+            ExprError::Expected(what) => write!(f, "expected {}", what),
+            ExprError::UnexpectedEnd => write!(f, "expression ends unexpectedly"),
+            ExprError::TooDeep => write!(f, "expression is nested too deeply")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    A, F, B, C, D, E, H, L,
+    AF, BC, DE, HL, SP, PC,
+    Flag(Flag),
+    Ly,
+    PpuMode,
+    RomBank
+}
+
+impl Value {
+    fn from_name(name: &str) -> Option<Self> {
+        let value = match name {
+            "a" => Value::A,
+            "f" => Value::F,
+            "b" => Value::B,
+            "c" => Value::C,
+            "d" => Value::D,
+            "e" => Value::E,
+            "h" => Value::H,
+            "l" => Value::L,
+            "af" => Value::AF,
+            "bc" => Value::BC,
+            "de" => Value::DE,
+            "hl" => Value::HL,
+            "sp" => Value::SP,
+            "pc" => Value::PC,
+            "zf" => Value::Flag(Flag::Z),
+            "nf" => Value::Flag(Flag::N),
+            "hf" => Value::Flag(Flag::H),
+            "cf" => Value::Flag(Flag::C),
+            "ly" => Value::Ly,
+            "mode" => Value::PpuMode,
+            "bank" => Value::RomBank,
+            _ => return None
+        };
+
+        Some(value)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOp {
+    Not,
+    Negate,
+    Complement
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Or, And,
+    BitOr, BitXor, BitAnd,
+    Equal, NotEqual,
+    Less, LessEqual, Greater, GreaterEqual,
+    ShiftLeft, ShiftRight,
+    Add, Subtract,
+    Multiply, Divide, Remainder
+}
+
+impl BinaryOp {
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::BitOr => 3,
+            BinaryOp::BitXor => 4,
+            BinaryOp::BitAnd => 5,
+            BinaryOp::Equal | BinaryOp::NotEqual => 6,
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => 7,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 8,
+            BinaryOp::Add | BinaryOp::Subtract => 9,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Remainder => 10
+        }
+    }
+
+    fn apply(&self, lhs: i64, rhs: i64) -> i64 {
+        match self {
+            BinaryOp::Or => ((lhs != 0) || (rhs != 0)) as i64,
+            BinaryOp::And => ((lhs != 0) && (rhs != 0)) as i64,
+            BinaryOp::BitOr => lhs | rhs,
+            BinaryOp::BitXor => lhs ^ rhs,
+            BinaryOp::BitAnd => lhs & rhs,
+            BinaryOp::Equal => (lhs == rhs) as i64,
+            BinaryOp::NotEqual => (lhs != rhs) as i64,
+            BinaryOp::Less => (lhs < rhs) as i64,
+            BinaryOp::LessEqual => (lhs <= rhs) as i64,
+            BinaryOp::Greater => (lhs > rhs) as i64,
+            BinaryOp::GreaterEqual => (lhs >= rhs) as i64,
+            BinaryOp::ShiftLeft => lhs.checked_shl(rhs as u32).unwrap_or(0),
+            BinaryOp::ShiftRight => lhs.checked_shr(rhs as u32).unwrap_or(0),
+            BinaryOp::Add => lhs.wrapping_add(rhs),
+            BinaryOp::Subtract => lhs.wrapping_sub(rhs),
+            BinaryOp::Multiply => lhs.wrapping_mul(rhs),
+
+            // dividing by zero gives 0 rather than stopping the emulator
+            BinaryOp::Divide => lhs.checked_div(rhs).unwrap_or(0),
+            BinaryOp::Remainder => lhs.checked_rem(rhs).unwrap_or(0)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Value(Value),
+    Byte(Box<Expr>),
+    Word(Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>)
+}
+
+// What expressions can look at: the registers and memory. Memory is read like
+// the memory viewer does, so vram/oam show through the ppu's locks and ly is
+// the real one even when it's faked for gameboy doctor
+pub struct ExprContext<'a> {
+    registers: Registers,
+    mmu: &'a Mmu
+}
+
+impl<'a> ExprContext<'a> {
+    pub(crate) fn new(registers: Registers, mmu: &'a Mmu) -> Self {
+        Self {
+            registers,
+            mmu
+        }
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.registers
+    }
+
+    pub fn rom_bank(&self) -> u16 {
+        self.rom_bank_at(0x4000)
+    }
+
+    pub(crate) fn rom_bank_at(&self, addr: u16) -> u16 {
+        self.mmu.rom_bank_at(addr).unwrap_or(0)
+    }
+
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        self.mmu.debug_read(addr, MemoryBanks::default())
+    }
+
+    pub(crate) fn cycle(&self) -> u64 {
+        self.mmu.clock
+    }
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Self, ExprError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0, depth: 0 };
+
+        let expr = parser.parse_expr(0)?;
+        if parser.pos != tokens.len() {
+            return Err(ExprError::Expected("end of expression"));
+        }
+
+        Ok(expr)
+    }
+
+    pub fn eval(&self, ctx: &ExprContext<'_>) -> i64 {
+        match self {
+            Expr::Number(number) => *number,
+            Expr::Value(value) => eval_value(*value, ctx),
+            Expr::Byte(addr) => ctx.read_byte(addr.eval(ctx) as u16) as i64,
+            Expr::Word(addr) => {
+                let addr = addr.eval(ctx) as u16;
+                let lo = ctx.read_byte(addr) as i64;
+                let hi = ctx.read_byte(addr.wrapping_add(1)) as i64;
+                (hi << 8) | lo
+            }
+
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(ctx);
+                match op {
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::Negate => value.wrapping_neg(),
+                    UnaryOp::Complement => !value
+                }
+            }
+
+            // && and || don't need to look at the right hand side if the left decides it
+            Expr::Binary(BinaryOp::And, lhs, rhs) => (lhs.eval(ctx) != 0 && rhs.eval(ctx) != 0) as i64,
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => (lhs.eval(ctx) != 0 || rhs.eval(ctx) != 0) as i64,
+            Expr::Binary(op, lhs, rhs) => op.apply(lhs.eval(ctx), rhs.eval(ctx))
+        }
+    }
+}
+
+fn eval_value(value: Value, ctx: &ExprContext<'_>) -> i64 {
+    let registers = ctx.registers();
+
+    let value = match value {
+        Value::A => registers.a as u16,
+        Value::F => registers.f as u16,
+        Value::B => registers.b as u16,
+        Value::C => registers.c as u16,
+        Value::D => registers.d as u16,
+        Value::E => registers.e as u16,
+        Value::H => registers.h as u16,
+        Value::L => registers.l as u16,
+        Value::AF => registers.af(),
+        Value::BC => registers.bc(),
+        Value::DE => registers.de(),
+        Value::HL => registers.hl(),
+        Value::SP => registers.sp,
+        Value::PC => registers.pc,
+        Value::Flag(flag) => registers.is_flag_set(flag) as u16,
+        Value::Ly => ctx.read_byte(0xFF44) as u16,
+        Value::PpuMode => (ctx.read_byte(0xFF41) & 0b11) as u16,
+        Value::RomBank => ctx.rom_bank()
+    };
+
+    value as i64
+}
+
+// An expression and the text it came from, so it can be shown again
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    text: String,
+    expr: Expr
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self, ExprError> {
+        Ok(Self {
+            text: text.trim().to_owned(),
+            expr: Expr::parse(text)?
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_true(&self, ctx: &ExprContext<'_>) -> bool {
+        self.expr.eval(ctx) != 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum LogPart {
+    Text(String),
+    Value { expr: Expr, hex: bool }
+}
+
+// Log-point messages: text with expressions in braces, add :x to print in hex
+//   "a is {a}, [hl] is {[hl]:x}"
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogMessage {
+    text: String,
+    parts: Vec<LogPart>
+}
+
+impl LogMessage {
+    pub fn parse(text: &str) -> Result<Self, ExprError> {
+        let mut parts = Vec::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(LogPart::Text(rest[..start].to_owned()));
+            }
+
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err(ExprError::Expected("}"))
+            };
+
+            let inner = &rest[start + 1..end];
+            let (inner, hex) = match inner.strip_suffix(":x") {
+                Some(inner) => (inner, true),
+                None => (inner, false)
+            };
+
+            parts.push(LogPart::Value { expr: Expr::parse(inner)?, hex });
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(LogPart::Text(rest.to_owned()));
+        }
+
+        Ok(Self {
+            text: text.to_owned(),
+            parts
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn format(&self, ctx: &ExprContext<'_>) -> String {
+        let mut message = String::new();
+
+        for part in &self.parts {
+            match part {
+                LogPart::Text(text) => message.push_str(text),
+                LogPart::Value { expr, hex: false } => message.push_str(&expr.eval(ctx).to_string()),
+                LogPart::Value { expr, hex: true } => {
+                    let value = expr.eval(ctx);
+                    if (0..=0xFF).contains(&value) {
+                        message.push_str(&format!("{:02X}", value));
+                    } else {
+                        message.push_str(&format!("{:04X}", value));
+                    }
+                }
+            }
+        }
+
+        message
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Op(&'static str)
+}
+
+const OPERATORS: [&str; 24] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>",
+    "|", "^", "&", "<", ">", "+", "-", "*", "/", "%", "!", "~",
+    "(", ")", "[", "]"
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+            let len = rest[1..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .map(|len| len + 1)
+                .unwrap_or(rest.len());
+
+            let word = rest[..len].to_ascii_lowercase();
+            if c.is_ascii_digit() || c == '$' {
+                tokens.push(Token::Number(parse_number(&word)?));
+            } else {
+                tokens.push(Token::Name(word));
+            }
+
+            rest = &rest[len..];
+        } else {
+            let op = match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                Some(op) => *op,
+                None => return Err(ExprError::InvalidCharacter(c))
+            };
+
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(word: &str) -> Result<i64, ExprError> {
+    let result = if let Some(hex) = word.strip_prefix("0x").or_else(|| word.strip_prefix('$')) {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(binary) = word.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2)
+    } else {
+        word.parse()
+    };
+
+    result.map_err(|_| ExprError::InvalidNumber(word.to_owned()))
+}
+
+// deeper than anyone would type, but shallow enough that parsing and
+// evaluating can't run out of stack
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn peek_binary_op(&self) -> Option<BinaryOp> {
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => *op,
+            _ => return None
+        };
+
+        let op = match op {
+            "||" => BinaryOp::Or,
+            "&&" => BinaryOp::And,
+            "|" => BinaryOp::BitOr,
+            "^" => BinaryOp::BitXor,
+            "&" => BinaryOp::BitAnd,
+            "==" => BinaryOp::Equal,
+            "!=" => BinaryOp::NotEqual,
+            "<" => BinaryOp::Less,
+            "<=" => BinaryOp::LessEqual,
+            ">" => BinaryOp::Greater,
+            ">=" => BinaryOp::GreaterEqual,
+            "<<" => BinaryOp::ShiftLeft,
+            ">>" => BinaryOp::ShiftRight,
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Subtract,
+            "*" => BinaryOp::Multiply,
+            "/" => BinaryOp::Divide,
+            "%" => BinaryOp::Remainder,
+            _ => return None
+        };
+
+        Some(op)
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<(), ExprError> {
+        match self.next() {
+            Some(Token::Op(next)) if *next == op => Ok(()),
+            Some(_) => Err(ExprError::Expected(op)),
+            None => Err(ExprError::UnexpectedEnd)
+        }
+    }
+
+    // binary operators with at least min_precedence, all left associative
+    fn parse_expr(&mut self, min_precedence: u8) -> Result<Expr, ExprError> {
+        let mut lhs = self.parse_unary()?;
+
+        while let Some(op) = self.peek_binary_op() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+
+            self.pos += 1;
+            let rhs = self.parse_expr(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    // every bracket or unary operator comes through here, so this is where
+    // the nesting gets counted
+    fn parse_unary(&mut self) -> Result<Expr, ExprError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ExprError::TooDeep);
+        }
+
+        self.depth += 1;
+        let expr = self.parse_value();
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_value(&mut self) -> Result<Expr, ExprError> {
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(ExprError::UnexpectedEnd)
+        };
+
+        match token {
+            Token::Number(number) => Ok(Expr::Number(*number)),
+
+            Token::Name(name) if name == "w" && self.tokens.get(self.pos) == Some(&Token::Op("[")) => {
+                self.pos += 1;
+                let addr = self.parse_expr(0)?;
+                self.expect("]")?;
+                Ok(Expr::Word(Box::new(addr)))
+            }
+
+            Token::Name(name) => match Value::from_name(name) {
+                Some(value) => Ok(Expr::Value(value)),
+                None => Err(ExprError::UnknownName(name.clone()))
+            },
+
+            Token::Op("(") => {
+                let expr = self.parse_expr(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+
+            Token::Op("[") => {
+                let addr = self.parse_expr(0)?;
+                self.expect("]")?;
+                Ok(Expr::Byte(Box::new(addr)))
+            }
+
+            Token::Op("!") => Ok(Expr::Unary(UnaryOp::Not, Box::new(self.parse_unary()?))),
+            Token::Op("-") => Ok(Expr::Unary(UnaryOp::Negate, Box::new(self.parse_unary()?))),
+            Token::Op("~") => Ok(Expr::Unary(UnaryOp::Complement, Box::new(self.parse_unary()?))),
+
+            Token::Op(_) => Err(ExprError::Expected("a value"))
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::{VecDeque, vec_deque};
+
+use crate::gameboy::cpu::{Registers, decode::{self, DecodedInstr}};
+
+// The last few hundred instructions the cpu ran, for working out how we got
+// somewhere (and for crash reports). Always on, it's just a copy of the
+// registers per instruction. Entries are added when an instruction is
+// fetched, so the newest one is what the cpu is running (or paused on).
+
+pub const DEFAULT_HISTORY_LENGTH: usize = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HistoryEntry {
+    // registers before the instruction ran
+    pub registers: Registers,
+
+    // the rom bank at pc
+    pub bank: u16,
+    pub opcode: u8,
+    pub operands: [u8; 2],
+    pub cycle: u64
+}
+
+impl HistoryEntry {
+    pub fn pc(&self) -> u16 {
+        self.registers.pc
+    }
+
+    // from the bytes as they were when it ran
+    pub fn decode(&self) -> DecodedInstr {
+        decode::decode(&[self.opcode, self.operands[0], self.operands[1]], self.pc())
+    }
+}
+
+pub struct ExecutionHistory {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize
+}
+
+impl ExecutionHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // 0 turns it off
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // oldest first
+    pub fn iter(&self) -> vec_deque::Iter<'_, HistoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(super) fn push(&mut self, entry: HistoryEntry) {
+        if self.capacity == 0 { return }
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
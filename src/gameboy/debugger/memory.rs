@@ -0,0 +1,176 @@
+// Bits for the memory viewer: which banks to look at, what lives where in
+// the address space, which bytes were written recently and searching for
+// byte strings. The reads themselves are Mmu::debug_read, which doesn't
+// care about vram/oam being locked and doesn't set anything off.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MemoryBanks {
+    // None for whatever is switched in at the moment
+    pub rom: Option<usize>,
+    pub ram: Option<usize>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemoryRegion {
+    Rom0,
+    RomX,
+    Vram,
+    Sram,
+    Wram,
+    Echo,
+    Oam,
+    Unusable,
+    Io,
+    Hram,
+    Ie
+}
+
+impl MemoryRegion {
+    pub const ALL: [MemoryRegion; 11] = [
+        MemoryRegion::Rom0, MemoryRegion::RomX, MemoryRegion::Vram, MemoryRegion::Sram,
+        MemoryRegion::Wram, MemoryRegion::Echo, MemoryRegion::Oam, MemoryRegion::Unusable,
+        MemoryRegion::Io, MemoryRegion::Hram, MemoryRegion::Ie
+    ];
+
+    pub fn at(addr: u16) -> Self {
+        match addr {
+            0x0000..=0x3FFF => MemoryRegion::Rom0,
+            0x4000..=0x7FFF => MemoryRegion::RomX,
+            0x8000..=0x9FFF => MemoryRegion::Vram,
+            0xA000..=0xBFFF => MemoryRegion::Sram,
+            0xC000..=0xDFFF => MemoryRegion::Wram,
+            0xE000..=0xFDFF => MemoryRegion::Echo,
+            0xFE00..=0xFE9F => MemoryRegion::Oam,
+            0xFEA0..=0xFEFF => MemoryRegion::Unusable,
+            0xFF00..=0xFF7F => MemoryRegion::Io,
+            0xFF80..=0xFFFE => MemoryRegion::Hram,
+            0xFFFF => MemoryRegion::Ie
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemoryRegion::Rom0 => "ROM0",
+            MemoryRegion::RomX => "ROMX",
+            MemoryRegion::Vram => "VRAM",
+            MemoryRegion::Sram => "SRAM",
+            MemoryRegion::Wram => "WRAM",
+            MemoryRegion::Echo => "ECHO",
+            MemoryRegion::Oam => "OAM",
+            MemoryRegion::Unusable => "----",
+            MemoryRegion::Io => "IO",
+            MemoryRegion::Hram => "HRAM",
+            MemoryRegion::Ie => "IE"
+        }
+    }
+
+    // inclusive
+    pub fn start(&self) -> u16 {
+        match self {
+            MemoryRegion::Rom0 => 0x0000,
+            MemoryRegion::RomX => 0x4000,
+            MemoryRegion::Vram => 0x8000,
+            MemoryRegion::Sram => 0xA000,
+            MemoryRegion::Wram => 0xC000,
+            MemoryRegion::Echo => 0xE000,
+            MemoryRegion::Oam => 0xFE00,
+            MemoryRegion::Unusable => 0xFEA0,
+            MemoryRegion::Io => 0xFF00,
+            MemoryRegion::Hram => 0xFF80,
+            MemoryRegion::Ie => 0xFFFF
+        }
+    }
+
+    pub fn end(&self) -> u16 {
+        match self {
+            MemoryRegion::Rom0 => 0x3FFF,
+            MemoryRegion::RomX => 0x7FFF,
+            MemoryRegion::Vram => 0x9FFF,
+            MemoryRegion::Sram => 0xBFFF,
+            MemoryRegion::Wram => 0xDFFF,
+            MemoryRegion::Echo => 0xFDFF,
+            MemoryRegion::Oam => 0xFE9F,
+            MemoryRegion::Unusable => 0xFEFF,
+            MemoryRegion::Io => 0xFF7F,
+            MemoryRegion::Hram => 0xFFFE,
+            MemoryRegion::Ie => 0xFFFF
+        }
+    }
+}
+
+// The last cycle each address was written to, by the cpu, oam dma or the
+// memory viewer. Writes that the hardware ignored (locked vram etc) count too.
+pub struct WriteTracker {
+    // cycle + 1, so 0 can mean never
+    cycles: Vec<u64>
+}
+
+impl Default for WriteTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WriteTracker {
+    pub fn new() -> Self {
+        Self {
+            cycles: vec![0; 0x10000]
+        }
+    }
+
+    pub fn last_write(&self, addr: u16) -> Option<u64> {
+        match self.cycles[addr as usize] {
+            0 => None,
+            cycle => Some(cycle - 1)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cycle in self.cycles.iter_mut() {
+            *cycle = 0;
+        }
+    }
+
+    pub(crate) fn record(&mut self, addr: u16, cycle: u64) {
+        self.cycles[addr as usize] = cycle + 1;
+    }
+}
+
+// "3E 05", "3e05" or "C3 ?? 40", ?? matches any byte
+pub fn parse_byte_pattern(text: &str) -> Result<Vec<Option<u8>>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if digits.is_empty() {
+        return Err(String::from("Nothing to search for"));
+    }
+
+    if digits.len() % 2 != 0 {
+        return Err(format!("{} isn't a whole number of bytes", text.trim()));
+    }
+
+    digits.chunks(2).map(|pair| {
+        let byte: String = pair.iter().collect();
+        if byte == "??" {
+            return Ok(None);
+        }
+
+        u8::from_str_radix(&byte, 16)
+            .map(Some)
+            .map_err(|_| format!("{} isn't a hex byte", byte))
+    }).collect()
+}
+
+// every offset in memory where the pattern starts
+pub fn find_pattern(memory: &[u8], pattern: &[Option<u8>]) -> Vec<usize> {
+    if pattern.is_empty() || pattern.len() > memory.len() {
+        return Vec::new();
+    }
+
+    memory.windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| {
+            window.iter().zip(pattern).all(|(byte, expected)| expected.is_none() || *expected == Some(*byte))
+        })
+        .map(|(offset, _)| offset)
+        .collect()
+}
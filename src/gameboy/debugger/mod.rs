@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+
+use self::{call_stack::CallStack, expr::{Condition, ExprContext, LogMessage}, history::{DEFAULT_HISTORY_LENGTH, ExecutionHistory, HistoryEntry}, symbols::Symbols, watchpoint::WatchpointHit};
+
+pub mod call_stack;
+pub mod expr;
+pub mod history;
+pub mod memory;
+pub mod oam;
+pub mod raster;
+pub mod symbols;
+pub mod timeline;
+pub mod vram;
+pub mod watchpoint;
+
+// The debugger gets told about every instruction the cpu fetches (see
+// Cpu::new_instruction_fetched) and decides if emulation should stop there.
+// While it's paused GameBoy::tick doesn't do anything.
+
+// how many log-point messages are kept around for the ui
+const MAX_LOG_LINES: usize = 200;
+
+#[derive(Clone, Debug)]
+pub struct Breakpoint {
+    pub addr: u16,
+
+    // only break when this rom bank is switched in, None for any bank
+    pub bank: Option<u16>,
+    pub enabled: bool,
+
+    // only counts as a hit when this is true
+    pub condition: Option<Condition>,
+
+    // hits so far, and how many are needed before it stops
+    pub hit_count: u32,
+    pub break_after: Option<u32>,
+
+    // log-points print this instead of stopping
+    pub log_message: Option<LogMessage>
+}
+
+impl Breakpoint {
+    fn new(addr: u16, bank: Option<u16>) -> Self {
+        Self {
+            addr,
+            bank,
+            enabled: true,
+            condition: None,
+            hit_count: 0,
+            break_after: None,
+            log_message: None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BreakReason {
+    Breakpoint(usize),
+    Watchpoint(WatchpointHit),
+    Step,
+    RunToCursor,
+
+    // a trace comparison found a difference or ran out of reference,
+    // see GameBoy::trace_compare
+    TraceCompare
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum StepMode {
+    Run,
+    StepInstruction,
+    StepOver { return_pc: u16, sp: u16 },
+    StepOut { sp: u16 },
+    RunTo(u16)
+}
+
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    step_mode: StepMode,
+    break_reason: Option<BreakReason>,
+    last_opcode: u8,
+    log: VecDeque<String>,
+
+    symbols: Symbols,
+    call_stack: CallStack,
+    history: ExecutionHistory
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            step_mode: StepMode::Run,
+            break_reason: None,
+            last_opcode: 0,
+            log: VecDeque::new(),
+
+            symbols: Symbols::new(),
+            call_stack: CallStack::new(),
+            history: ExecutionHistory::new(DEFAULT_HISTORY_LENGTH)
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.break_reason.is_some()
+    }
+
+    pub fn break_reason(&self) -> Option<BreakReason> {
+        self.break_reason
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16, bank: Option<u16>) -> usize {
+        if let Some(index) = self.breakpoints.iter().position(|bp| bp.addr == addr && bp.bank == bank) {
+            return index;
+        }
+
+        self.breakpoints.push(Breakpoint::new(addr, bank));
+        self.breakpoints.len() - 1
+    }
+
+    // for setting conditions, hit counts and log messages
+    pub fn breakpoint_mut(&mut self, index: usize) -> Option<&mut Breakpoint> {
+        self.breakpoints.get_mut(index)
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) {
+        if index < self.breakpoints.len() {
+            self.breakpoints.remove(index);
+        }
+    }
+
+    pub fn set_breakpoint_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(bp) = self.breakpoints.get_mut(index) {
+            bp.enabled = enabled;
+        }
+    }
+
+    // adds a breakpoint for any bank, or removes all the ones at addr
+    pub fn toggle_breakpoint(&mut self, addr: u16) {
+        if self.breakpoints.iter().any(|bp| bp.addr == addr) {
+            self.breakpoints.retain(|bp| bp.addr != addr);
+        } else {
+            self.add_breakpoint(addr, None);
+        }
+    }
+
+    pub fn has_breakpoint(&self, addr: u16) -> bool {
+        self.breakpoints.iter().any(|bp| bp.enabled && bp.addr == addr)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    // messages from log-points, oldest first
+    pub fn log(&self) -> &VecDeque<String> {
+        &self.log
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    pub fn call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
+
+    pub fn history(&self) -> &ExecutionHistory {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut ExecutionHistory {
+        &mut self.history
+    }
+
+    pub fn resume(&mut self) {
+        self.step_mode = StepMode::Run;
+        self.break_reason = None;
+    }
+
+    // stop before the next instruction
+    pub fn pause(&mut self) {
+        self.step_mode = StepMode::StepInstruction;
+    }
+
+    pub fn step_instruction(&mut self) {
+        self.step_mode = StepMode::StepInstruction;
+        self.break_reason = None;
+    }
+
+    // skips over calls (and rsts) by running until we're back after them
+    // with the stack where it was
+    pub fn step_over(&mut self, pc: u16, sp: u16, opcode: u8) {
+        let call_length = match opcode {
+            0xC4 | 0xCC | 0xCD | 0xD4 | 0xDC => Some(3),
+            0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => Some(1),
+            _ => None
+        };
+
+        self.step_mode = match call_length {
+            Some(length) => StepMode::StepOver { return_pc: pc.wrapping_add(length), sp },
+            None => StepMode::StepInstruction
+        };
+        self.break_reason = None;
+    }
+
+    // run until something returns past the current stack frame
+    pub fn step_out(&mut self, sp: u16) {
+        self.step_mode = StepMode::StepOut { sp };
+        self.break_reason = None;
+    }
+
+    pub fn run_to(&mut self, addr: u16) {
+        self.step_mode = StepMode::RunTo(addr);
+        self.break_reason = None;
+    }
+
+    pub(super) fn on_watchpoint_hit(&mut self, hit: WatchpointHit) {
+        self.break_reason = Some(BreakReason::Watchpoint(hit));
+        self.step_mode = StepMode::Run;
+    }
+
+    pub(super) fn on_trace_compare_finished(&mut self) {
+        self.break_reason = Some(BreakReason::TraceCompare);
+        self.step_mode = StepMode::Run;
+    }
+
+    pub(super) fn on_instruction_fetched(&mut self, ctx: &ExprContext<'_>, opcode: u8) {
+        let registers = ctx.registers();
+        let (pc, sp) = (registers.pc, registers.sp);
+
+        let returned = matches!(self.last_opcode, 0xC0 | 0xC8 | 0xC9 | 0xD0 | 0xD8 | 0xD9);
+        self.last_opcode = opcode;
+        self.call_stack.on_instruction(pc, ctx.rom_bank_at(pc), sp, opcode);
+        self.history.push(HistoryEntry {
+            registers,
+            bank: ctx.rom_bank_at(pc),
+            opcode,
+            operands: [ctx.read_byte(pc.wrapping_add(1)), ctx.read_byte(pc.wrapping_add(2))],
+            cycle: ctx.cycle()
+        });
+
+        let hit_breakpoint = self.check_breakpoints(ctx);
+
+        let reason = match self.step_mode {
+            _ if hit_breakpoint.is_some() => hit_breakpoint.map(BreakReason::Breakpoint),
+            StepMode::StepInstruction => Some(BreakReason::Step),
+            StepMode::StepOver { return_pc, sp: call_sp } if pc == return_pc && sp >= call_sp => Some(BreakReason::Step),
+            StepMode::StepOut { sp: start_sp } if returned && sp > start_sp => Some(BreakReason::Step),
+            StepMode::RunTo(addr) if addr == pc => Some(BreakReason::RunToCursor),
+            _ => None
+        };
+
+        if reason.is_some() {
+            self.break_reason = reason;
+            self.step_mode = StepMode::Run;
+        }
+    }
+
+    // counts hits and prints log-points, returns the first breakpoint that should stop
+    fn check_breakpoints(&mut self, ctx: &ExprContext<'_>) -> Option<usize> {
+        let pc = ctx.registers().pc;
+        let bank = if pc < 0x8000 { Some(ctx.rom_bank_at(pc)) } else { None };
+        let mut hit = None;
+
+        for (i, bp) in self.breakpoints.iter_mut().enumerate() {
+            if !bp.enabled || bp.addr != pc || (bp.bank.is_some() && bp.bank != bank) {
+                continue;
+            }
+
+            if let Some(condition) = &bp.condition {
+                if !condition.is_true(ctx) {
+                    continue;
+                }
+            }
+
+            bp.hit_count += 1;
+            if let Some(break_after) = bp.break_after {
+                if bp.hit_count < break_after {
+                    continue;
+                }
+            }
+
+            match &bp.log_message {
+                Some(log_message) => {
+                    let message = log_message.format(ctx);
+                    println!("{}", message);
+
+                    if self.log.len() == MAX_LOG_LINES {
+                        self.log.pop_front();
+                    }
+                    self.log.push_back(message);
+                }
+
+                None => {
+                    if hit.is_none() {
+                        hit = Some(i);
+                    }
+                }
+            }
+        }
+
+        hit
+    }
+}
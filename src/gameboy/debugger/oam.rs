@@ -0,0 +1,110 @@
+use super::vram::{palette_shades, tile_pixel};
+
+// Sprite attribute table (0xFE00-0xFE9F) decoding for the oam viewer, and
+// what the ppu's oam scan picked for the current line.
+
+pub const SPRITE_COUNT: usize = 40;
+
+// the ppu only draws this many per line
+pub const MAX_SPRITES_PER_LINE: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OamEntry {
+    pub index: usize,
+
+    // as stored, so the sprite's top left is on screen at (x - 8, y - 16)
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub flags: u8
+}
+
+impl OamEntry {
+    pub fn read(oam: &[u8], index: usize) -> Self {
+        let offset = index * 4;
+        Self {
+            index,
+            y: oam[offset],
+            x: oam[offset + 1],
+            tile: oam[offset + 2],
+            flags: oam[offset + 3]
+        }
+    }
+
+    // 0 = OBP0, 1 = OBP1
+    pub fn palette(&self) -> usize {
+        ((self.flags >> 4) & 1) as usize
+    }
+
+    pub fn xflip(&self) -> bool {
+        self.flags & (1 << 5) != 0
+    }
+
+    pub fn yflip(&self) -> bool {
+        self.flags & (1 << 6) != 0
+    }
+
+    // colours 1-3 of the bg and window are drawn over it
+    pub fn behind_bg(&self) -> bool {
+        self.flags & (1 << 7) != 0
+    }
+
+    pub fn height(tall: bool) -> u16 {
+        if tall { 16 } else { 8 }
+    }
+
+    // does the sprite cover this scanline, the same check as the ppu's
+    // oam scan (which also skips sprites at x = 0)
+    pub fn on_line(&self, line: u8, tall: bool) -> bool {
+        let line = line as u16 + 16;
+        self.x > 0 && line >= self.y as u16 && line < self.y as u16 + Self::height(tall)
+    }
+
+    // the tile number in vram for each 8 pixel high half
+    pub fn tiles(&self, tall: bool) -> Vec<usize> {
+        if tall {
+            vec![(self.tile & 0xFE) as usize, (self.tile | 0x01) as usize]
+        } else {
+            vec![self.tile as usize]
+        }
+    }
+}
+
+pub fn entries(oam: &[u8]) -> Vec<OamEntry> {
+    (0..SPRITE_COUNT).map(|index| OamEntry::read(oam, index)).collect()
+}
+
+// 8x8 or 8x16 grey shades with the flips done, colour 0 is drawn in
+// the palette's colour 0 even though it's transparent
+pub fn render_sprite(vram: &[u8], entry: &OamEntry, tall: bool, palette: u8) -> Vec<u8> {
+    let shades = palette_shades(palette);
+    let height = OamEntry::height(tall) as usize;
+    let tiles = entry.tiles(tall);
+    let mut pixels = vec![0; 8 * height];
+
+    for y in 0..height {
+        let sprite_y = if entry.yflip() { height - 1 - y } else { y };
+        let tile = tiles[sprite_y / 8];
+
+        for x in 0..8 {
+            let sprite_x = if entry.xflip() { 7 - x } else { x };
+            let colour = tile_pixel(vram, tile, sprite_x, sprite_y % 8);
+            pixels[y * 8 + x] = shades[colour as usize];
+        }
+    }
+
+    pixels
+}
+
+// The sprites the ppu's oam scan picked (at most 10, by oam index) for a line
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OamScan {
+    pub line: u8,
+    pub sprites: Vec<usize>
+}
+
+impl OamScan {
+    pub fn contains(&self, index: usize) -> bool {
+        self.sprites.contains(&index)
+    }
+}
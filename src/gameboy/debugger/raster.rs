@@ -0,0 +1,159 @@
+use std::mem;
+
+// Per scanline history of the registers raster effects are made from, for
+// the raster viewer. The ppu notes the values when each line starts drawing
+// (mode 3), and every write to them with the dot it happened on.
+
+pub const LINES: usize = 154;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RasterRegister {
+    Lcdc,
+    Scy,
+    Scx,
+    Bgp,
+    Wy,
+    Wx
+}
+
+impl RasterRegister {
+    pub const ALL: [RasterRegister; 6] = [
+        RasterRegister::Lcdc, RasterRegister::Scy, RasterRegister::Scx,
+        RasterRegister::Bgp, RasterRegister::Wy, RasterRegister::Wx
+    ];
+
+    pub fn from_addr(addr: u16) -> Option<Self> {
+        match addr {
+            0xFF40 => Some(RasterRegister::Lcdc),
+            0xFF42 => Some(RasterRegister::Scy),
+            0xFF43 => Some(RasterRegister::Scx),
+            0xFF47 => Some(RasterRegister::Bgp),
+            0xFF4A => Some(RasterRegister::Wy),
+            0xFF4B => Some(RasterRegister::Wx),
+            _ => None
+        }
+    }
+
+    pub fn addr(&self) -> u16 {
+        match self {
+            RasterRegister::Lcdc => 0xFF40,
+            RasterRegister::Scy => 0xFF42,
+            RasterRegister::Scx => 0xFF43,
+            RasterRegister::Bgp => 0xFF47,
+            RasterRegister::Wy => 0xFF4A,
+            RasterRegister::Wx => 0xFF4B
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RasterRegister::Lcdc => "LCDC",
+            RasterRegister::Scy => "SCY",
+            RasterRegister::Scx => "SCX",
+            RasterRegister::Bgp => "BGP",
+            RasterRegister::Wy => "WY",
+            RasterRegister::Wx => "WX"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LineRegisters {
+    pub lcdc: u8,
+    pub scy: u8,
+    pub scx: u8,
+    pub bgp: u8,
+    pub wy: u8,
+    pub wx: u8
+}
+
+impl LineRegisters {
+    // from the io registers (0xFF00-0xFF7F)
+    pub(crate) fn read(io: &[u8]) -> Self {
+        Self {
+            lcdc: io[0x40],
+            scy: io[0x42],
+            scx: io[0x43],
+            bgp: io[0x47],
+            wy: io[0x4A],
+            wx: io[0x4B]
+        }
+    }
+
+    pub fn get(&self, register: RasterRegister) -> u8 {
+        match register {
+            RasterRegister::Lcdc => self.lcdc,
+            RasterRegister::Scy => self.scy,
+            RasterRegister::Scx => self.scx,
+            RasterRegister::Bgp => self.bgp,
+            RasterRegister::Wy => self.wy,
+            RasterRegister::Wx => self.wx
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegisterWrite {
+    pub register: RasterRegister,
+    pub value: u8,
+
+    // 0-455 into the line
+    pub dot: u64
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineHistory {
+    // None for lines that didn't draw (vblank)
+    pub mode3: Option<LineRegisters>,
+    pub writes: Vec<RegisterWrite>
+}
+
+pub struct RasterHistory {
+    current: Vec<LineHistory>,
+    previous: Vec<LineHistory>
+}
+
+impl Default for RasterHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RasterHistory {
+    pub fn new() -> Self {
+        Self {
+            current: vec![LineHistory::default(); LINES],
+            previous: vec![LineHistory::default(); LINES]
+        }
+    }
+
+    // the frame being drawn, lines past LY are still empty
+    pub fn current_frame(&self) -> &[LineHistory] {
+        &self.current
+    }
+
+    // the last whole frame
+    pub fn last_frame(&self) -> &[LineHistory] {
+        &self.previous
+    }
+
+    pub(crate) fn new_frame(&mut self) {
+        mem::swap(&mut self.current, &mut self.previous);
+        for line in self.current.iter_mut() {
+            line.mode3 = None;
+            line.writes.clear();
+        }
+    }
+
+    pub(crate) fn mode3_started(&mut self, ly: u8, registers: LineRegisters) {
+        if let Some(line) = self.current.get_mut(ly as usize) {
+            line.mode3 = Some(registers);
+        }
+    }
+
+    pub(crate) fn write(&mut self, ly: u8, write: RegisterWrite) {
+        if let Some(line) = self.current.get_mut(ly as usize) {
+            line.writes.push(write);
+        }
+    }
+}
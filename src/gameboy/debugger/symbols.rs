@@ -0,0 +1,100 @@
+use std::{collections::{BTreeMap, HashMap}, fs, io, path::{Path, PathBuf}};
+
+// Symbol files from RGBDS (rgblink -n) and no$gmb, one label per line:
+//   00:0150 Start
+//   01:4000 Main.loop
+// Anything after a ; is a comment.
+
+#[derive(Default)]
+pub struct Symbols {
+    // (bank, addr) -> the first label there
+    labels: BTreeMap<(u16, u16), String>,
+    by_name: HashMap<String, (u16, u16)>
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut symbols = Self::new();
+
+        for line in text.lines() {
+            let line = line.split(';').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (location, name) = match line.split_once(char::is_whitespace) {
+                Some((location, name)) => (location, name.trim()),
+                None => continue
+            };
+
+            let parsed = location.split_once(':').and_then(|(bank, addr)| {
+                Some((u16::from_str_radix(bank, 16).ok()?, u16::from_str_radix(addr, 16).ok()?))
+            });
+
+            match parsed {
+                Some((bank, addr)) if !name.is_empty() => symbols.add(bank, addr, name),
+                _ => println!("Skipping invalid symbol: {}", line)
+            }
+        }
+
+        symbols
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    // game.sym next to game.gb
+    pub fn find_for_rom(rom_path: &Path) -> Option<PathBuf> {
+        let path = rom_path.with_extension("sym");
+        if path.is_file() { Some(path) } else { None }
+    }
+
+    pub fn add(&mut self, bank: u16, addr: u16, name: &str) {
+        self.labels.entry((bank, addr)).or_insert_with(|| name.to_owned());
+        self.by_name.insert(name.to_owned(), (bank, addr));
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    // (bank, addr) of a label
+    pub fn lookup(&self, name: &str) -> Option<(u16, u16)> {
+        self.by_name.get(name).copied()
+    }
+
+    // Address of a label and the rom bank a breakpoint on it needs, if it's
+    // in the switchable rom bank
+    pub fn resolve(&self, name: &str) -> Option<(u16, Option<u16>)> {
+        let (bank, addr) = self.lookup(name)?;
+        let bank = if (0x4000..=0x7FFF).contains(&addr) { Some(bank) } else { None };
+        Some((addr, bank))
+    }
+
+    // The label at addr. Without a bank any bank matches, for ram where the
+    // bank in the file doesn't tell us much
+    pub fn name_at(&self, bank: Option<u16>, addr: u16) -> Option<&str> {
+        match bank {
+            Some(bank) => self.labels.get(&(bank, addr)).map(|name| name.as_str()),
+            None => self.labels.iter()
+                .find(|((_, label_addr), _)| *label_addr == addr)
+                .map(|(_, name)| name.as_str())
+        }
+    }
+
+    // The closest label at or before addr in the same bank, and how far past it addr is
+    pub fn nearest(&self, bank: u16, addr: u16) -> Option<(&str, u16)> {
+        self.labels.range((bank, 0)..=(bank, addr))
+            .next_back()
+            .map(|((_, label_addr), name)| (name.as_str(), addr - label_addr))
+    }
+}
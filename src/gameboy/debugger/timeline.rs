@@ -0,0 +1,187 @@
+use std::{collections::VecDeque, fmt, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use crate::gameboy::interupt::InterruptFlag;
+
+// Interrupts and the other things that decide when code runs, with the
+// cycle they happened on, for checking irq timing/latency against hardware.
+// Off until started, the frontend draws it a frame (vblank to vblank) at a
+// time and can save it as csv.
+
+// about 30 seconds of a busy game
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+pub const CSV_HEADER: &str = "cycle,frame,frame_cycle,ly,dot,event,detail";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimelineEvent {
+    InterruptRequested(InterruptFlag),
+
+    // the cpu started dispatching it (20 cycles to get to the vector)
+    InterruptServiced(InterruptFlag),
+
+    HaltEntered,
+    HaltExited,
+
+    // source is the high byte written to 0xFF46
+    OamDma { source: u8 },
+
+    LcdOn,
+    LcdOff
+}
+
+impl TimelineEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimelineEvent::InterruptRequested(_) => "irq_request",
+            TimelineEvent::InterruptServiced(_) => "irq_service",
+            TimelineEvent::HaltEntered => "halt",
+            TimelineEvent::HaltExited => "halt_exit",
+            TimelineEvent::OamDma { .. } => "oam_dma",
+            TimelineEvent::LcdOn => "lcd_on",
+            TimelineEvent::LcdOff => "lcd_off"
+        }
+    }
+
+    pub fn detail(&self) -> String {
+        match self {
+            TimelineEvent::InterruptRequested(flag) | TimelineEvent::InterruptServiced(flag) => interrupt_name(*flag).to_string(),
+            TimelineEvent::OamDma { source } => format!("{:02X}00", source),
+            _ => String::new()
+        }
+    }
+}
+
+impl fmt::Display for TimelineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelineEvent::InterruptRequested(flag) => write!(f, "{} requested", interrupt_name(*flag)),
+            TimelineEvent::InterruptServiced(flag) => write!(f, "{} serviced", interrupt_name(*flag)),
+            TimelineEvent::HaltEntered => write!(f, "HALT"),
+            TimelineEvent::HaltExited => write!(f, "HALT exited"),
+            TimelineEvent::OamDma { source } => write!(f, "OAM DMA from ${:02X}00", source),
+            TimelineEvent::LcdOn => write!(f, "LCD on"),
+            TimelineEvent::LcdOff => write!(f, "LCD off")
+        }
+    }
+}
+
+pub fn interrupt_name(flag: InterruptFlag) -> &'static str {
+    match flag {
+        InterruptFlag::VBlank => "VBlank",
+        InterruptFlag::Stat => "STAT",
+        InterruptFlag::Timer => "Timer",
+        InterruptFlag::Serial => "Serial",
+        InterruptFlag::Joypad => "Joypad"
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimelineEntry {
+    // cycles since power on
+    pub cycle: u64,
+
+    // GameBoy::frames at the time, frames start at vblank
+    pub frame: u64,
+    pub frame_cycle: u64,
+
+    // where the ppu was
+    pub ly: u8,
+    pub dot: u64,
+
+    pub event: TimelineEvent
+}
+
+pub struct EventTimeline {
+    recording: bool,
+    capacity: usize,
+    entries: VecDeque<TimelineEntry>
+}
+
+impl Default for EventTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventTimeline {
+    pub fn new() -> Self {
+        Self {
+            recording: false,
+            capacity: DEFAULT_CAPACITY,
+            entries: VecDeque::new()
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.recording = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.recording = false;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // the oldest entries are dropped past this
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn entries(&self) -> &VecDeque<TimelineEntry> {
+        &self.entries
+    }
+
+    pub fn frame(&self, frame: u64) -> Vec<TimelineEntry> {
+        self.entries.iter().filter(|entry| entry.frame == frame).copied().collect()
+    }
+
+    pub(crate) fn record(&mut self, entry: TimelineEntry) {
+        if !self.recording { return }
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", CSV_HEADER)?;
+        for entry in self.entries.iter() {
+            writeln!(
+                out, "{},{},{},{},{},{},{}",
+                entry.cycle, entry.frame, entry.frame_cycle, entry.ly, entry.dot,
+                entry.event.name(), entry.event.detail()
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut out)?;
+        out.flush()
+    }
+}
+
+// for a service, the cycles since that interrupt was last requested
+pub fn latency(entries: &[TimelineEntry], index: usize) -> Option<u64> {
+    let serviced = match entries.get(index)?.event {
+        TimelineEvent::InterruptServiced(flag) => flag,
+        _ => return None
+    };
+
+    let cycle = entries[index].cycle;
+    entries[..index].iter().rev()
+        .find(|entry| entry.event == TimelineEvent::InterruptRequested(serviced))
+        .map(|entry| cycle - entry.cycle)
+}
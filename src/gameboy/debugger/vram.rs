@@ -0,0 +1,123 @@
+use crate::gameboy::mmu::PALETTE;
+
+// Pictures of what's in vram for the tile and tilemap viewers. These work on
+// a copy of vram (GameBoy::vram) and give one grey shade per pixel, the same
+// as the frame buffer.
+
+pub const TILE_COUNT: usize = 384;
+
+// the tileset is drawn 16 tiles across, 24 down
+pub const TILES_PER_ROW: usize = 16;
+pub const TILESET_WIDTH: usize = TILES_PER_ROW * 8;
+pub const TILESET_HEIGHT: usize = TILE_COUNT / TILES_PER_ROW * 8;
+
+// 32x32 tiles
+pub const TILEMAP_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileMap {
+    Low,  // 0x9800
+    High  // 0x9C00
+}
+
+impl TileMap {
+    pub fn addr(&self) -> u16 {
+        match self {
+            TileMap::Low => 0x9800,
+            TileMap::High => 0x9C00
+        }
+    }
+
+    // from LCDC, the bit for the bg or window map
+    pub fn from_lcdc(lcdc: u8, bit: u8) -> Self {
+        if lcdc & bit != 0 { TileMap::High } else { TileMap::Low }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapTile {
+    // where in the map the entry is, and what it points at
+    pub map_addr: u16,
+    pub tile_index: u8,
+    pub tile_addr: u16
+}
+
+// the 4 shades a palette register (BGP, OBP0, OBP1) maps colours 0-3 to
+pub fn palette_shades(palette: u8) -> [u8; 4] {
+    let mut shades = [0; 4];
+    for (i, shade) in shades.iter_mut().enumerate() {
+        *shade = PALETTE[((palette >> (i * 2)) & 3) as usize];
+    }
+    shades
+}
+
+// LCDC bit 4 off means tiles 0-127 come from 0x9000 and 128-255 from 0x8800
+pub fn tile_data_addr(tile_index: u8, signed_addressing: bool) -> u16 {
+    if signed_addressing {
+        (0x9000 + (tile_index as i8 as i32) * 16) as u16
+    } else {
+        0x8000 + tile_index as u16 * 16
+    }
+}
+
+// colour 0-3 of a pixel in one of the 384 tiles
+pub fn tile_pixel(vram: &[u8], tile: usize, x: usize, y: usize) -> u8 {
+    let offset = tile * 16 + y * 2;
+    let low = vram[offset];
+    let high = vram[offset + 1];
+    let bit = 7 - x;
+
+    (((high >> bit) & 1) << 1) | ((low >> bit) & 1)
+}
+
+pub fn render_tileset(vram: &[u8], palette: u8) -> Vec<u8> {
+    let shades = palette_shades(palette);
+    let mut pixels = vec![0; TILESET_WIDTH * TILESET_HEIGHT];
+
+    for tile in 0..TILE_COUNT {
+        let tile_x = (tile % TILES_PER_ROW) * 8;
+        let tile_y = (tile / TILES_PER_ROW) * 8;
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let colour = tile_pixel(vram, tile, x, y);
+                pixels[(tile_y + y) * TILESET_WIDTH + tile_x + x] = shades[colour as usize];
+            }
+        }
+    }
+
+    pixels
+}
+
+// the tile at (x, y) in tiles
+pub fn map_tile_at(vram: &[u8], map: TileMap, signed_addressing: bool, x: usize, y: usize) -> MapTile {
+    let map_addr = map.addr() + (y * 32 + x) as u16;
+    let tile_index = vram[(map_addr - 0x8000) as usize];
+
+    MapTile {
+        map_addr,
+        tile_index,
+        tile_addr: tile_data_addr(tile_index, signed_addressing)
+    }
+}
+
+pub fn render_tilemap(vram: &[u8], map: TileMap, signed_addressing: bool, palette: u8) -> Vec<u8> {
+    let shades = palette_shades(palette);
+    let mut pixels = vec![0; TILEMAP_SIZE * TILEMAP_SIZE];
+
+    for map_y in 0..32 {
+        for map_x in 0..32 {
+            let tile = map_tile_at(vram, map, signed_addressing, map_x, map_y);
+            let tile_number = ((tile.tile_addr - 0x8000) / 16) as usize;
+
+            for y in 0..8 {
+                for x in 0..8 {
+                    let colour = tile_pixel(vram, tile_number, x, y);
+                    pixels[(map_y * 8 + y) * TILEMAP_SIZE + map_x * 8 + x] = shades[colour as usize];
+                }
+            }
+        }
+    }
+
+    pixels
+}
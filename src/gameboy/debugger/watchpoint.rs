@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+
+    // writes to 0x0000-0x7FFF go to the cartridge mapper's registers
+    MbcWrite
+}
+
+// Checked against the value being read or written
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueCondition {
+    Equals(u8),
+    NotEquals(u8),
+    BitsSet(u8),
+    BitsClear(u8)
+}
+
+impl ValueCondition {
+    // "=42", "!=42", "set 80" or "clear 80", values in hex
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (constructor, value): (fn(u8) -> Self, &str) = if let Some(value) = text.strip_prefix("!=") {
+            (ValueCondition::NotEquals, value)
+        } else if let Some(value) = text.strip_prefix("==").or_else(|| text.strip_prefix('=')) {
+            (ValueCondition::Equals, value)
+        } else if let Some(value) = text.strip_prefix("set") {
+            (ValueCondition::BitsSet, value)
+        } else if let Some(value) = text.strip_prefix("clear") {
+            (ValueCondition::BitsClear, value)
+        } else {
+            return None;
+        };
+
+        let value = value.trim().trim_start_matches("0x").trim_start_matches('$');
+        u8::from_str_radix(value, 16).ok().map(constructor)
+    }
+
+    pub fn matches(&self, value: u8) -> bool {
+        match *self {
+            ValueCondition::Equals(expected) => value == expected,
+            ValueCondition::NotEquals(expected) => value != expected,
+            ValueCondition::BitsSet(mask) => value & mask == mask,
+            ValueCondition::BitsClear(mask) => value & mask == 0
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Watchpoint {
+    // inclusive
+    pub start: u16,
+    pub end: u16,
+    pub kind: WatchKind,
+    pub condition: Option<ValueCondition>,
+    pub enabled: bool
+}
+
+impl Watchpoint {
+    pub fn new(start: u16, end: u16, kind: WatchKind) -> Self {
+        Self {
+            start: start.min(end),
+            end: start.max(end),
+            kind,
+            condition: None,
+            enabled: true
+        }
+    }
+
+    fn matches(&self, access: Access, addr: u16, value: u8) -> bool {
+        let kind_matches = match access {
+            Access::Read => self.kind != WatchKind::Write,
+            Access::Write | Access::MbcWrite => self.kind != WatchKind::Read
+        };
+
+        let condition_matches = match self.condition {
+            Some(condition) => condition.matches(value),
+            None => true
+        };
+
+        self.enabled && kind_matches && condition_matches && addr >= self.start && addr <= self.end
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchpointHit {
+    pub index: usize,
+    pub access: Access,
+    pub addr: u16,
+    pub value: u8,
+
+    // the instruction that made the access, and when
+    pub pc: u16,
+    pub cycle: u64
+}
+
+// Lives in the mmu so every read/write can be checked. Reads only have
+// &self, so hits are collected in a RefCell until the gameboy picks them up.
+#[derive(Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    hits: RefCell<Vec<WatchpointHit>>
+}
+
+impl Watchpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    pub fn add(&mut self, watchpoint: Watchpoint) -> usize {
+        self.list.push(watchpoint);
+        self.list.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.list.len() {
+            self.list.remove(index);
+        }
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(watchpoint) = self.list.get_mut(index) {
+            watchpoint.enabled = enabled;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        !self.list.is_empty()
+    }
+
+    pub(crate) fn check(&self, access: Access, addr: u16, value: u8, pc: u16, cycle: u64) {
+        if let Some(index) = self.list.iter().position(|watchpoint| watchpoint.matches(access, addr, value)) {
+            self.hits.borrow_mut().push(WatchpointHit { index, access, addr, value, pc, cycle });
+        }
+    }
+
+    // the first hit since the last call, if any
+    pub(crate) fn take_hit(&self) -> Option<WatchpointHit> {
+        let mut hits = self.hits.borrow_mut();
+        let hit = hits.first().copied();
+        hits.clear();
+        hit
+    }
+}
@@ -0,0 +1,27 @@
+use std::fmt;
+
+// Things the emulated hardware does that a frontend probably wants to tell
+// the user about, see GameBoy::set_event_callback
+
+pub type EventCallback = Box<dyn FnMut(&EmulatorEvent)>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmulatorEvent {
+    // an illegal opcode (0xD3, 0xDB, ...) hangs the cpu until it's reset,
+    // the rest of the hardware keeps going
+    CpuLocked { pc: u16, opcode: u8 },
+
+    // reads of nothing give 0xFF (open bus), writes are dropped
+    UnmappedRead { addr: u16, pc: u16 },
+    UnmappedWrite { addr: u16, value: u8, pc: u16 }
+}
+
+impl fmt::Display for EmulatorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulatorEvent::CpuLocked { pc, opcode } => write!(f, "CPU locked up at ${:04X} (illegal opcode ${:02X})", pc, opcode),
+            EmulatorEvent::UnmappedRead { addr, pc } => write!(f, "Read from unmapped address ${:04X} at ${:04X}", addr, pc),
+            EmulatorEvent::UnmappedWrite { addr, value, pc } => write!(f, "Write of ${:02X} to unmapped address ${:04X} at ${:04X}", value, addr, pc)
+        }
+    }
+}
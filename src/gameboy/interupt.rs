@@ -1,6 +1,6 @@
 use std::{collections::VecDeque, fmt};
 
-use super::{cpu::{Cpu, disassembler::{Instruction, InstructionStep}}};
+use super::{cpu::{Cpu, disassembler::{Instruction, InstructionStep}}, debugger::timeline::TimelineEvent};
 
 // https://eldred.fr/gb-asm-tutorial/interrupts.html
 
@@ -10,10 +10,13 @@ pub struct Interupt {
     pub flags: u8,
 
     pub waiting_for_halt_if: bool,
-    pub halt_interupt_pending: bool
+    pub halt_interupt_pending: bool,
+
+    // requests and services since the last tick, for the event timeline
+    pub(crate) events: Vec<TimelineEvent>
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InterruptFlag {
     VBlank = 0b00000001,
     Stat   = 0b00000010,
@@ -33,6 +36,12 @@ impl fmt::Debug for Interupt {
     }
 }
 
+impl Default for Interupt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Interupt {
     pub fn new() -> Self {
         Self {
@@ -41,7 +50,9 @@ impl Interupt {
             flags: 0,
 
             waiting_for_halt_if: false,
-            halt_interupt_pending: false
+            halt_interupt_pending: false,
+
+            events: Vec::new()
         }
     }
 
@@ -95,6 +106,7 @@ impl Interupt {
 
     pub fn request_interupt(&mut self, flag: InterruptFlag) {
         self.flags = self.flags | flag as u8;
+        self.events.push(TimelineEvent::InterruptRequested(flag));
         
         if self.waiting_for_halt_if {
             self.halt_interupt_pending = true;
@@ -102,11 +114,14 @@ impl Interupt {
     }
 
     pub fn handle(interrupt: &mut Interupt, cpu: &mut Cpu) {
+        if cpu.locked { return }
+
         if interrupt.is_master_enabled() && (!cpu.is_processing_instruction() || cpu.is_fetching) {
-            let _interrupt_flag = match interrupt.get_interupt_state() {
+            let interrupt_flag = match interrupt.get_interupt_state() {
                 Some(flag) => flag,
                 None => return
             };
+            interrupt.events.push(TimelineEvent::InterruptServiced(interrupt_flag));
             
             let interrupt_instr = Self::create_interupt_instruction();
             cpu.set_interrupt_instruction(interrupt_instr);
@@ -1,8 +1,10 @@
+use std::cell::RefCell;
+
 use rand::Rng;
 
-use super::{cartridge::Cartridge, input::Input, interupt::{InterruptFlag, Interupt}, ppu::PpuMode, spu::Spu, timer::Timer};
+use super::{cartridge::Cartridge, cheats::Cheats, cpu::decode::MemoryView, debugger::{memory::{MemoryBanks, WriteTracker}, raster::RasterRegister, timeline::TimelineEvent, watchpoint::{Access, Watchpoints}}, events::EmulatorEvent, input::Input, interupt::{InterruptFlag, Interupt}, ppu::PpuMode, spu::Spu, timer::Timer};
 
-const PALETTE: [u8; 4] = [
+pub(crate) const PALETTE: [u8; 4] = [
     255, 192, 96, 0
 ];
 
@@ -11,6 +13,7 @@ pub struct Mmu {
     pub interupts: Interupt,
     pub input: Input,
     pub timer: Timer,
+    pub cheats: Cheats,
     cartridge: Box<dyn Cartridge>,
 
     pub gpu_vram: [u8; 0x2000],
@@ -20,6 +23,8 @@ pub struct Mmu {
     zero_page: [u8; 0x80],
 
     pub sprite_table: [u8; 0xA0],
+
+    // shade numbers (0 lightest) for each colour, PALETTE has the greys
     pub sprite_palette: [[u8; 4]; 2],
 
     pub tileset: [[[u8; 8]; 8]; 384],
@@ -38,7 +43,28 @@ pub struct Mmu {
     stat_irq_state: bool,
 
     pub bios_enabled: bool,
-    bios: [u8; 0x100]
+    bios: [u8; 0x100],
+
+    // LY always reads 0x90 to the cpu, like Gameboy Doctor's reference logs
+    pub doctor_ly: bool,
+
+    // for watchpoints, the current cycle and instruction
+    pub watchpoints: Watchpoints,
+    pub clock: u64,
+    pub current_pc: u16,
+
+    // for highlighting recent writes in the memory viewer
+    pub writes: WriteTracker,
+
+    // writes to scroll/palette/etc registers, the ppu picks these up
+    // on its next tick for the raster history
+    pub(crate) raster_writes: Vec<(RasterRegister, u8)>,
+
+    // dma starts and the lcd turning on/off, for the event timeline
+    pub(crate) timeline_events: Vec<TimelineEvent>,
+
+    // waiting to be passed on by GameBoy::tick
+    events: RefCell<Vec<EmulatorEvent>>
 }
 
 impl Mmu {
@@ -48,6 +74,7 @@ impl Mmu {
             interupts: Interupt::new(),
             input: Input::new(),
             timer: Timer::new(),
+            cheats: Cheats::new(),
             cartridge,
 
             gpu_vram: [0; 0x2000],
@@ -57,15 +84,13 @@ impl Mmu {
 
             sprite_table: [0; 0xA0],
             sprite_palette: [
-                [PALETTE[0], PALETTE[1], PALETTE[2], PALETTE[3]],
-                [PALETTE[0], PALETTE[1], PALETTE[2], PALETTE[3]]
+                [0, 1, 2, 3],
+                [0, 1, 2, 3]
             ],
 
             // ppu
             tileset: [[[0; 8]; 8]; 384],
-            bg_palette: [
-                PALETTE[0], PALETTE[1], PALETTE[2], PALETTE[3]
-            ],
+            bg_palette: [0, 1, 2, 3],
 
             dma_transfer_base_addr: 0,
             dma_transfer_index: 0,
@@ -79,6 +104,8 @@ impl Mmu {
 
             stat_irq_state: false,
 
+            doctor_ly: false,
+
             bios_enabled: true,
             bios: [
                 0x31,0xFE,0xFF,0x21,0xFF,0x9F,0xAF,0x32,0xCB,0x7C,0x20,0xFA,0x0E,0x11,
@@ -100,7 +127,17 @@ impl Mmu {
                 0x4D,0x47,0x20,0x76,0x31,0x2E,0x32,0x00,0x3E,0xFF,0xC6,0x01,0x0B,0x1E,
                 0xD8,0x21,0x4D,0x01,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
                 0x3E,0x01,0xE0,0x50
-            ]
+            ],
+
+            watchpoints: Watchpoints::new(),
+            clock: 0,
+            current_pc: 0,
+
+            writes: WriteTracker::new(),
+            raster_writes: Vec::new(),
+            timeline_events: Vec::new(),
+
+            events: RefCell::new(Vec::new())
         };
 
         mmu.randomize_ram_values();
@@ -112,6 +149,32 @@ impl Mmu {
         mmu
     }
 
+    pub fn save_cartridge(&mut self) -> bool {
+        self.cartridge.save()
+    }
+
+    // which rom bank an address is in, None if it's not rom
+    pub fn rom_bank_at(&self, addr: u16) -> Option<u16> {
+        match addr {
+            0x0000..=0x3FFF => Some(0),
+            0x4000..=0x7FFF => Some(self.cartridge.current_rom_bank()),
+            _ => None
+        }
+    }
+
+    // gameshark codes, called once a frame. These aren't the game's writes so
+    // they skip watchpoints and the write/raster history, and they can't
+    // land on the mbc registers
+    pub fn apply_cheats(&mut self) {
+        for (ram_bank, addr, value) in self.cheats.game_shark_writes() {
+            match addr {
+                0x0000..=0x7FFF => { }
+                0xA000..=0xBFFF => self.cartridge.poke_ram(ram_bank.map(|bank| bank as usize), addr - 0xA000, value),
+                _ => self.store_byte(addr, value, false)
+            }
+        }
+    }
+
     fn randomize_ram_values(&mut self) {
         let mut rng = rand::thread_rng();
         
@@ -138,6 +201,70 @@ impl Mmu {
     }
 
     pub fn read_byte(&self, addr: u16) -> u8 {
+        let value = self.read_memory(addr, false, true);
+
+        if self.watchpoints.is_active() {
+            self.watchpoints.check(Access::Read, addr, value, self.current_pc, self.clock);
+        }
+
+        value
+    }
+
+    // read_byte without setting off any watchpoints or reporting open bus reads
+    pub fn peek_byte(&self, addr: u16) -> u8 {
+        self.read_memory(addr, false, false)
+    }
+
+    // what's really there for the memory viewer, even if the ppu has vram/oam
+    // locked or dma is running. Doesn't set off anything. A bank in
+    // banks is shown at 0x4000-0x7FFF / 0xA000-0xBFFF instead of the current one
+    pub fn debug_read(&self, addr: u16, banks: MemoryBanks) -> u8 {
+        match (addr, banks.rom, banks.ram) {
+            (0x4000..=0x7FFF, Some(bank), _) => self.cartridge.peek_rom(bank, addr - 0x4000),
+            (0xA000..=0xBFFF, _, ram_bank) => self.cartridge.peek_ram(ram_bank, addr - 0xA000),
+            _ => self.read_memory(addr, true, false)
+        }
+    }
+
+    // memory viewer edits. Goes straight into memory where it can (rom is
+    // patched, vram/oam are written even if locked), io registers get a normal
+    // write so the hardware sees the change. Watchpoints aren't checked
+    pub fn debug_write(&mut self, addr: u16, banks: MemoryBanks, value: u8) {
+        self.writes.record(addr, self.clock);
+
+        match addr {
+            0x0000..=0x3FFF => self.cartridge.poke_rom(0, addr, value),
+            0x4000..=0x7FFF => {
+                let bank = banks.rom.unwrap_or(self.cartridge.current_rom_bank() as usize);
+                self.cartridge.poke_rom(bank, addr - 0x4000, value);
+            }
+            0x8000..=0x9FFF => self.gpu_vram[(addr - 0x8000) as usize] = value,
+            0xA000..=0xBFFF => {
+                self.cartridge.poke_ram(banks.ram, addr - 0xA000, value);
+                self.cartridge.mark_ram_dirty();
+            }
+            0xC000..=0xFDFF => self.working_ram[((addr - 0xC000) & 0x1FFF) as usize] = value,
+            0xFE00..=0xFE9F => self.sprite_table[(addr - 0xFE00) as usize] = value,
+            0xFEA0..=0xFEFF => { }
+            _ => self.store_byte(addr, value, false)
+        }
+    }
+
+    pub fn rom_bank_count(&self) -> usize {
+        self.cartridge.rom_bank_count()
+    }
+
+    pub fn ram_bank_count(&self) -> usize {
+        self.cartridge.ram_bank_count()
+    }
+
+    pub fn current_ram_bank(&self) -> usize {
+        self.cartridge.current_ram_bank()
+    }
+
+    // debug reads skip the vram/oam locks. Only the cpu's own reads report
+    // open bus, anything else looking at memory would flood the events
+    fn read_memory(&self, addr: u16, debug: bool, report: bool) -> u8 {
         match addr & 0xF000 {
             // rom_bank_0
             0x0000 | 0x1000 | 0x2000 | 0x3000 |
@@ -146,12 +273,13 @@ impl Mmu {
                     return self.bios[addr as usize];
                 }
 
-                self.cartridge.read_rom(addr)
+                let value = self.cartridge.read_rom(addr);
+                self.cheats.patch_rom_read(addr, value)
             }
 
             // vram
             0x8000 | 0x9000 => {
-                if self.lock_vram {
+                if self.lock_vram && !debug {
                     return 0xFF;
                 }
 
@@ -184,6 +312,10 @@ impl Mmu {
 
                     0x0E00 => {
                         if addr < 0xFEA0 {
+                            if debug {
+                                return self.sprite_table[(addr - 0xFE00) as usize];
+                            }
+
                             if self.lock_oam || self.lock_vram {
                                 return 0xFF;
                             }
@@ -208,6 +340,10 @@ impl Mmu {
                             return 0b1110_0000 | (self.interupts.flags & 0b0001_1111);
                         }
 
+                        else if addr == 0xFF44 && self.doctor_ly && !debug {
+                            return 0x90;
+                        }
+
                         else if addr == 0xFF50 {
                             return 0xFF;
                         }
@@ -323,29 +459,42 @@ impl Mmu {
                             return self.spu.get_nr52();
                         }
 
+                        else if unmapped_io(addr) {
+                            return self.open_bus_read(addr, report);
+                        }
+
                         else if addr >= 0xFF00 && addr <= 0xFF7F {
                             return self.io[(addr - 0xFF00) as usize]
                         } 
                         
                         else {
-                            panic!("unhandled byte read from memory! Addr: {:#X}", addr);
+                            self.open_bus_read(addr, report)
                         }
                     },
 
-                    _ => {
-                        println!("Unhandled branch in read request for mem (0xFxxx): {:#X}", addr);
-                        std::process::exit(0);
-                    }
+                    _ => self.open_bus_read(addr, report)
                 }
             }
 
-            _ => {
-                panic!("Unhandled read at addr {:#06X}", addr);
-            }
+            _ => self.open_bus_read(addr, report)
         }
     }
 
     pub fn write_byte(&mut self, addr: u16, val: u8) {
+        if self.watchpoints.is_active() {
+            let access = if addr < 0x8000 { Access::MbcWrite } else { Access::Write };
+            self.watchpoints.check(access, addr, val, self.current_pc, self.clock);
+        }
+
+        self.writes.record(addr, self.clock);
+        if let Some(register) = RasterRegister::from_addr(addr) {
+            self.raster_writes.push((register, val));
+        }
+
+        self.store_byte(addr, val, true);
+    }
+
+    fn store_byte(&mut self, addr: u16, val: u8, report: bool) {
         match addr & 0xF000 {
             0x0000 | 0x1000 | 0x2000 | 0x3000 | 0x4000 |
             0x5000 | 0x6000 | 0x7000 => {
@@ -412,6 +561,9 @@ impl Mmu {
 
                         // LCD CONTROL
                         else if addr == 0xFF40 {
+                            if (self.io[0x40] ^ val) >> 7 != 0 {
+                                self.timeline_events.push(if val >> 7 == 0 { TimelineEvent::LcdOff } else { TimelineEvent::LcdOn });
+                            }
                             self.io[0x40] = val;
                             
                             // is the lcd set to off?
@@ -452,11 +604,12 @@ impl Mmu {
                         else if addr == 0xFF46 {
                             self.dma_queue(val);
                             self.io[0x46] = val;
+                            self.timeline_events.push(TimelineEvent::OamDma { source: val });
                         }
 
                         else if addr == 0xFF47 {
                             for i in 0..4 {
-                                self.bg_palette[i] = PALETTE[((val >> (i * 2)) & 3) as usize];
+                                self.bg_palette[i] = (val >> (i * 2)) & 3;
                             }
 
                             self.io[0x47] = val;
@@ -464,7 +617,7 @@ impl Mmu {
 
                         else if addr == 0xFF48 {
                             for i in 0..4 {
-                                self.sprite_palette[0][i] = PALETTE[((val >> (i * 2)) & 3) as usize];
+                                self.sprite_palette[0][i] = (val >> (i * 2)) & 3;
                             }
 
                             self.io[0x48] = val;
@@ -472,7 +625,7 @@ impl Mmu {
 
                         else if addr == 0xFF49 {
                             for i in 0..4 {
-                                self.sprite_palette[1][i] = PALETTE[((val >> (i * 2)) & 3) as usize];
+                                self.sprite_palette[1][i] = (val >> (i * 2)) & 3;
                             }
 
                             self.io[0x49] = val;
@@ -566,6 +719,10 @@ impl Mmu {
                             self.spu.set_nr52(val);
                         }
 
+                        else if unmapped_io(addr) {
+                            self.open_bus_write(addr, val, report);
+                        }
+
                         else if addr >= 0xFF00 && addr <= 0xFF7F {
                             self.io[(addr - 0xFF00) as usize] = val;
                         }
@@ -575,24 +732,44 @@ impl Mmu {
                         } 
                         
                         else {
-                            panic!("unhandled byte write to memory! Addr: {:#X} Val: {:#X}", addr, val);
+                            self.open_bus_write(addr, val, report);
                         }
                     },
 
-                    _ => {
-                        println!("Unhandled branch in write request for mem (0xFxxx): {:#X}, Val: {:#X}", addr, val);
-                        std::process::exit(0);
-                    }
+                    _ => self.open_bus_write(addr, val, report)
                 }
             },
 
-            _ => {
-                println!("Unhandled write request for mem address: {:#X}, Val: {:#X}", addr, val);
-                std::process::exit(0);
-            }
+            _ => self.open_bus_write(addr, val, report)
+        }
+    }
+
+    // nothing's there, reads see 0xFF
+    fn open_bus_read(&self, addr: u16, report: bool) -> u8 {
+        if report {
+            self.push_event(EmulatorEvent::UnmappedRead { addr, pc: self.current_pc });
+        }
+        0xFF
+    }
+
+    fn open_bus_write(&self, addr: u16, value: u8, report: bool) {
+        if report {
+            self.push_event(EmulatorEvent::UnmappedWrite { addr, value, pc: self.current_pc });
         }
     }
 
+    pub(crate) fn push_event(&self, event: EmulatorEvent) {
+        self.events.borrow_mut().push(event);
+    }
+
+    pub(crate) fn take_events(&self) -> Vec<EmulatorEvent> {
+        self.events.replace(Vec::new())
+    }
+
+    pub(crate) fn has_events(&self) -> bool {
+        !self.events.borrow().is_empty()
+    }
+
     pub fn read_word(&self, addr: u16) -> u16 {
         self.read_byte(addr) as u16 + ((self.read_byte(addr + 1) as u16) << 8)
     }
@@ -616,8 +793,9 @@ impl Mmu {
             self.dma_active_clock += 1;
 
             if self.dma_active_clock == 4 { 
-                let src_val = self.read_byte(self.dma_transfer_base_addr + self.dma_transfer_index);
+                let src_val = self.peek_byte(self.dma_transfer_base_addr + self.dma_transfer_index);
                 self.sprite_table[self.dma_transfer_index as usize] = src_val;
+                self.writes.record(0xFE00 + self.dma_transfer_index, self.clock);
                 self.dma_transfer_index += 1;
     
                 if self.dma_transfer_index == 160 {
@@ -684,4 +862,20 @@ impl Mmu {
 
         self.stat_irq_state = stat_irq_state;
     }
-}
\ No newline at end of file
+}
+
+// io registers the DMG doesn't have (sound gaps and the CGB ones), they're
+// open bus like the rest of the unmapped space
+fn unmapped_io(addr: u16) -> bool {
+    match addr {
+        0xFF08..=0xFF0E | 0xFF15 | 0xFF1F | 0xFF27..=0xFF2F => true,
+        0xFF4C..=0xFF7F => addr != 0xFF50,
+        _ => false
+    }
+}
+
+impl MemoryView for Mmu {
+    fn read(&self, addr: u16) -> u8 {
+        self.peek_byte(addr)
+    }
+}
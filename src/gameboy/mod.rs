@@ -1,17 +1,22 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::{Ref, RefCell, RefMut}, io, path::Path, rc::Rc};
 
 use sdl2::{audio::AudioQueue, keyboard::Keycode};
 
-use self::{cpu::Cpu, interupt::{InterruptFlag, Interupt}, mmu::Mmu, ppu::Ppu, spu::{Spu}};
+use self::{cartridge::{LoadOptions, header::CartridgeHeader}, cheats::Cheats, cpu::{Cpu, Registers, decode::{self, DecodedInstr, MemoryView}}, debugger::{Debugger, expr::{Expr, ExprContext}, memory::{self, MemoryBanks}, oam::OamScan, raster::RasterHistory, symbols::Symbols, timeline::{EventTimeline, TimelineEntry, TimelineEvent}, watchpoint::Watchpoints}, events::{EmulatorEvent, EventCallback}, interupt::{InterruptFlag, Interupt}, mmu::Mmu, ppu::{Layers, PixelSource, Ppu, PpuState, colourise::Colourisation, palette::DmgPalette}, spu::{Spu}, trace::{TraceLogger, TraceOptions, compare::{CompareOptions, TraceComparer}}};
 
-mod cpu;
+pub mod cpu;
 mod mmu;
-mod interupt;
-mod ppu;
+pub mod interupt;
+pub mod ppu;
 pub mod spu;
 mod timer;
 mod input;
-mod cartridge;
+pub mod cartridge;
+pub mod cheats;
+pub mod debugger;
+pub mod crash;
+pub mod events;
+pub mod trace;
 
 /*
     System Clocks
@@ -23,26 +28,80 @@ mod cartridge;
     VRAM: 2,097,152 Hz
 */
 
+// how often (in clock cycles) we check if the cartridge ram needs saving
+const AUTOSAVE_INTERVAL: u64 = 4_194_304;
+
 pub struct GameBoy {
     cpu: Cpu,
     mmu: Rc<RefCell<Mmu>>,
-    ppu: Ppu
+    ppu: Ppu,
+    header: CartridgeHeader,
+
+    // the rom's file name without the extension, the one inside for archives
+    rom_name: String,
+    debugger: Debugger,
+    trace: Option<TraceLogger>,
+    trace_compare: Option<TraceComparer>,
+    // the instruction being fetched has already gone to the trace
+    fetch_traced: bool,
+    event_callback: Option<EventCallback>,
+    timeline: EventTimeline,
+
+    // vblanks since power on
+    frames: u64,
+    frame_start_cycle: u64,
+    autosave_clock: u64
 }
 
 impl GameBoy {
     pub fn new(rom_path: &str, device: Option<Rc<RefCell<AudioQueue<f32>>>>) -> Self {
-        let cartridge = cartridge::create(rom_path);
+        match Self::new_with_options(rom_path, device, LoadOptions::default()) {
+            Ok(gb) => gb,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn new_with_options(
+        rom_path: &str, 
+        device: Option<Rc<RefCell<AudioQueue<f32>>>>, 
+        options: LoadOptions
+    ) -> Result<Self, String> {
+        let (cartridge, header, rom_name) = cartridge::create(rom_path, options)?;
         let spu = Spu::new(device);
         let mmu = Rc::new(RefCell::new(Mmu::new(cartridge, spu)));
         
         let cpu = Cpu::new(mmu.clone());
         let ppu = Ppu::new(mmu.clone());
+
+        let mut debugger = Debugger::new();
+        if let Some(symbols_path) = Symbols::find_for_rom(Path::new(rom_path)) {
+            match Symbols::load(&symbols_path) {
+                Ok(symbols) => {
+                    println!("Loaded {} symbols from {}", symbols.len(), symbols_path.display());
+                    debugger.set_symbols(symbols);
+                }
+
+                Err(err) => println!("Unable to read {}: {}", symbols_path.display(), err)
+            }
+        }
         
-        Self {
+        Ok(Self {
             cpu,
             mmu,
-            ppu
-        }
+            ppu,
+            header,
+            rom_name,
+            debugger,
+            trace: None,
+            trace_compare: None,
+            fetch_traced: false,
+            event_callback: None,
+            timeline: EventTimeline::new(),
+
+            frames: 0,
+            frame_start_cycle: 0,
+            autosave_clock: 0
+        })
     }
 
     pub fn key_down(&mut self, key: Keycode) {
@@ -59,10 +118,254 @@ impl GameBoy {
         (*self.mmu).borrow_mut().input.key_up(key);
     }
 
+    pub fn cartridge_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    // what the save (<name>.sav) and cheats (<name>.cht) are called
+    pub fn rom_name(&self) -> &str {
+        &self.rom_name
+    }
+
+    pub fn cheats(&self) -> Ref<'_, Cheats> {
+        Ref::map((*self.mmu).borrow(), |mmu| &mmu.cheats)
+    }
+
+    pub fn cheats_mut(&mut self) -> RefMut<'_, Cheats> {
+        RefMut::map((*self.mmu).borrow_mut(), |mmu| &mut mmu.cheats)
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    // interrupts, halts, dma and the lcd turning on/off with their timings,
+    // nothing is recorded until it's started
+    pub fn timeline(&self) -> &EventTimeline {
+        &self.timeline
+    }
+
+    pub fn timeline_mut(&mut self) -> &mut EventTimeline {
+        &mut self.timeline
+    }
+
+    pub fn watchpoints(&self) -> Ref<'_, Watchpoints> {
+        Ref::map((*self.mmu).borrow(), |mmu| &mmu.watchpoints)
+    }
+
+    pub fn watchpoints_mut(&mut self) -> RefMut<'_, Watchpoints> {
+        RefMut::map((*self.mmu).borrow_mut(), |mmu| &mut mmu.watchpoints)
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.cpu.registers()
+    }
+
+    pub fn set_registers(&mut self, registers: Registers) {
+        self.cpu.set_registers(registers);
+    }
+
+    // read memory without triggering watchpoints or anything else
+    pub fn peek_byte(&self, addr: u16) -> u8 {
+        (*self.mmu).borrow().peek_byte(addr)
+    }
+
+    // for the memory viewer, see Mmu::debug_read and Mmu::debug_write
+    pub fn debug_read(&self, addr: u16, banks: MemoryBanks) -> u8 {
+        (*self.mmu).borrow().debug_read(addr, banks)
+    }
+
+    pub fn debug_write(&mut self, addr: u16, banks: MemoryBanks, value: u8) {
+        (*self.mmu).borrow_mut().debug_write(addr, banks, value);
+    }
+
+    // addresses where the pattern starts, anywhere in the address space
+    pub fn search_memory(&self, pattern: &[Option<u8>], banks: MemoryBanks) -> Vec<u16> {
+        let mmu = (*self.mmu).borrow();
+        let memory: Vec<u8> = (0..=0xFFFF).map(|addr| mmu.debug_read(addr, banks)).collect();
+
+        memory::find_pattern(&memory, pattern).into_iter()
+            .map(|offset| offset as u16)
+            .collect()
+    }
+
+    // the cycle the address was last written to
+    pub fn last_write(&self, addr: u16) -> Option<u64> {
+        (*self.mmu).borrow().writes.last_write(addr)
+    }
+
+    pub fn rom_bank_count(&self) -> usize {
+        (*self.mmu).borrow().rom_bank_count()
+    }
+
+    pub fn ram_bank_count(&self) -> usize {
+        (*self.mmu).borrow().ram_bank_count()
+    }
+
+    pub fn current_rom_bank(&self) -> u16 {
+        (*self.mmu).borrow().rom_bank_at(0x4000).unwrap_or(1)
+    }
+
+    pub fn current_ram_bank(&self) -> usize {
+        (*self.mmu).borrow().current_ram_bank()
+    }
+
+    // clock cycles since power on
+    pub fn cycles(&self) -> u64 {
+        (*self.mmu).borrow().clock
+    }
+
+    // a copy of 0x8000-0x9FFF for the tile viewers, see debugger::vram
+    pub fn vram(&self) -> [u8; 0x2000] {
+        (*self.mmu).borrow().gpu_vram
+    }
+
+    // a copy of 0xFE00-0xFE9F, see debugger::oam
+    pub fn oam(&self) -> [u8; 0xA0] {
+        (*self.mmu).borrow().sprite_table
+    }
+
+    // which sprites the ppu picked for the line it's on
+    pub fn oam_scan(&self) -> &OamScan {
+        &self.ppu.oam_scan
+    }
+
+    pub fn ppu_state(&self) -> PpuState {
+        self.ppu.state()
+    }
+
+    // scroll/window/palette registers per line, for raster effects
+    pub fn raster_history(&self) -> &RasterHistory {
+        &self.ppu.raster
+    }
+
+    // e.g. for printing a value in the debugger
+    pub fn evaluate(&self, expr: &Expr) -> i64 {
+        let mmu = (*self.mmu).borrow();
+        expr.eval(&ExprContext::new(self.cpu.registers(), &mmu))
+    }
+
+    pub fn load_symbols<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let symbols = Symbols::load(path)?;
+        let count = symbols.len();
+        self.debugger.set_symbols(symbols);
+        Ok(count)
+    }
+
+    // The label at addr, looking in whichever rom bank is switched in there
+    pub fn label_at(&self, addr: u16) -> Option<String> {
+        let bank = (*self.mmu).borrow().rom_bank_at(addr);
+        self.debugger.symbols().name_at(bank, addr).map(|name| name.to_owned())
+    }
+
+    // addr as Label, Label+$N or just $XXXX if there's nothing near it
+    pub fn describe_address(&self, addr: u16) -> String {
+        let bank = (*self.mmu).borrow().rom_bank_at(addr);
+        let symbols = self.debugger.symbols();
+
+        let nearest = match bank {
+            Some(bank) => symbols.nearest(bank, addr),
+            None => symbols.name_at(None, addr).map(|name| (name, 0))
+        };
+
+        match nearest {
+            Some((name, 0)) => name.to_owned(),
+            Some((name, offset)) => format!("{}+${:X}", name, offset),
+            None => format!("${:04X}", addr)
+        }
+    }
+
+    // the instruction with labels in place of addresses
+    pub fn format_instruction(&self, instr: &DecodedInstr) -> String {
+        instr.format_with_names(|addr| self.label_at(addr))
+    }
+
+    pub fn disassemble_instruction(&self, addr: u16) -> DecodedInstr {
+        decode::decode_at(&*(*self.mmu).borrow(), addr)
+    }
+
+    pub fn disassemble_range(&self, start: u16, end: u16) -> Vec<DecodedInstr> {
+        decode::disassemble_range(&*(*self.mmu).borrow(), start, end)
+    }
+
+    pub fn step_instruction(&mut self) {
+        self.debugger.step_instruction();
+    }
+
+    pub fn step_over(&mut self) {
+        let registers = self.cpu.registers();
+        let opcode = (*self.mmu).borrow().peek_byte(registers.pc);
+        self.debugger.step_over(registers.pc, registers.sp, opcode);
+    }
+
+    pub fn step_out(&mut self) {
+        self.debugger.step_out(self.cpu.sp);
+    }
+
+    pub fn run_to(&mut self, addr: u16) {
+        self.debugger.run_to(addr);
+    }
+
+    // 160x144 RGBA8888, in the palette's colours
     pub fn get_frame_buffer(&self) -> &[u8] {
+        &self.ppu.rgba_frame_buffer
+    }
+
+    // 160x144 greys (255, 192, 96 and 0) whatever the palette is
+    pub fn get_shade_buffer(&self) -> &[u8] {
         &self.ppu.frame_buffer
     }
 
+    pub fn palette(&self) -> DmgPalette {
+        self.ppu.palette
+    }
+
+    pub fn set_palette(&mut self, palette: DmgPalette) {
+        self.ppu.set_palette(palette);
+    }
+
+    // separate bg, OBP0 and OBP1 colours like a gbc, None goes back to the palette
+    pub fn colourisation(&self) -> Option<Colourisation> {
+        self.ppu.colourisation
+    }
+
+    pub fn set_colourisation(&mut self, colourisation: Option<Colourisation>) {
+        self.ppu.set_colourisation(colourisation);
+    }
+
+    // what a gbc would pick for this game
+    pub fn title_colourisation(&self) -> Colourisation {
+        Colourisation::for_header(&self.header)
+    }
+
+    // the layer (bg, window or which sprite palette) each pixel came from
+    pub fn get_pixel_sources(&self) -> &[PixelSource] {
+        &self.ppu.pixel_sources
+    }
+
+    pub fn layers(&self) -> Layers {
+        self.ppu.layers
+    }
+
+    // hide the bg, window or sprites without the game knowing
+    pub fn set_layers(&mut self, layers: Layers) {
+        self.ppu.layers = layers;
+    }
+
+    // draw every sprite on a line, not just the first 10, mode 3 timing
+    // doesn't change so games can't tell
+    pub fn unlimited_sprites(&self) -> bool {
+        self.ppu.unlimited_sprites
+    }
+
+    pub fn set_unlimited_sprites(&mut self, unlimited: bool) {
+        self.ppu.unlimited_sprites = unlimited;
+    }
+
     pub fn get_draw_flag(&self) -> bool {
         self.ppu.draw_flag
     }
@@ -71,22 +374,119 @@ impl GameBoy {
         self.ppu.draw_flag = false;
     }
 
-    pub fn start_log(&mut self) {
-        self.cpu.start_log = true;
+    // write the cartridge ram out now, rather than waiting for the next autosave
+    pub fn save(&mut self) {
+        if (*self.mmu).borrow_mut().save_cartridge() {
+            println!("Save file written!");
+        }
+    }
+
+    // called from tick when something happens that the user should know
+    // about, without one events are dropped
+    pub fn set_event_callback<F: FnMut(&EmulatorEvent) + 'static>(&mut self, callback: F) {
+        self.event_callback = Some(Box::new(callback));
     }
 
+    // an illegal opcode hung the cpu
+    pub fn is_cpu_locked(&self) -> bool {
+        self.cpu.locked
+    }
+
+    // the execution history, registers and io registers, for when things go
+    // wrong (see crash::panic_message for getting the reason out of a panic)
+    pub fn crash_report(&self, reason: &str) -> String {
+        let mmu = (*self.mmu).borrow();
+        crash::format_report(reason, &self.header, self.cpu.registers(), self.frames, &mmu, &self.debugger)
+    }
+
+    pub fn write_crash_report<P: AsRef<Path>>(&self, path: P, reason: &str) -> io::Result<()> {
+        std::fs::write(path, self.crash_report(reason))
+    }
+
+    // replaces any trace that's already running
+    pub fn start_trace(&mut self, options: TraceOptions) -> io::Result<()> {
+        self.stop_trace();
+        self.trace = Some(TraceLogger::new(options)?);
+        Ok(())
+    }
+
+    pub fn stop_trace(&mut self) {
+        if let Some(mut trace) = self.trace.take() {
+            if let Err(err) = trace.flush() {
+                println!("Unable to write trace to {}: {}", trace.options().path.display(), err);
+            }
+        }
+    }
+
+    pub fn trace(&self) -> Option<&TraceLogger> {
+        self.trace.as_ref()
+    }
+
+    // Gameboy Doctor's logs are made with LY stuck at 0x90, traces only line
+    // up with them once a rom polls LY if this is on. Off by default, games
+    // waiting for a line other than 0x90 will hang
+    pub fn doctor_ly(&self) -> bool {
+        (*self.mmu).borrow().doctor_ly
+    }
+
+    pub fn set_doctor_ly(&mut self, doctor_ly: bool) {
+        (*self.mmu).borrow_mut().doctor_ly = doctor_ly;
+    }
+
+    // runs against a reference trace, pausing the debugger when it stops matching
+    pub fn start_trace_compare(&mut self, options: CompareOptions) -> io::Result<()> {
+        self.trace_compare = Some(TraceComparer::new(options)?);
+        Ok(())
+    }
+
+    pub fn stop_trace_compare(&mut self) {
+        self.trace_compare = None;
+    }
+
+    pub fn trace_compare(&self) -> Option<&TraceComparer> {
+        self.trace_compare.as_ref()
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // returns true if emulation can't continue right now (the cpu is
+    // stopped or the debugger is paused)
     pub fn tick(&mut self) -> bool {
-        if self.cpu.stopped { return true }
+        if self.cpu.stopped || self.debugger.is_paused() { return true }
 
+        let was_halted = self.cpu.halted;
         {
             let mut mmu = (*self.mmu).borrow_mut();
+            mmu.clock += 1;
             Interupt::handle(&mut mmu.interupts, &mut self.cpu);
         }
 
+        // only trace instructions once it's too late for an interrupt to
+        // replace them, otherwise the log gets ones that never ran
+        if self.cpu.fetch_completes_this_tick() && !self.fetch_traced {
+            self.fetch_traced = true;
+            if self.trace_instruction() { return true }
+        }
+
         self.cpu.tick();
+        if self.cpu.new_instruction_fetched {
+            self.fetch_traced = false;
+            let mmu = (*self.mmu).borrow();
+            let ctx = ExprContext::new(self.cpu.registers(), &mmu);
+            self.debugger.on_instruction_fetched(&ctx, mmu.peek_byte(self.cpu.instruction_pc()));
+        }
+
         self.ppu.tick();
         
         let mut mmu = (*self.mmu).borrow_mut();
+        if self.ppu.vblank_started {
+            self.frames += 1;
+            self.frame_start_cycle = mmu.clock;
+            mmu.apply_cheats();
+        }
+
         mmu.dma_tick();
         mmu.spu.tick();
         
@@ -95,6 +495,78 @@ impl GameBoy {
             mmu.interupts.request_interupt(InterruptFlag::Timer)
         }
 
-        self.cpu.stopped
+        self.autosave_clock += 1;
+        if self.autosave_clock == AUTOSAVE_INTERVAL {
+            self.autosave_clock = 0;
+            mmu.save_cartridge();
+        }
+
+        if let Some(hit) = mmu.watchpoints.take_hit() {
+            self.debugger.on_watchpoint_hit(hit);
+        }
+
+        if self.timeline.is_recording() {
+            // the cpu wakes up before it services anything
+            let mut events = Vec::new();
+            if self.cpu.halted != was_halted {
+                events.push(if self.cpu.halted { TimelineEvent::HaltEntered } else { TimelineEvent::HaltExited });
+            }
+            events.append(&mut mmu.interupts.events);
+            events.append(&mut mmu.timeline_events);
+
+            let state = self.ppu.state_from(&mmu);
+            for event in events {
+                self.timeline.record(TimelineEntry {
+                    cycle: mmu.clock,
+                    frame: self.frames,
+                    frame_cycle: mmu.clock - self.frame_start_cycle,
+                    ly: state.ly,
+                    dot: state.dot,
+                    event
+                });
+            }
+        } else {
+            mmu.interupts.events.clear();
+            mmu.timeline_events.clear();
+        }
+
+        if mmu.has_events() {
+            let events = mmu.take_events();
+            if let Some(callback) = &mut self.event_callback {
+                for event in &events {
+                    callback(event);
+                }
+            }
+        }
+
+        self.cpu.stopped || self.debugger.is_paused()
+    }
+
+    // returns true if the comparison finished and paused the debugger
+    fn trace_instruction(&mut self) -> bool {
+        let mmu = (*self.mmu).borrow();
+        let registers = self.cpu.registers();
+
+        if let Some(trace) = &mut self.trace {
+            if let Err(err) = trace.on_instruction(registers, self.frames, &mmu, self.debugger.symbols()) {
+                println!("Unable to write trace to {}, stopping it: {}", trace.options().path.display(), err);
+                self.trace = None;
+            }
+        }
+
+        if let Some(compare) = &mut self.trace_compare {
+            if compare.on_instruction(registers, self.frames, &mmu) {
+                self.debugger.on_trace_compare_finished();
+                return true;
+            }
+        }
+
+        false
     }
-}
\ No newline at end of file
+}
+
+impl MemoryView for GameBoy {
+    fn read(&self, addr: u16) -> u8 {
+        self.peek_byte(addr)
+    }
+}
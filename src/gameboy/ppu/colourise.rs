@@ -0,0 +1,239 @@
+use crate::gameboy::cartridge::header::CartridgeHeader;
+
+use super::{PixelSource, palette::DmgPalette};
+
+// Colouring DMG games the way a GBC does: the bg/window, OBP0 sprites and
+// OBP1 sprites each get their own four colours. The GBC boot rom picks them
+// by adding up the title bytes of Nintendo published games and looking the
+// sum up in a table (with the title's 4th letter to tell apart games whose
+// sums clash), or the player picks one by holding a direction (and A or B)
+// while the logo is up.
+//
+// The tables below are the ones in the CGB boot rom (laid out like in the
+// SameBoy and ISSOtm gb-bootroms disassemblies, Pan Docs has them too under
+// "Power Up Sequence", compatibility palettes).
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colourisation {
+    pub bg: DmgPalette,
+    pub obj0: DmgPalette,
+    pub obj1: DmgPalette
+}
+
+impl Default for Colourisation {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Colourisation {
+    // what the gbc uses for games that aren't in its table (right + A)
+    pub const DEFAULT: Colourisation = combination(0);
+
+    // the button combinations for picking one on the boot logo
+    pub const MANUAL: [(&'static str, Colourisation); 12] = [
+        ("Up", combination(5)),
+        ("Up + A", combination(43)),
+        ("Up + B", combination(28)),
+        ("Left", combination(48)),
+        ("Left + A", combination(40)),
+        ("Left + B", combination(7)),
+        ("Down", combination(8)),
+        ("Down + A", combination(3)),
+        ("Down + B", combination(49)),
+        ("Right", combination(1)),
+        ("Right + A", Colourisation::DEFAULT),
+        ("Right + B", combination(6))
+    ];
+
+    // what a gbc would pick for this game, or the default
+    pub fn for_header(header: &CartridgeHeader) -> Self {
+        if !is_nintendo(header) {
+            return Self::DEFAULT;
+        }
+
+        lookup(&header.raw_title).unwrap_or(Self::DEFAULT)
+    }
+
+    pub fn palette_for(&self, source: PixelSource) -> &DmgPalette {
+        match source {
+            PixelSource::Background | PixelSource::Window => &self.bg,
+            PixelSource::Obj0 => &self.obj0,
+            PixelSource::Obj1 => &self.obj1
+        }
+    }
+}
+
+// the boot rom's colours (RGB555), 4 to a palette
+const BOOT_COLOURS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000,
+    0x639F, 0x4279, 0x15B0, 0x04CB,
+    0x7FFF, 0x6E31, 0x454A, 0x0000,
+    0x7FFF, 0x1BEF, 0x0200, 0x0000,
+    0x7FFF, 0x421F, 0x1CF2, 0x0000,
+    0x7FFF, 0x5294, 0x294A, 0x0000,
+    0x7FFF, 0x03FF, 0x012F, 0x0000,
+    0x7FFF, 0x03EF, 0x01D6, 0x0000,
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000,
+    0x7E74, 0x03FF, 0x0180, 0x0000,
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B,
+    0x7ED6, 0x4BFF, 0x2175, 0x0000,
+    0x53FF, 0x4A5F, 0x7E52, 0x0000,
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0,
+    0x03ED, 0x7FFF, 0x255F, 0x0000,
+    0x036A, 0x021F, 0x03FF, 0x7FFF,
+    0x7FFF, 0x01DF, 0x0112, 0x0000,
+    0x231F, 0x035F, 0x00F2, 0x0009,
+    0x7FFF, 0x03EA, 0x011F, 0x0000,
+    0x299F, 0x001A, 0x000C, 0x0000,
+    0x7FFF, 0x027F, 0x001F, 0x0000,
+    0x7FFF, 0x03E0, 0x0206, 0x0120,
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00,
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+    0x7FFF, 0x03FF, 0x001F, 0x0000,
+    0x03FF, 0x001F, 0x000C, 0x0000,
+    0x7FFF, 0x033F, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037F, 0x7FFF,
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000,
+    0x7FFF, 0x1BEF, 0x6180, 0x0000
+];
+
+const fn at(palette: usize) -> usize {
+    palette * 4
+}
+
+// where the obj0, obj1 and bg colours start in BOOT_COLOURS. a few start one
+// colour early and get the last colour of the palette before theirs
+const COMBINATIONS: [[usize; 3]; 51] = [
+    [at(4), at(4), at(29)], // right + A, the default
+    [at(18), at(18), at(18)], // right
+    [at(20), at(20), at(20)],
+    [at(24), at(24), at(24)], // down + A
+    [at(9), at(9), at(9)],
+    [at(0), at(0), at(0)], // up
+    [at(27), at(27), at(27)], // right + B
+    [at(5), at(5), at(5)], // left + B
+    [at(12), at(12), at(12)], // down
+    [at(26), at(26), at(26)],
+    [at(16), at(8), at(8)],
+    [at(4), at(28), at(28)],
+    [at(4), at(2), at(2)],
+    [at(3), at(4), at(4)],
+    [at(4), at(29), at(29)],
+    [at(28), at(4), at(28)],
+    [at(2), at(17), at(2)],
+    [at(16), at(16), at(8)],
+    [at(4), at(4), at(7)],
+    [at(4), at(4), at(18)],
+    [at(4), at(4), at(20)],
+    [at(19), at(19), at(9)],
+    [at(4) - 1, at(4) - 1, at(11)],
+    [at(17), at(17), at(2)],
+    [at(4), at(4), at(2)],
+    [at(4), at(4), at(3)],
+    [at(28), at(28), at(0)],
+    [at(3), at(3), at(0)],
+    [at(0), at(0), at(1)], // up + B
+    [at(18), at(22), at(18)],
+    [at(20), at(22), at(20)],
+    [at(24), at(22), at(24)],
+    [at(16), at(22), at(8)],
+    [at(17), at(4), at(13)],
+    [at(28) - 1, at(0), at(14)],
+    [at(28) - 1, at(4), at(15)],
+    [at(19), at(22), at(9)],
+    [at(16), at(28), at(10)],
+    [at(4), at(23), at(28)],
+    [at(17), at(22), at(2)],
+    [at(4), at(0), at(2)], // left + A
+    [at(4), at(28), at(3)],
+    [at(28), at(3), at(0)],
+    [at(3), at(28), at(4)], // up + A
+    [at(21), at(28), at(4)],
+    [at(3), at(28), at(0)],
+    [at(25), at(3), at(28)],
+    [at(0), at(28), at(8)],
+    [at(4), at(3), at(28)], // left
+    [at(28), at(3), at(6)], // down + B
+    [at(4), at(28), at(29)]
+];
+
+// past this the checksum clashes with another game's, and the 4th letter of
+// the title has to match FOURTH_LETTERS too. if it doesn't, the next row
+// (DUPLICATE_ROW on) has the same checksums with other letters
+const FIRST_DUPLICATE: usize = 65;
+const DUPLICATE_ROW: usize = 14;
+const FOURTH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+const TITLE_CHECKSUMS: [u8; 79] = [
+    0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58,
+    0xC9, 0x3E, 0x70, 0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95,
+    0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97, 0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6,
+    0xA2, 0x49, 0x4E, 0x43, 0x68, 0xE0, 0x8B, 0xF0, 0xCE, 0x0C, 0x29, 0xE8, 0xB7,
+    0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F, 0x6B,
+    0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D,
+    0xF4
+];
+
+// the combination for each checksum (and for the duplicate rows after it)
+const TITLE_COMBINATIONS: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44,
+    21, 32, 31, 20, 5, 33, 13, 14, 5, 29, 5, 18, 9, 3, 2, 26,
+    25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34,
+    5, 42, 6, 5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0,
+    39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39, 24, 31, 50, 17,
+    46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29
+];
+
+// 5 bits a channel to 8 (rounded, so 0x1F is 0xFF)
+const fn expand(channel: u16) -> u32 {
+    (channel as u32 * 255 + 15) / 31
+}
+
+const fn rgb555(colour: u16) -> u32 {
+    (expand(colour & 0x1F) << 16) | (expand((colour >> 5) & 0x1F) << 8) | expand((colour >> 10) & 0x1F)
+}
+
+const fn boot_palette(start: usize) -> DmgPalette {
+    DmgPalette::from_rgb([
+        rgb555(BOOT_COLOURS[start]),
+        rgb555(BOOT_COLOURS[start + 1]),
+        rgb555(BOOT_COLOURS[start + 2]),
+        rgb555(BOOT_COLOURS[start + 3])
+    ])
+}
+
+// one of the boot rom's palette combinations
+pub const fn combination(index: usize) -> Colourisation {
+    let [obj0, obj1, bg] = COMBINATIONS[index];
+    Colourisation { bg: boot_palette(bg), obj0: boot_palette(obj0), obj1: boot_palette(obj1) }
+}
+
+// the gbc only colours games published by Nintendo
+pub fn is_nintendo(header: &CartridgeHeader) -> bool {
+    header.old_licensee_code == 0x01 || (header.old_licensee_code == 0x33 && header.new_licensee_code == "01")
+}
+
+// the sum of the 16 title bytes (0x134-0x143)
+pub fn title_checksum(raw_title: &[u8]) -> u8 {
+    raw_title.iter().take(16).fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+// the boot rom's search: the first entry with the checksum, then for the
+// ones that clash, along the rows until the 4th letter matches
+pub fn lookup(raw_title: &[u8]) -> Option<Colourisation> {
+    let checksum = title_checksum(raw_title);
+    let mut index = TITLE_CHECKSUMS.iter().position(|sum| *sum == checksum)?;
+
+    if index >= FIRST_DUPLICATE {
+        let fourth = raw_title.get(3).copied().unwrap_or(0);
+        while FOURTH_LETTERS[index - FIRST_DUPLICATE] != fourth {
+            index += DUPLICATE_ROW;
+            if index >= TITLE_COMBINATIONS.len() {
+                return None;
+            }
+        }
+    }
+
+    Some(combination(TITLE_COMBINATIONS[index] as usize))
+}
@@ -1,15 +1,32 @@
-use std::{borrow::Borrow, cell::{RefCell}, cmp::Ordering, collections::VecDeque, rc::Rc};
-use self::{bg_fetcher::{FetchMode, BgFetcher}, sprite_fetcher::SpriteFetcher};
+use std::{borrow::Borrow, cell::{RefCell}, cmp::Ordering, collections::VecDeque, mem, rc::Rc};
+use self::{bg_fetcher::{FetchMode, BgFetcher}, colourise::Colourisation, palette::{DmgPalette, Rgba}, sprite_fetcher::SpriteFetcher};
 
-use super::{interupt::InterruptFlag, mmu::Mmu};
+use super::{debugger::{oam::{MAX_SPRITES_PER_LINE, OamScan}, raster::{LineRegisters, RasterHistory, RegisterWrite}}, interupt::InterruptFlag, mmu::{Mmu, PALETTE}};
 
 mod bg_fetcher;
+pub mod colourise;
+pub mod palette;
 mod sprite_fetcher;
 
 pub struct Ppu {
     mmu: Rc<RefCell<Mmu>>,
     mode: PpuMode,
+    // greys from mmu::PALETTE, and the same frame in the palette's colours
     pub frame_buffer: [u8; 160 * 144],
+    pub rgba_frame_buffer: Vec<u8>,
+    pub palette: DmgPalette,
+
+    // gbc style colours for each layer, used instead of palette
+    pub colourisation: Option<Colourisation>,
+
+    // which layer each pixel in the frame buffer came from
+    pub pixel_sources: [PixelSource; 160 * 144],
+    pub layers: Layers,
+
+    // draw every sprite on a line rather than the first 10, the extra ones
+    // are mixed in without being fetched so mode 3 takes as long as it would
+    pub unlimited_sprites: bool,
+    extra_sprites: Vec<Sprite>,
 
     fifo_sprite_buffer: VecDeque<Sprite>,
     fifo_sprite_buffer_peek: Option<Sprite>,
@@ -36,6 +53,13 @@ pub struct Ppu {
 
     pub draw_flag: bool,
 
+    // only true for the tick that entered vblank
+    pub vblank_started: bool,
+
+    // the sprites picked for the current line, for the oam viewer
+    pub oam_scan: OamScan,
+    pub raster: RasterHistory,
+
     ly_153_early: bool,
 
     power_on_line_0: bool
@@ -45,6 +69,8 @@ pub struct Sprite {
     y: u8,
     x: u8,
     tile_num: u16,
+    // 8 or 16, from lcdc when oam was scanned
+    height: u8,
 
     sprite_palette: usize,
     xflip: bool,
@@ -53,12 +79,42 @@ pub struct Sprite {
 }
 
 pub struct FifoPixel {
+    // x of the sprite it came from, lower x wins where sprites overlap
+    sprite_x: u8,
     sprite_palette: usize,
     sprite_color_bit: u8,
     belowbg: bool
 }
 
-#[derive(Clone, Copy)]
+// Switches for hiding layers while debugging or taking screenshots. They're
+// only looked at when pixels are mixed, LCDC is left alone and the fetchers
+// still run, so games (and mode 3 timing) can't tell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layers {
+    pub background: bool,
+    pub window: bool,
+    pub sprites: bool
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self {
+            background: true,
+            window: true,
+            sprites: true
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelSource {
+    Background,
+    Window,
+    Obj0,
+    Obj1
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PpuMode {
     HBlank = 0, // mode 0
     VBlank = 1, // mode 1
@@ -78,6 +134,21 @@ impl PpuMode {
    }  
 }
 
+// A read only look at where the ppu is, see GameBoy::ppu_state
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PpuState {
+    pub lcd_on: bool,
+    pub mode: PpuMode,
+    pub ly: u8,
+
+    // dots (clock cycles) into the current line (0-455) and frame
+    pub dot: u64,
+    pub frame_dot: u64,
+
+    // which line of the window is drawn next
+    pub window_line: u8
+}
+
 pub enum LcdControlFlag {
     // 1: on, 0: off
     BGEnable =             0b0000_0001, 
@@ -122,6 +193,14 @@ impl Ppu {
             mmu,
             mode: PpuMode::OAM,
             frame_buffer: [0; 160 * 144],
+            rgba_frame_buffer: vec![0; 160 * 144 * 4],
+            palette: DmgPalette::default(),
+            colourisation: None,
+            pixel_sources: [PixelSource::Background; 160 * 144],
+            layers: Layers::default(),
+
+            unlimited_sprites: false,
+            extra_sprites: Vec::new(),
 
             fifo_sprite_buffer: VecDeque::new(),
             fifo_sprite_buffer_peek: None,
@@ -147,6 +226,9 @@ impl Ppu {
             wy_ly_equality_latch: false,
 
             draw_flag: false,
+            vblank_started: false,
+            oam_scan: OamScan::default(),
+            raster: RasterHistory::new(),
 
             ly_153_early: false,
 
@@ -154,6 +236,51 @@ impl Ppu {
         }
     }
 
+    pub fn state(&self) -> PpuState {
+        self.state_from(&(*self.mmu).borrow())
+    }
+
+    // for when the mmu is already borrowed
+    pub(crate) fn state_from(&self, mmu: &Mmu) -> PpuState {
+        PpuState {
+            lcd_on: mmu.io[0x40] & LcdControlFlag::LCDDisplayEnable as u8 != 0,
+            mode: self.mode,
+            ly: mmu.io[0x44],
+            dot: self.line_clock_cycles,
+            frame_dot: self.frame_clock_cycles,
+            window_line: self.window_internal_line_counter
+        }
+    }
+
+    pub fn set_palette(&mut self, palette: DmgPalette) {
+        self.palette = palette;
+        self.repaint();
+    }
+
+    pub fn set_colourisation(&mut self, colourisation: Option<Colourisation>) {
+        self.colourisation = colourisation;
+        self.repaint();
+    }
+
+    fn palette_for(&self, source: PixelSource) -> &DmgPalette {
+        match &self.colourisation {
+            Some(colourisation) => colourisation.palette_for(source),
+            None => &self.palette
+        }
+    }
+
+    fn colour(&self, source: PixelSource, shade: u8) -> Rgba {
+        self.palette_for(source).shades[shade as usize]
+    }
+
+    // redo the rgba frame from the greys, so a new palette shows up straight away
+    fn repaint(&mut self) {
+        for i in 0..self.frame_buffer.len() {
+            let rgba = self.palette_for(self.pixel_sources[i]).colour_for_grey(self.frame_buffer[i]);
+            self.rgba_frame_buffer[i * 4..i * 4 + 4].copy_from_slice(&rgba);
+        }
+    }
+
     fn get_scan_line(&self) -> u8 {
         (*self.mmu).borrow().io[0x44]
     }
@@ -164,6 +291,11 @@ impl Ppu {
         current_scan_line + 1
     }
 
+    // LY reads 0 for most of line 153, this doesn't
+    fn current_line(&self) -> u8 {
+        if self.ly_153_early { 153 } else { self.get_scan_line() }
+    }
+
     fn set_scan_line(&mut self, val: u8) {
         (*self.mmu).borrow_mut().io[0x44] = val;
     }
@@ -196,6 +328,11 @@ impl Ppu {
     const STAT_CHANGE_OFFSET: u64 = 4;
 
     pub fn tick(&mut self) {
+        self.vblank_started = false;
+
+        // writes to scx etc since the last tick, dropped if the lcd is off
+        let raster_writes = mem::take(&mut (*self.mmu).borrow_mut().raster_writes);
+
         {
             let mut mmu = (*self.mmu).borrow_mut();
             if mmu.io[0x40] >> 7 == 0 && !self.reset {
@@ -204,6 +341,11 @@ impl Ppu {
                 self.frame_clock_cycles = 0;
                 self.mode = PpuMode::HBlank;
                 self.frame_buffer = [220; 160 * 144];
+                let off = self.colour(PixelSource::Background, 0);
+                for rgba in self.rgba_frame_buffer.chunks_mut(4) {
+                    rgba.copy_from_slice(&off);
+                }
+                self.pixel_sources = [PixelSource::Background; 160 * 144];
                 self.reset = true;
                 mmu.io[0x44] = 0; // set ly to 0
                 mmu.io[0x41] = mmu.io[0x41] & 0b11111100;
@@ -226,6 +368,7 @@ impl Ppu {
             self.mode_clock_cycles = 6;
             self.frame_clock_cycles = 6;
             self.mode = PpuMode::OAM;
+            self.raster.new_frame();
 
             {
                 let mut mmu = (*self.mmu).borrow_mut();
@@ -239,6 +382,13 @@ impl Ppu {
             self.check_ly_eq_lyc();
         }
 
+        if !raster_writes.is_empty() {
+            let line = self.current_line();
+            for (register, value) in raster_writes {
+                self.raster.write(line, RegisterWrite { register, value, dot: self.line_clock_cycles });
+            }
+        }
+
         self.mode_clock_cycles += 1;
         self.line_clock_cycles += 1;
         self.frame_clock_cycles += 1;
@@ -276,6 +426,7 @@ impl Ppu {
 
                         // notify safe draw
                         self.draw_flag = true;
+                        self.vblank_started = true;
                     }
                     else {
                         self.mode = PpuMode::OAM;
@@ -325,6 +476,7 @@ impl Ppu {
 
                         // wy == ly latch is reset in VBlank
                         self.wy_ly_equality_latch = false;
+                        self.raster.new_frame();
                         
                         self.mode = PpuMode::OAM;
                         self.mmu.borrow_mut().lock_oam = true;
@@ -359,6 +511,8 @@ impl Ppu {
                     self.fifo_sprite_buffer_peek = None;
 
                     let mut sprites = Vec::new();
+                    self.oam_scan.sprites.clear();
+                    self.extra_sprites.clear();
 
                     {
                         let mmu = (*self.mmu).borrow();
@@ -366,9 +520,10 @@ impl Ppu {
                         let sprite_size = if ldlc_flags & LcdControlFlag::OBJSize as u8 != 0 
                             {16} else {8};
                         let scan_line = mmu.io[0x44];
+                        self.oam_scan.line = scan_line;
 
                         let mut i = 0;
-                        while i < 40 && sprites.len() < 10 {
+                        while i < 40 && (sprites.len() < MAX_SPRITES_PER_LINE || self.unlimited_sprites) {
                             let sprite_addr = (i as usize) * 4;
                     
                             let sprite_y = mmu.sprite_table[sprite_addr];
@@ -387,6 +542,7 @@ impl Ppu {
                                 x: sprite_x,
                                 y: sprite_y,
                                 tile_num,
+                                height: sprite_size as u8,
                                 sprite_palette,
                                 xflip,
                                 yflip,
@@ -398,7 +554,12 @@ impl Ppu {
                             let cond3 = scan_line as u16 + 16 < sprite_y as u16 + sprite_size;
                             
                             if cond1 && cond2 && cond3 {
-                                sprites.push(sprite);
+                                if sprites.len() < MAX_SPRITES_PER_LINE {
+                                    sprites.push(sprite);
+                                    self.oam_scan.sprites.push(i as usize);
+                                } else {
+                                    self.extra_sprites.push(sprite);
+                                }
                             }
 
                             i += 1;
@@ -420,6 +581,8 @@ impl Ppu {
                         self.fifo_sprite_buffer.push_back(s);
                     }
 
+                    self.extra_sprites.sort_by_key(|sprite| sprite.x);
+
                     self.fifo_sprite_buffer_peek = self.fifo_sprite_buffer.pop_front();
 
                     self.bg_fetcher.reset();
@@ -435,7 +598,10 @@ impl Ppu {
 
                     self.mode_clock_cycles = 0;
                     self.mode = PpuMode::VRAM;
-                    self.mmu.borrow_mut().lock_vram = true;
+
+                    let mut mmu = self.mmu.borrow_mut();
+                    mmu.lock_vram = true;
+                    self.raster.mode3_started(mmu.io[0x44], LineRegisters::read(&mmu.io));
                 }
             }
 
@@ -465,7 +631,10 @@ impl Ppu {
         match self.line_clock_cycles {
             83 => {
                 self.set_mode_lcdc(PpuMode::VRAM);
-                self.mmu.borrow_mut().lock_vram = true;
+
+                let mut mmu = self.mmu.borrow_mut();
+                mmu.lock_vram = true;
+                self.raster.mode3_started(mmu.io[0x44], LineRegisters::read(&mmu.io));
             }
 
             257 => {
@@ -584,24 +753,83 @@ impl Ppu {
             color_bit = 0;
         }
 
-        let mut color = mmu.bg_palette[color_bit as usize];
+        // a hidden layer is drawn as colour 0, so sprites behind it show up
+        let mut source = if self.fifo_wy_ly_equal { PixelSource::Window } else { PixelSource::Background };
+        let layer_shown = if self.fifo_wy_ly_equal { self.layers.window } else { self.layers.background };
+        if !layer_shown {
+            color_bit = 0;
+        }
+
+        let mut shade = mmu.bg_palette[color_bit as usize];
+
+        let mut sprite_pixel = self.sprite_fifo.pop_front();
+
+        // the extra sprites come after the fetched ones in oam, so they only
+        // win with a lower x or where the fetched ones are see through
+        let sprites_on = ldlc_flags & LcdControlFlag::OBJEnable as u8 != 0;
+        if sprites_on && !self.extra_sprites.is_empty() {
+            if let Some(px) = self.extra_sprite_pixel(&mmu, scan_line) {
+                let extra_wins = match &sprite_pixel {
+                    Some(fetched) => fetched.sprite_color_bit == 0 || px.sprite_x < fetched.sprite_x,
+                    None => true
+                };
+
+                if extra_wins {
+                    sprite_pixel = Some(px);
+                }
+            }
+        }
 
-        let sprite_pixel = self.sprite_fifo.pop_front();
         if sprite_pixel.is_some() {
             let sprite_pixel = sprite_pixel.unwrap();
 
             let skip = (sprite_pixel.belowbg && color_bit != 0) || sprite_pixel.sprite_color_bit == 0;
 
-            if !skip {
-                color = mmu.sprite_palette[sprite_pixel.sprite_palette][sprite_pixel.sprite_color_bit as usize];
+            if !skip && self.layers.sprites {
+                shade = mmu.sprite_palette[sprite_pixel.sprite_palette][sprite_pixel.sprite_color_bit as usize];
+                source = if sprite_pixel.sprite_palette == 0 { PixelSource::Obj0 } else { PixelSource::Obj1 };
             }
         }
 
         let fb_offset = (scan_line as usize * 160) + self.fifo_current_x;
 
-        self.frame_buffer[fb_offset] = color;
+        self.frame_buffer[fb_offset] = PALETTE[shade as usize];
+        let rgba = self.colour(source, shade);
+        self.rgba_frame_buffer[fb_offset * 4..fb_offset * 4 + 4].copy_from_slice(&rgba);
+        self.pixel_sources[fb_offset] = source;
 
         self.fifo_current_x += 1;
         return self.fifo_current_x == 160 
     }
+
+    // the first non transparent pixel of the sprites past the 10 sprite
+    // limit, lowest x first (then oam order) like the fetched ones
+    fn extra_sprite_pixel(&self, mmu: &Mmu, scan_line: u8) -> Option<FifoPixel> {
+        let x = self.fifo_current_x + 8;
+
+        for sprite in self.extra_sprites.iter() {
+            let sprite_x = sprite.x as usize;
+            if x < sprite_x || x >= sprite_x + 8 { continue }
+
+            let row = scan_line.wrapping_sub(sprite.y.wrapping_sub(16)) as u16;
+            let row = if sprite.yflip { sprite.height as u16 - 1 - row } else { row };
+            let column = x - sprite_x;
+
+            let addr = (sprite.tile_num * 16 + row * 2) as usize;
+            let xbit = 1 << (if sprite.xflip { column } else { 7 - column });
+            let colnr = (if mmu.gpu_vram[addr] & xbit != 0 { 1 } else { 0 }) |
+                (if mmu.gpu_vram[addr + 1] & xbit != 0 { 2 } else { 0 });
+
+            if colnr != 0 {
+                return Some(FifoPixel {
+                    sprite_x: sprite.x,
+                    belowbg: sprite.belowbg,
+                    sprite_color_bit: colnr,
+                    sprite_palette: sprite.sprite_palette
+                });
+            }
+        }
+
+        None
+    }
 }
@@ -0,0 +1,107 @@
+use crate::gameboy::mmu::PALETTE;
+
+// The four colours a DMG screen is drawn with, lightest (shade 0) first.
+// The frame buffer is RGBA made from these, the greys in mmu::PALETTE are
+// still kept around as the shade buffer (the tests compare against those).
+
+pub type Rgba = [u8; 4];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DmgPalette {
+    pub shades: [Rgba; 4]
+}
+
+impl Default for DmgPalette {
+    fn default() -> Self {
+        Self::GREY
+    }
+}
+
+const fn rgb(colour: u32) -> Rgba {
+    [(colour >> 16) as u8, (colour >> 8) as u8, colour as u8, 0xFF]
+}
+
+impl DmgPalette {
+    // the greys this has always used
+    pub const GREY: DmgPalette = DmgPalette {
+        shades: [
+            [PALETTE[0], PALETTE[0], PALETTE[0], 0xFF],
+            [PALETTE[1], PALETTE[1], PALETTE[1], 0xFF],
+            [PALETTE[2], PALETTE[2], PALETTE[2], 0xFF],
+            [PALETTE[3], PALETTE[3], PALETTE[3], 0xFF]
+        ]
+    };
+
+    // the original pea soup green
+    pub const CLASSIC_GREEN: DmgPalette = DmgPalette {
+        shades: [rgb(0x9BBC0F), rgb(0x8BAC0F), rgb(0x306230), rgb(0x0F380F)]
+    };
+
+    pub const POCKET: DmgPalette = DmgPalette {
+        shades: [rgb(0xC4CFA1), rgb(0x8B956D), rgb(0x4D533C), rgb(0x1F1F1F)]
+    };
+
+    // the Game Boy Light's backlight
+    pub const LIGHT: DmgPalette = DmgPalette {
+        shades: [rgb(0x00B581), rgb(0x009A71), rgb(0x00694A), rgb(0x004F3B)]
+    };
+
+    // colours as 0xRRGGBB, lightest first
+    pub const fn from_rgb(colours: [u32; 4]) -> Self {
+        Self {
+            shades: [rgb(colours[0]), rgb(colours[1]), rgb(colours[2]), rgb(colours[3])]
+        }
+    }
+
+    pub const PRESETS: [(&'static str, DmgPalette); 4] = [
+        ("Grey", DmgPalette::GREY),
+        ("Classic green", DmgPalette::CLASSIC_GREEN),
+        ("Pocket", DmgPalette::POCKET),
+        ("Light", DmgPalette::LIGHT)
+    ];
+
+    // a preset ("green", "pocket", ...) or four hex colours lightest first,
+    // "9BBC0F,8BAC0F,306230,0F380F" (a # in front of each is fine)
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        match text.to_lowercase().as_str() {
+            "grey" | "gray" => return Ok(Self::GREY),
+            "green" | "classic" | "dmg" => return Ok(Self::CLASSIC_GREEN),
+            "pocket" | "mgb" => return Ok(Self::POCKET),
+            "light" => return Ok(Self::LIGHT),
+            _ => {}
+        }
+
+        let colours: Vec<&str> = text.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|colour| !colour.is_empty())
+            .collect();
+
+        if colours.len() != 4 {
+            return Err(format!("Expected a palette name or 4 hex colours, got {}", text));
+        }
+
+        let mut shades = [[0u8; 4]; 4];
+        for (shade, colour) in shades.iter_mut().zip(colours.iter()) {
+            let hex = colour.trim_start_matches('#');
+            if hex.len() != 6 {
+                return Err(format!("Invalid colour {} (should be RRGGBB)", colour));
+            }
+
+            let value = u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid colour {} (should be RRGGBB)", colour))?;
+            *shade = rgb(value);
+        }
+
+        Ok(Self { shades })
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        Self::PRESETS.iter().find(|(_, palette)| palette == self).map(|(name, _)| *name)
+    }
+
+    // the colour for a grey from the shade buffer, the lcd being off (which
+    // isn't one of the four) is shade 0
+    pub fn colour_for_grey(&self, grey: u8) -> Rgba {
+        let shade = PALETTE.iter().position(|palette_grey| *palette_grey == grey).unwrap_or(0);
+        self.shades[shade]
+    }
+}
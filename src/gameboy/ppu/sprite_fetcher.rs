@@ -2,7 +2,7 @@ use std::{cell::RefCell, collections::VecDeque, rc::Rc};
 
 use crate::gameboy::mmu::Mmu;
 
-use super::{FifoPixel, LcdControlFlag, Sprite};
+use super::{FifoPixel, Sprite};
 
 
 pub struct SpriteFetcher {
@@ -38,15 +38,13 @@ impl SpriteFetcher {
 
         match self.cycle {
             2 => {
-                // could do some of this in OAM and store it in the sprite struct instead?
+                // the height comes from the oam scan, the row picked then is
+                // always inside the sprite even if lcdc changes since
                 let mmu = (*self.mmu).borrow();
-                let ldlc_flags = mmu.io[0x40];
-                let sprite_size: u8 = if ldlc_flags & LcdControlFlag::OBJSize as u8 != 0 
-                    {16} else {8};
                 let scan_line = mmu.io[0x44];
 
                 let tile_y: u16 = if sprite.yflip {
-                    let base = if sprite_size == 16 { 30 } else { 14 };
+                    let base = (sprite.height as u16 - 1) * 2;
                     base - ((scan_line - (sprite.y - 16)) as u16 * 2)
                 } else {
                     (scan_line.wrapping_sub(sprite.y.wrapping_sub(16))) as u16 * 2
@@ -91,6 +89,7 @@ impl SpriteFetcher {
                         (if self.data_high & xbit != 0 { 2 } else { 0 });
 
                     let px_data = FifoPixel {
+                        sprite_x: sprite.x,
                         belowbg: sprite.belowbg,
                         sprite_color_bit: colnr,
                         sprite_palette: sprite.sprite_palette 
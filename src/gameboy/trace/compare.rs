@@ -0,0 +1,417 @@
+use std::{collections::VecDeque, fmt, fs::File, io::{self, BufRead, BufReader, Read}, path::{Path, PathBuf}};
+
+use crate::gameboy::{cpu::{Registers, decode}, mmu::Mmu};
+
+use super::{BINARY_ENTRY_SIZE, BINARY_MAGIC, BINARY_VERSION, TraceEntry, TraceStart};
+
+// Runs a rom against a trace from another emulator and stops at the first
+// instruction where the registers don't match.
+//
+// The reference can be one of our binary traces or a text log with one
+// instruction per line of KEY:VALUE pairs (hex), like Gameboy Doctor's:
+//   A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02
+// Keys can be A F B C D E H L, AF BC DE HL, SP, PC and PCMEM in any order
+// and case, anything else on the line is ignored. Only what the reference
+// has gets compared.
+
+pub const DEFAULT_HISTORY: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceField {
+    A, F, B, C, D, E, H, L,
+    Sp,
+    Pc,
+    PcMem
+}
+
+impl TraceField {
+    pub const ALL: [TraceField; 11] = [
+        TraceField::A, TraceField::F, TraceField::B, TraceField::C, TraceField::D, TraceField::E,
+        TraceField::H, TraceField::L, TraceField::Sp, TraceField::Pc, TraceField::PcMem
+    ];
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TraceField::A => "A",
+            TraceField::F => "F",
+            TraceField::B => "B",
+            TraceField::C => "C",
+            TraceField::D => "D",
+            TraceField::E => "E",
+            TraceField::H => "H",
+            TraceField::L => "L",
+            TraceField::Sp => "SP",
+            TraceField::Pc => "PC",
+            TraceField::PcMem => "PCMEM"
+        }
+    }
+
+    fn format(self, entry: &TraceEntry) -> String {
+        let r = &entry.registers;
+        match self {
+            TraceField::A => format!("{:02X}", r.a),
+            TraceField::F => format!("{:02X}", r.f),
+            TraceField::B => format!("{:02X}", r.b),
+            TraceField::C => format!("{:02X}", r.c),
+            TraceField::D => format!("{:02X}", r.d),
+            TraceField::E => format!("{:02X}", r.e),
+            TraceField::H => format!("{:02X}", r.h),
+            TraceField::L => format!("{:02X}", r.l),
+            TraceField::Sp => format!("{:04X}", r.sp),
+            TraceField::Pc => format!("{:04X}", r.pc),
+            TraceField::PcMem => {
+                let m = &entry.pcmem;
+                format!("{:02X},{:02X},{:02X},{:02X}", m[0], m[1], m[2], m[3])
+            }
+        }
+    }
+}
+
+// An instruction from the reference trace
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReferenceEntry {
+    // line in a text trace, or entry number in a binary one (from 1)
+    pub line: usize,
+    pub entry: TraceEntry,
+
+    // TraceField bits of what the reference had
+    fields: u16
+}
+
+impl ReferenceEntry {
+    // None if the line doesn't have any registers on it
+    pub fn parse(line_number: usize, line: &str) -> Result<Option<Self>, String> {
+        let mut entry = TraceEntry::default();
+        let mut fields = 0u16;
+
+        for token in line.split_whitespace() {
+            let (key, value) = match token.split_once(':').or_else(|| token.split_once('=')) {
+                Some(pair) => pair,
+                None => continue
+            };
+
+            let key = key.to_uppercase();
+            let parse_byte = |value: &str| u8::from_str_radix(value, 16)
+                .map_err(|_| format!("Line {}: invalid value for {}: {}", line_number, key, value));
+            let parse_word = |value: &str| u16::from_str_radix(value, 16)
+                .map_err(|_| format!("Line {}: invalid value for {}: {}", line_number, key, value));
+
+            let r = &mut entry.registers;
+            let set: &[TraceField] = match key.as_str() {
+                "A" => { r.a = parse_byte(value)?; &[TraceField::A] }
+                "F" => { r.f = parse_byte(value)?; &[TraceField::F] }
+                "B" => { r.b = parse_byte(value)?; &[TraceField::B] }
+                "C" => { r.c = parse_byte(value)?; &[TraceField::C] }
+                "D" => { r.d = parse_byte(value)?; &[TraceField::D] }
+                "E" => { r.e = parse_byte(value)?; &[TraceField::E] }
+                "H" => { r.h = parse_byte(value)?; &[TraceField::H] }
+                "L" => { r.l = parse_byte(value)?; &[TraceField::L] }
+                "AF" | "BC" | "DE" | "HL" => {
+                    let [hi, lo] = parse_word(value)?.to_be_bytes();
+                    match key.as_str() {
+                        "AF" => { r.a = hi; r.f = lo; &[TraceField::A, TraceField::F] }
+                        "BC" => { r.b = hi; r.c = lo; &[TraceField::B, TraceField::C] }
+                        "DE" => { r.d = hi; r.e = lo; &[TraceField::D, TraceField::E] }
+                        _ => { r.h = hi; r.l = lo; &[TraceField::H, TraceField::L] }
+                    }
+                }
+                "SP" => { r.sp = parse_word(value)?; &[TraceField::Sp] }
+                "PC" => { r.pc = parse_word(value)?; &[TraceField::Pc] }
+                "PCMEM" => {
+                    let bytes: Vec<&str> = value.split(',').collect();
+                    if bytes.len() != 4 {
+                        return Err(format!("Line {}: PCMEM needs 4 bytes: {}", line_number, value));
+                    }
+
+                    for (i, byte) in bytes.iter().enumerate() {
+                        entry.pcmem[i] = parse_byte(byte)?;
+                    }
+                    &[TraceField::PcMem]
+                }
+                _ => continue
+            };
+
+            for field in set {
+                fields |= field.bit();
+            }
+        }
+
+        if fields == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self { line: line_number, entry, fields }))
+    }
+
+    pub fn has(&self, field: TraceField) -> bool {
+        self.fields & field.bit() != 0
+    }
+
+    pub fn mismatches(&self, actual: &TraceEntry) -> Vec<TraceField> {
+        TraceField::ALL.iter()
+            .copied()
+            .filter(|field| self.has(*field) && field.format(&self.entry) != field.format(actual))
+            .collect()
+    }
+}
+
+enum Source {
+    Text(io::Lines<BufReader<File>>),
+    Binary(BufReader<File>)
+}
+
+pub struct ReferenceTrace {
+    source: Source,
+    line: usize
+}
+
+impl ReferenceTrace {
+    // binary traces are recognised by their header, anything else is read as text
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let source = if reader.fill_buf()?.starts_with(BINARY_MAGIC) {
+            let mut header = [0u8; 8];
+            reader.read_exact(&mut header)?;
+
+            if header[4] != BINARY_VERSION {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unsupported binary trace version {}", header[4])));
+            }
+
+            Source::Binary(reader)
+        } else {
+            Source::Text(reader.lines())
+        };
+
+        Ok(Self { source, line: 0 })
+    }
+
+    pub fn next_entry(&mut self) -> Result<Option<ReferenceEntry>, String> {
+        match &mut self.source {
+            Source::Binary(reader) => {
+                let mut bytes = [0u8; BINARY_ENTRY_SIZE];
+                match reader.read_exact(&mut bytes) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(err) => return Err(err.to_string())
+                }
+
+                self.line += 1;
+                let fields = TraceField::ALL.iter().fold(0, |fields, field| fields | field.bit());
+                Ok(Some(ReferenceEntry { line: self.line, entry: TraceEntry::from_bytes(&bytes), fields }))
+            }
+
+            Source::Text(lines) => {
+                for line in lines {
+                    let line = line.map_err(|err| err.to_string())?;
+                    self.line += 1;
+
+                    if let Some(entry) = ReferenceEntry::parse(self.line, &line)? {
+                        return Ok(Some(entry));
+                    }
+                }
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    pub path: PathBuf,
+
+    // None starts comparing the first time pc gets to the reference's first pc,
+    // which skips the boot rom for logs that start at 0x0100
+    pub start: Option<TraceStart>,
+
+    // how many instructions before the divergence to report
+    pub history: usize
+}
+
+impl CompareOptions {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            start: None,
+            history: DEFAULT_HISTORY
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    // instructions compared before this one
+    pub index: u64,
+    pub expected: ReferenceEntry,
+    pub actual: TraceEntry,
+    pub mismatched: Vec<TraceField>,
+
+    // the instructions leading up to it, oldest first
+    pub history: Vec<TraceEntry>
+}
+
+#[derive(Clone, Debug)]
+pub enum CompareResult {
+    // the reference ran out and everything matched
+    Matched(u64),
+    Diverged(Box<Divergence>),
+    InvalidReference(String)
+}
+
+pub struct TraceComparer {
+    options: CompareOptions,
+    reference: ReferenceTrace,
+    next: Option<ReferenceEntry>,
+
+    start: TraceStart,
+    started: bool,
+    compared: u64,
+    history: VecDeque<TraceEntry>,
+    result: Option<CompareResult>
+}
+
+impl TraceComparer {
+    pub fn new(options: CompareOptions) -> io::Result<Self> {
+        let mut reference = ReferenceTrace::open(&options.path)?;
+        let next = reference.next_entry()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "The reference trace is empty"))?;
+
+        let start = match options.start {
+            Some(start) => start,
+            None if next.has(TraceField::Pc) => TraceStart::Pc(next.entry.registers.pc),
+            None => TraceStart::Boot
+        };
+
+        Ok(Self {
+            options,
+            reference,
+            next: Some(next),
+
+            start,
+            started: start == TraceStart::Boot,
+            compared: 0,
+            history: VecDeque::new(),
+            result: None
+        })
+    }
+
+    pub fn options(&self) -> &CompareOptions {
+        &self.options
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    pub fn compared(&self) -> u64 {
+        self.compared
+    }
+
+    pub fn result(&self) -> Option<&CompareResult> {
+        self.result.as_ref()
+    }
+
+    // returns true when the comparison has just finished, one way or another
+    pub(crate) fn on_instruction(&mut self, registers: Registers, frame: u64, mmu: &Mmu) -> bool {
+        if self.result.is_some() { return false }
+
+        if !self.started {
+            self.started = match self.start {
+                TraceStart::Boot => true,
+                TraceStart::Pc(pc) => registers.pc == pc,
+                TraceStart::Frame(start_frame) => frame >= start_frame
+            };
+
+            if !self.started { return false }
+        }
+
+        let expected = match self.next.take() {
+            Some(expected) => expected,
+            None => {
+                self.result = Some(CompareResult::Matched(self.compared));
+                return true;
+            }
+        };
+
+        let actual = TraceEntry::capture(registers, mmu);
+        let mismatched = expected.mismatches(&actual);
+
+        if !mismatched.is_empty() {
+            self.result = Some(CompareResult::Diverged(Box::new(Divergence {
+                index: self.compared,
+                expected,
+                actual,
+                mismatched,
+                history: self.history.iter().copied().collect()
+            })));
+            return true;
+        }
+
+        self.compared += 1;
+        if self.options.history > 0 {
+            if self.history.len() == self.options.history {
+                self.history.pop_front();
+            }
+            self.history.push_back(actual);
+        }
+
+        match self.reference.next_entry() {
+            Ok(Some(next)) => {
+                self.next = Some(next);
+                false
+            }
+
+            Ok(None) => {
+                self.result = Some(CompareResult::Matched(self.compared));
+                true
+            }
+
+            Err(err) => {
+                self.result = Some(CompareResult::InvalidReference(err));
+                true
+            }
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Diverged at instruction {} (line {} of the reference)", self.index, self.expected.line)?;
+        writeln!(f, "  {:<6} {:<12} {:<12}", "", "expected", "actual")?;
+
+        for field in TraceField::ALL.iter().filter(|field| self.expected.has(**field)) {
+            let marker = if self.mismatched.contains(field) { "<--" } else { "" };
+            writeln!(
+                f, "  {:<6} {:<12} {:<12} {}",
+                field.name(), field.format(&self.expected.entry), field.format(&self.actual), marker
+            )?;
+        }
+
+        if !self.history.is_empty() {
+            writeln!(f, "Last {} instructions:", self.history.len())?;
+            for entry in &self.history {
+                let instr = decode::decode(&entry.pcmem, entry.registers.pc);
+                writeln!(f, "  {:04X}  {:<20} {}", entry.registers.pc, instr.to_string(), entry.to_doctor_line())?;
+            }
+        }
+
+        let instr = decode::decode(&self.actual.pcmem, self.actual.registers.pc);
+        write!(f, "> {:04X}  {:<20} {}", self.actual.registers.pc, instr.to_string(), self.actual.to_doctor_line())
+    }
+}
+
+impl fmt::Display for CompareResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompareResult::Matched(count) => write!(f, "All {} instructions in the reference matched", count),
+            CompareResult::Diverged(divergence) => write!(f, "{}", divergence),
+            CompareResult::InvalidReference(err) => write!(f, "Unable to read the reference trace: {}", err)
+        }
+    }
+}
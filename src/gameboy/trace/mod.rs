@@ -0,0 +1,234 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::PathBuf};
+
+use super::{cpu::{Registers, decode}, debugger::symbols::Symbols, mmu::Mmu};
+
+pub mod compare;
+
+// Logs the cpu state before every instruction.
+//
+// GameboyDoctor: one line per instruction, the format used by
+// https://github.com/robert/gameboy-doctor
+//   A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02
+// (the doctor expects LY to always read 0x90, turn on GameBoy::set_doctor_ly
+// or logs will differ from its references once a rom waits on LY)
+//
+// Disassembly: the same registers plus the instruction, with labels if
+// there's a .sym file loaded
+//
+// Binary: "FTRC" and a version byte (padded to 8 bytes), then 16 bytes per
+// instruction: PC and SP (little endian), A F B C D E H L and PCMEM
+
+pub const BINARY_MAGIC: &[u8; 4] = b"FTRC";
+pub const BINARY_VERSION: u8 = 1;
+pub const BINARY_ENTRY_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    GameboyDoctor,
+    Disassembly,
+    Binary
+}
+
+impl TraceFormat {
+    // "doctor", "readable" or "binary"
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "doctor" | "gbdoctor" => Some(Self::GameboyDoctor),
+            "readable" | "disassembly" | "disas" => Some(Self::Disassembly),
+            "binary" | "bin" => Some(Self::Binary),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceStart {
+    // straight away, which is at boot if it's set up before the first tick
+    Boot,
+    Pc(u16),
+
+    // frames since the gameboy was created
+    Frame(u64)
+}
+
+impl TraceStart {
+    // "boot", "pc:0150" (hex) or "frame:60"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        if text == "boot" {
+            return Some(Self::Boot);
+        }
+
+        let (kind, value) = text.split_once(':')?;
+        match kind {
+            "pc" => {
+                let value = value.trim_start_matches("0x").trim_start_matches('$');
+                u16::from_str_radix(value, 16).ok().map(Self::Pc)
+            }
+
+            "frame" => value.parse().ok().map(Self::Frame),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceOptions {
+    pub path: PathBuf,
+    pub format: TraceFormat,
+    pub start: TraceStart
+}
+
+impl TraceOptions {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            format: TraceFormat::GameboyDoctor,
+            start: TraceStart::Boot
+        }
+    }
+}
+
+// One logged instruction
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TraceEntry {
+    pub registers: Registers,
+
+    // the 4 bytes starting at pc
+    pub pcmem: [u8; 4]
+}
+
+impl TraceEntry {
+    pub(crate) fn capture(registers: Registers, mmu: &Mmu) -> Self {
+        let pc = registers.pc;
+        let pcmem = [
+            mmu.peek_byte(pc),
+            mmu.peek_byte(pc.wrapping_add(1)),
+            mmu.peek_byte(pc.wrapping_add(2)),
+            mmu.peek_byte(pc.wrapping_add(3))
+        ];
+
+        Self { registers, pcmem }
+    }
+
+    pub fn to_doctor_line(&self) -> String {
+        let r = &self.registers;
+        format!(
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp, r.pc,
+            self.pcmem[0], self.pcmem[1], self.pcmem[2], self.pcmem[3]
+        )
+    }
+
+    pub fn to_bytes(&self) -> [u8; BINARY_ENTRY_SIZE] {
+        let r = &self.registers;
+        let pc = r.pc.to_le_bytes();
+        let sp = r.sp.to_le_bytes();
+
+        [
+            pc[0], pc[1], sp[0], sp[1],
+            r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l,
+            self.pcmem[0], self.pcmem[1], self.pcmem[2], self.pcmem[3]
+        ]
+    }
+
+    pub fn from_bytes(bytes: &[u8; BINARY_ENTRY_SIZE]) -> Self {
+        let registers = Registers {
+            pc: u16::from_le_bytes([bytes[0], bytes[1]]),
+            sp: u16::from_le_bytes([bytes[2], bytes[3]]),
+            a: bytes[4],
+            f: bytes[5],
+            b: bytes[6],
+            c: bytes[7],
+            d: bytes[8],
+            e: bytes[9],
+            h: bytes[10],
+            l: bytes[11]
+        };
+
+        Self {
+            registers,
+            pcmem: [bytes[12], bytes[13], bytes[14], bytes[15]]
+        }
+    }
+}
+
+pub struct TraceLogger {
+    options: TraceOptions,
+    writer: BufWriter<File>,
+    started: bool,
+    instructions: u64
+}
+
+impl TraceLogger {
+    pub fn new(options: TraceOptions) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(&options.path)?);
+
+        if options.format == TraceFormat::Binary {
+            writer.write_all(BINARY_MAGIC)?;
+            writer.write_all(&[BINARY_VERSION, 0, 0, 0])?;
+        }
+
+        Ok(Self {
+            started: options.start == TraceStart::Boot,
+            options,
+            writer,
+            instructions: 0
+        })
+    }
+
+    pub fn options(&self) -> &TraceOptions {
+        &self.options
+    }
+
+    // false while waiting for the pc or frame to start at
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    pub fn instructions_logged(&self) -> u64 {
+        self.instructions
+    }
+
+    pub(crate) fn on_instruction(&mut self, registers: Registers, frame: u64, mmu: &Mmu, symbols: &Symbols) -> io::Result<()> {
+        if !self.started {
+            self.started = match self.options.start {
+                TraceStart::Boot => true,
+                TraceStart::Pc(pc) => registers.pc == pc,
+                TraceStart::Frame(start_frame) => frame >= start_frame
+            };
+
+            if !self.started { return Ok(()) }
+        }
+
+        let entry = TraceEntry::capture(registers, mmu);
+
+        match self.options.format {
+            TraceFormat::GameboyDoctor => writeln!(self.writer, "{}", entry.to_doctor_line())?,
+            TraceFormat::Binary => self.writer.write_all(&entry.to_bytes())?,
+            TraceFormat::Disassembly => {
+                let pc = registers.pc;
+                let instr = decode::decode(&entry.pcmem, pc);
+                let name_for = |addr: u16| symbols.name_at(mmu.rom_bank_at(addr), addr).map(|name| name.to_owned());
+
+                if let Some(label) = name_for(pc) {
+                    writeln!(self.writer, "{}:", label)?;
+                }
+
+                writeln!(
+                    self.writer,
+                    "{:04X}  {:<24} A:{:02X} F:{:02X} BC:{:04X} DE:{:04X} HL:{:04X} SP:{:04X}",
+                    pc, instr.format_with_names(name_for),
+                    registers.a, registers.f, registers.bc(), registers.de(), registers.hl(), registers.sp
+                )?;
+            }
+        }
+
+        self.instructions += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
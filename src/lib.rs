@@ -1 +1,2 @@
-pub mod gameboy;
\ No newline at end of file
+pub mod filters;
+pub mod gameboy;
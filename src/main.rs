@@ -6,13 +6,16 @@ extern crate imgui_sdl2;
 extern crate gl;
 extern crate imgui_opengl_renderer;
 
-use std::{cell::RefCell, collections::VecDeque, ffi::c_void, process, rc::Rc, time::Duration};
+use std::{cell::RefCell, collections::{HashSet, VecDeque}, env, ffi::c_void, panic::{self, AssertUnwindSafe}, path::PathBuf, process, rc::Rc, time::Duration};
 
-use gameboy_rs::{gameboy::{GameBoy, spu::{ SAMPLES_PER_BUFFER}}};
+use gameboy_rs::{filters::{Filter, FrameBlender}, gameboy::{GameBoy, crash, events::EmulatorEvent, ppu::{Layers, PixelSource, colourise::Colourisation, palette::DmgPalette}, cartridge::{LoadOptions, header::CartridgeHeader, loader::load_rom_file, save_storage::FileSaveStorage}, spu::{ SAMPLES_PER_BUFFER}, trace::{TraceFormat, TraceOptions, TraceStart}}};
 use gl::types::GLuint;
-use imgui::{MenuItem, im_str};
+use imgui::{Condition, ImString, MenuItem, Window as ImWindow, im_str};
 use nfd2::Response;
-use sdl2::{audio::{AudioSpecDesired, AudioStatus}, pixels::PixelFormatEnum, surface::Surface, video::Window};
+use sdl2::{audio::{AudioSpecDesired, AudioStatus}, pixels::PixelFormatEnum, surface::Surface, video::{FullscreenType, Window}};
+use ui::{cheats::CheatManager, debugger::DebuggerWindow, memory::MemoryViewer, oam::OamViewer, raster::RasterViewer, timeline::TimelineWindow, trace::TraceWindow, vram::{TileViewer, TilemapViewer}};
+
+mod ui;
 
 const SCALE: u32 = 2;
 const WIDTH: u32 = 160;
@@ -20,7 +23,18 @@ const HEIGHT: u32 = 144;
 const MENU_BAR_HEIGHT: u32 = 19;
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "info" {
+        print_rom_info(args.get(2));
+        return;
+    }
+
     let mut gb: Option<GameBoy> = None;
+    let mut rom_title: Option<String> = None;
+    let save_dir = get_save_dir_arg();
+    let trace_options = get_trace_args();
+    let doctor_ly = env::args().any(|arg| arg == "--doctor-ly");
+    let custom_palette = get_palette_arg();
 
     let sdl = sdl2::init().unwrap();
     let video = sdl.video().unwrap();
@@ -72,6 +86,35 @@ fn main() {
 
     let mut paused = true;
 
+    // View menu, layers are handed to whatever rom is loaded
+    let mut layers = Layers::default();
+    let mut tint_layers = false;
+    let mut unlimited_sprites = false;
+    let mut palette = custom_palette.unwrap_or_default();
+    let mut colourise = Colourise::Off;
+    let mut filter = Filter::None;
+    let mut frame_blending = false;
+    let mut blender = FrameBlender::new();
+    let mut fullscreen = false;
+
+    // the paused frame needs uploading again after a palette/tint change
+    let mut repaint_frame = false;
+
+    // shown in a window when the emulator panics or the cpu locks up, the
+    // event callback fills in cpu_locked
+    let mut alert: Option<String> = None;
+    let cpu_locked: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    let mut cheat_manager = CheatManager::new();
+    let mut debugger_window = DebuggerWindow::new();
+    let mut memory_viewer = MemoryViewer::new();
+    let mut tile_viewer = TileViewer::new();
+    let mut tilemap_viewer = TilemapViewer::new();
+    let mut oam_viewer = OamViewer::new();
+    let mut raster_viewer = RasterViewer::new();
+    let mut timeline_window = TimelineWindow::new();
+    let mut trace_window = TraceWindow::new();
+
     let timer = sdl.timer().unwrap();
     let mut turbo = false;
 
@@ -103,6 +146,10 @@ fn main() {
                                 (*audio_device).borrow().pause();
                                 comutative_speed.clear();
                             },
+                            sdl2::keyboard::Keycode::F11 => {
+                                fullscreen = !fullscreen;
+                                resize_window(&mut window, filter, fullscreen);
+                            },
                             _ => {
                                 if gb.is_some() && !paused {
                                     gb.as_mut().unwrap().key_down(keycode)
@@ -152,24 +199,62 @@ fn main() {
         imgui_sdl2.prepare_frame(imgui.io_mut(), &window, &event_pump.mouse_state());
 
         unsafe {
-            gl::ClearColor(0.4549, 0.92549, 0.968627, 0.7);
+            if fullscreen {
+                gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            } else {
+                gl::ClearColor(0.4549, 0.92549, 0.968627, 0.7);
+            }
             gl::Clear(gl::COLOR_BUFFER_BIT);
         }
 
         if gb.is_some() && !paused {
             let gb = gb.as_mut().unwrap();
-            let mut stopped: bool = false;
-            while !gb.get_draw_flag() && !stopped {
-                stopped = gb.tick();
+            gb.set_layers(layers);
+            gb.set_unlimited_sprites(unlimited_sprites);
+
+            let ran_frame = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut stopped: bool = false;
+                while !gb.get_draw_flag() && !stopped {
+                    stopped = gb.tick();
+                }
+            }));
+
+            if let Err(payload) = ran_frame {
+                paused = true;
+                (*audio_device).borrow().pause();
+
+                let reason = crash::panic_message(&*payload);
+                alert = Some(write_crash_report(gb, &reason, save_dir.as_ref()));
             }
 
-            render_gb(gb, fb_id, tex_id);
+            let mut frame = frame_rgba(gb, tint_layers);
+            if frame_blending {
+                frame = blender.blend(&frame);
+            }
+
+            render_frame(&frame, fb_id, tex_id, filter, screen_rect(&window, filter));
             gb.clear_draw_flag();
+
+            // don't spin while sitting at a breakpoint
+            if gb.debugger().is_paused() {
+                std::thread::sleep(Duration::from_millis(16));
+            }
         }
 
         else if gb.is_some() && paused {
             std::thread::sleep(Duration::from_millis(16));
-            render_paused_frame(fb_id, tex_id);
+
+            if repaint_frame {
+                let mut frame = frame_rgba(gb.as_ref().unwrap(), tint_layers);
+                if frame_blending {
+                    frame = blender.reblend(&frame);
+                }
+
+                render_frame(&frame, fb_id, tex_id, filter, screen_rect(&window, filter));
+                repaint_frame = false;
+            } else {
+                render_paused_frame(fb_id, tex_id, filter, screen_rect(&window, filter));
+            }
         }
 
         if gb.is_some() && !turbo && !paused && (*audio_device).borrow().status() == AudioStatus::Playing {
@@ -183,25 +268,95 @@ fn main() {
             Some(mmb_token) => {
                 match ui.begin_menu(im_str!("File"), true) {
                     Some(mm_token) => {
+                        let mut rom_to_load: Option<(PathBuf, Option<PathBuf>)> = None;
+
                         if MenuItem::new(im_str!("Load ROM")).build(&ui) {
                             (*audio_device).borrow().pause();
 
-                            match nfd2::open_file_dialog(Some("gb"), None).expect("Hmm?") {
-                                Response::Okay(file_path) => {
-                                    let _gb = GameBoy::new(
-                                        file_path.to_str().unwrap(), 
-                                        Some(audio_device.clone())
-                                    );
+                            match nfd2::open_file_dialog(Some("gb,gbc,zip,gz"), None).expect("Hmm?") {
+                                Response::Okay(file_path) => rom_to_load = Some((file_path, None)),
+                                Response::OkayMultiple(files) => println!("Files {:?}", files),
+                                Response::Cancel => println!("User canceled"),
+                            }
+                        }
+
+                        if MenuItem::new(im_str!("Load ROM with patch")).build(&ui) {
+                            (*audio_device).borrow().pause();
+
+                            if let Response::Okay(file_path) = nfd2::open_file_dialog(Some("gb,gbc,zip,gz"), None).expect("Hmm?") {
+                                match nfd2::open_file_dialog(Some("ips,ups,bps"), file_path.parent()).expect("Hmm?") {
+                                    Response::Okay(patch_path) => rom_to_load = Some((file_path, Some(patch_path))),
+                                    Response::OkayMultiple(files) => println!("Files {:?}", files),
+                                    Response::Cancel => println!("User canceled"),
+                                }
+                            }
+                        }
+
+                        if let Some((file_path, patch_path)) = rom_to_load {
+                            let mut options = LoadOptions {
+                                patch_path,
+                                ..LoadOptions::default()
+                            };
+
+                            if let Some(save_dir) = &save_dir {
+                                options.save_storage = Some(Box::new(FileSaveStorage::new(save_dir)));
+                            }
+
+                            let loaded = GameBoy::new_with_options(
+                                file_path.to_str().unwrap(), 
+                                Some(audio_device.clone()),
+                                options
+                            );
+
+                            match loaded {
+                                Ok(mut _gb) => {
+                                    cheat_manager.load_for_rom(&mut _gb, &file_path, save_dir.as_ref());
+                                    _gb.set_palette(palette);
+                                    apply_colourise(&mut _gb, colourise);
+
+                                    // games that poke at unmapped memory usually do it
+                                    // every frame, so each access is only printed once
+                                    let cpu_locked = cpu_locked.clone();
+                                    let mut unmapped_seen = HashSet::new();
+                                    _gb.set_event_callback(move |event| {
+                                        match *event {
+                                            EmulatorEvent::CpuLocked { .. } => {
+                                                println!("{}", event);
+                                                *cpu_locked.borrow_mut() = Some(event.to_string());
+                                            }
+                                            EmulatorEvent::UnmappedRead { addr, pc } => {
+                                                if unmapped_seen.insert((false, addr, pc)) { println!("{}", event) }
+                                            }
+                                            EmulatorEvent::UnmappedWrite { addr, pc, .. } => {
+                                                if unmapped_seen.insert((true, addr, pc)) { println!("{}", event) }
+                                            }
+                                        }
+                                    });
+
+                                    _gb.set_doctor_ly(doctor_ly);
+                                    if let Some(trace_options) = &trace_options {
+                                        if let Err(err) = _gb.start_trace(trace_options.clone()) {
+                                            println!("Unable to create trace {}: {}", trace_options.path.display(), err);
+                                        }
+                                    }
+
+                                    let title = _gb.cartridge_header().title.clone();
+                                    window.set_title(format!("Frosty - {}", title).as_str()).unwrap();
+                                    rom_title = Some(title);
                                     gb = Some(_gb);
+                                    blender.clear();
 
                                     let ad = (*audio_device).borrow();
                                     ad.clear();
                                     // the gameboy will resume the audio
                                     paused = false;
-                                },
-                                
-                                Response::OkayMultiple(files) => println!("Files {:?}", files),
-                                Response::Cancel => println!("User canceled"),
+                                }
+
+                                // a patch that doesn't fit or a rom we can't run, keep going with what was loaded before
+                                Err(err) => {
+                                    println!("{}", err);
+                                    alert = Some(err);
+                                }
                             }
                         }
 
@@ -219,13 +374,14 @@ fn main() {
                             }
                         }
 
-                        if MenuItem::new(im_str!("Start log")).build(&ui) {
-                            if gb.is_some() {
-                                gb.as_mut().unwrap().start_log();
+                        if MenuItem::new(im_str!("Exit")).build(&ui) {
+                            if let Some(gb) = gb.as_mut() {
+                                gb.save();
+
+                                // the end of the trace is still sitting in the buffer
+                                gb.stop_trace();
                             }
-                        }
 
-                        if MenuItem::new(im_str!("Exit")).build(&ui) {
                             process::exit(0);
                         }
 
@@ -234,11 +390,197 @@ fn main() {
                     None => {}
                 }
 
+                if let Some(menu_token) = ui.begin_menu(im_str!("View"), true) {
+                    if MenuItem::new(im_str!("Background")).selected(layers.background).build(&ui) {
+                        layers.background = !layers.background;
+                    }
+
+                    if MenuItem::new(im_str!("Window")).selected(layers.window).build(&ui) {
+                        layers.window = !layers.window;
+                    }
+
+                    if MenuItem::new(im_str!("Sprites")).selected(layers.sprites).build(&ui) {
+                        layers.sprites = !layers.sprites;
+                    }
+
+                    ui.separator();
+
+                    if MenuItem::new(im_str!("Tint by layer")).selected(tint_layers).build(&ui) {
+                        tint_layers = !tint_layers;
+                        repaint_frame = true;
+                    }
+
+                    ui.separator();
+
+                    if let Some(palette_token) = ui.begin_menu(im_str!("Palette"), true) {
+                        let mut choices: Vec<(String, DmgPalette)> = DmgPalette::PRESETS.iter()
+                            .map(|(name, preset)| (name.to_string(), *preset))
+                            .collect();
+
+                        if let Some(custom) = custom_palette {
+                            choices.push((String::from("Custom"), custom));
+                        }
+
+                        for (name, choice) in choices {
+                            if MenuItem::new(&ImString::new(name)).selected(palette == choice).build(&ui) {
+                                palette = choice;
+                                repaint_frame = true;
+                                if let Some(gb) = gb.as_mut() {
+                                    gb.set_palette(palette);
+                                }
+                            }
+                        }
+
+                        palette_token.end(&ui);
+                    }
+
+                    if let Some(colourise_token) = ui.begin_menu(im_str!("Colourise"), true) {
+                        let mut choices = vec![(String::from("Off"), Colourise::Off), (String::from("By title (GBC)"), Colourise::Title)];
+                        for (i, (name, _)) in Colourisation::MANUAL.iter().enumerate() {
+                            choices.push((name.to_string(), Colourise::Manual(i)));
+                        }
+
+                        for (name, choice) in choices {
+                            if MenuItem::new(&ImString::new(name)).selected(colourise == choice).build(&ui) {
+                                colourise = choice;
+                                repaint_frame = true;
+                                if let Some(gb) = gb.as_mut() {
+                                    apply_colourise(gb, colourise);
+                                }
+                            }
+                        }
+
+                        colourise_token.end(&ui);
+                    }
+
+                    if MenuItem::new(im_str!("Unlimited sprites")).selected(unlimited_sprites).build(&ui) {
+                        unlimited_sprites = !unlimited_sprites;
+                    }
+
+                    ui.separator();
+
+                    if let Some(filter_token) = ui.begin_menu(im_str!("Filter"), true) {
+                        for choice in Filter::ALL.iter() {
+                            if MenuItem::new(&ImString::new(choice.name())).selected(filter == *choice).build(&ui) {
+                                filter = *choice;
+                                repaint_frame = true;
+                                resize_window(&mut window, filter, fullscreen);
+                            }
+                        }
+
+                        filter_token.end(&ui);
+                    }
+
+                    if MenuItem::new(im_str!("Frame blending")).selected(frame_blending).build(&ui) {
+                        frame_blending = !frame_blending;
+                        blender.clear();
+                        repaint_frame = true;
+                    }
+
+                    if MenuItem::new(im_str!("Fullscreen")).shortcut(im_str!("F11")).selected(fullscreen).build(&ui) {
+                        fullscreen = !fullscreen;
+                        resize_window(&mut window, filter, fullscreen);
+                    }
+
+                    menu_token.end(&ui);
+                }
+
+                if let Some(menu_token) = ui.begin_menu(im_str!("Cheats"), gb.is_some()) {
+                    if MenuItem::new(im_str!("Cheat manager")).selected(cheat_manager.open).build(&ui) {
+                        cheat_manager.open = !cheat_manager.open;
+                    }
+
+                    menu_token.end(&ui);
+                }
+
+                if let Some(menu_token) = ui.begin_menu(im_str!("Debug"), gb.is_some()) {
+                    if MenuItem::new(im_str!("Debugger")).selected(debugger_window.open).build(&ui) {
+                        debugger_window.open = !debugger_window.open;
+                    }
+
+                    if MenuItem::new(im_str!("Memory")).selected(memory_viewer.open).build(&ui) {
+                        memory_viewer.open = !memory_viewer.open;
+                    }
+
+                    if MenuItem::new(im_str!("Tiles")).selected(tile_viewer.open).build(&ui) {
+                        tile_viewer.open = !tile_viewer.open;
+                    }
+
+                    if MenuItem::new(im_str!("Tilemaps")).selected(tilemap_viewer.open).build(&ui) {
+                        tilemap_viewer.open = !tilemap_viewer.open;
+                    }
+
+                    if MenuItem::new(im_str!("OAM")).selected(oam_viewer.open).build(&ui) {
+                        oam_viewer.open = !oam_viewer.open;
+                    }
+
+                    if MenuItem::new(im_str!("Raster")).selected(raster_viewer.open).build(&ui) {
+                        raster_viewer.open = !raster_viewer.open;
+                    }
+
+                    if MenuItem::new(im_str!("Timeline")).selected(timeline_window.open).build(&ui) {
+                        timeline_window.open = !timeline_window.open;
+                    }
+
+                    if MenuItem::new(im_str!("Trace log")).selected(trace_window.open).build(&ui) {
+                        trace_window.open = !trace_window.open;
+                    }
+
+                    if MenuItem::new(im_str!("Save crash report")).build(&ui) {
+                        if let Some(gb) = gb.as_ref() {
+                            println!("{}", write_crash_report(gb, "Requested from the debug menu", save_dir.as_ref()));
+                        }
+                    }
+
+                    if MenuItem::new(im_str!("Load symbols")).build(&ui) {
+                        if let Response::Okay(sym_path) = nfd2::open_file_dialog(Some("sym"), None).expect("Hmm?") {
+                            if let Some(gb) = gb.as_mut() {
+                                match gb.load_symbols(&sym_path) {
+                                    Ok(count) => println!("Loaded {} symbols", count),
+                                    Err(err) => println!("Unable to read {}: {}", sym_path.display(), err)
+                                }
+                            }
+                        }
+                    }
+
+                    menu_token.end(&ui);
+                }
+
                 mmb_token.end(&ui);
             }
             None => {}
         }
 
+        if let Some(message) = cpu_locked.borrow_mut().take() {
+            alert = Some(message);
+        }
+
+        if let Some(message) = &alert {
+            let mut open = true;
+            ImWindow::new(im_str!("Frosty"))
+                .opened(&mut open)
+                .size([400.0, 120.0], Condition::FirstUseEver)
+                .build(&ui, || {
+                    ui.text_wrapped(&ImString::new(message.as_str()));
+                });
+
+            if !open {
+                alert = None;
+            }
+        }
+
+        if let Some(gb) = gb.as_mut() {
+            cheat_manager.draw(&ui, gb);
+            debugger_window.draw(&ui, gb);
+            memory_viewer.draw(&ui, gb);
+            tile_viewer.draw(&ui, gb);
+            tilemap_viewer.draw(&ui, gb);
+            oam_viewer.draw(&ui, gb);
+            raster_viewer.draw(&ui, gb);
+            timeline_window.draw(&ui, gb);
+            trace_window.draw(&ui, gb);
+        }
+
         let end = timer.performance_counter();
 
         elapsed_ns += end - start;
@@ -256,7 +598,12 @@ fn main() {
 
             let average_percent = comutative_speed.iter().sum::<f64>() / comutative_speed.len() as f64;
 
-            window.set_title(format!("Frosty  [{:.0}%]", average_percent).as_str()).unwrap()
+            let window_title = match &rom_title {
+                Some(title) => format!("Frosty - {}  [{:.0}%]", title, average_percent),
+                None => format!("Frosty  [{:.0}%]", average_percent)
+            };
+
+            window.set_title(window_title.as_str()).unwrap()
         }
 
         imgui_sdl2.prepare_render(&ui, &window);
@@ -266,6 +613,122 @@ fn main() {
     }
 }
 
+// frosty info <rom>
+fn print_rom_info(rom_path: Option<&String>) {
+    let rom_path = match rom_path {
+        Some(rom_path) => rom_path,
+        None => {
+            println!("Usage: frosty info <rom>");
+            process::exit(1);
+        }
+    };
+
+    let rom = match load_rom_file(rom_path) {
+        Ok(loaded_rom) => loaded_rom.data,
+        Err(err) => {
+            println!("Unable to read {}: {}", rom_path, err);
+            process::exit(1);
+        }
+    };
+
+    match CartridgeHeader::parse(&rom) {
+        Some(header) => println!("{}", header),
+        None => {
+            println!("{} is too small to contain a cartridge header", rom_path);
+            process::exit(1);
+        }
+    }
+}
+
+// Writes a crash report next to the saves (or into the working directory),
+// returns a message saying where it went
+fn write_crash_report(gb: &GameBoy, reason: &str, save_dir: Option<&PathBuf>) -> String {
+    let path = match save_dir {
+        Some(save_dir) => save_dir.join(crash::report_file_name()),
+        None => PathBuf::from(crash::report_file_name())
+    };
+
+    match gb.write_crash_report(&path, reason) {
+        Ok(()) => format!("{}\nCrash report saved to {}", reason, path.display()),
+        Err(err) => format!("{}\nUnable to write crash report to {}: {}", reason, path.display(), err)
+    }
+}
+
+// --save-dir <dir>: where to keep .sav files, defaults to next to the rom
+fn get_save_dir_arg() -> Option<PathBuf> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--save-dir" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+
+    None
+}
+
+// View > Colourise, gbc style colours for each layer
+#[derive(Clone, Copy, PartialEq)]
+enum Colourise {
+    Off,
+    Title,
+    Manual(usize)
+}
+
+fn apply_colourise(gb: &mut GameBoy, colourise: Colourise) {
+    let colourisation = match colourise {
+        Colourise::Off => None,
+        Colourise::Title => Some(gb.title_colourisation()),
+        Colourise::Manual(i) => Some(Colourisation::MANUAL[i].1)
+    };
+
+    gb.set_colourisation(colourisation);
+}
+
+// --palette grey|green|pocket|light|RRGGBB,RRGGBB,RRGGBB,RRGGBB (lightest first),
+// the hex colours show up as Custom in View > Palette
+fn get_palette_arg() -> Option<DmgPalette> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--palette" {
+            return match DmgPalette::parse(&args.next().unwrap_or_default()) {
+                Ok(palette) => Some(palette),
+                Err(err) => {
+                    println!("{}", err);
+                    None
+                }
+            };
+        }
+    }
+
+    None
+}
+
+// --trace <file> [--trace-format doctor|readable|binary] [--trace-start boot|pc:XXXX|frame:N]
+// traces every rom that gets loaded from the start
+fn get_trace_args() -> Option<TraceOptions> {
+    let mut options: Option<TraceOptions> = None;
+    let mut format = TraceFormat::GameboyDoctor;
+    let mut start = TraceStart::Boot;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" => options = args.next().map(TraceOptions::new),
+            "--trace-format" => {
+                let value = args.next().unwrap_or_default();
+                format = TraceFormat::parse(&value).unwrap_or_else(|| panic!("Unknown trace format: {}", value));
+            }
+            "--trace-start" => {
+                let value = args.next().unwrap_or_default();
+                start = TraceStart::parse(&value).unwrap_or_else(|| panic!("Invalid trace start: {}", value));
+            }
+            _ => {}
+        }
+    }
+
+    options.map(|options| TraceOptions { format, start, ..options })
+}
+
 fn init_gl_state(tex_id: &mut u32, fb_id: &mut u32) {
     unsafe {
         gl::GenTextures(1, tex_id);
@@ -309,63 +772,100 @@ fn init_gl_state(tex_id: &mut u32, fb_id: &mut u32) {
     }
 }
 
-fn render_gb(gb: &GameBoy, fb_id: GLuint, tex_id: GLuint) {
-    let frame_buffer = gb.get_frame_buffer();
-    let mut tex_data = [0u8; (WIDTH * HEIGHT * 3) as usize];
-    let mut i: usize = 0;
+// mixed half and half with the shade when tinting by layer
+fn layer_tint(source: PixelSource) -> [u8; 3] {
+    match source {
+        PixelSource::Background => [60, 120, 255],
+        PixelSource::Window => [60, 220, 60],
+        PixelSource::Obj0 => [255, 60, 60],
+        PixelSource::Obj1 => [255, 200, 0]
+    }
+}
 
-    while i < (WIDTH * HEIGHT * 3) as usize {
-        let index = i / 3;
-        let color = frame_buffer[index];
+// the frame as RGBA, tinted by layer if that's on
+fn frame_rgba(gb: &GameBoy, tint_layers: bool) -> Vec<u8> {
+    let mut frame = gb.get_frame_buffer().to_vec();
 
-        tex_data[i] = color;
-        tex_data[i + 1] = color;
-        tex_data[i + 2] = color;
+    if tint_layers {
+        for (pixel, source) in frame.chunks_exact_mut(4).zip(gb.get_pixel_sources().iter()) {
+            for (channel, tint) in pixel.iter_mut().zip(layer_tint(*source).iter()) {
+                *channel = ((*channel as u16 + *tint as u16) / 2) as u8;
+            }
+        }
+    }
 
-        i += 3;
+    frame
+}
+
+// windowed, the screen is SCALE times bigger, or the filter's scale if that's
+// more. fullscreen takes up the whole desktop and screen_rect fits it in
+fn resize_window(window: &mut Window, filter: Filter, fullscreen: bool) {
+    if fullscreen {
+        if let Err(err) = window.set_fullscreen(FullscreenType::Desktop) {
+            println!("Unable to go fullscreen: {}", err);
+        }
+        return;
+    }
+
+    if let Err(err) = window.set_fullscreen(FullscreenType::Off) {
+        println!("Unable to leave fullscreen: {}", err);
     }
 
+    let scale = SCALE.max(filter.scale() as u32);
+    if let Err(err) = window.set_size(WIDTH * scale, (HEIGHT * scale) + MENU_BAR_HEIGHT) {
+        println!("Unable to resize the window: {}", err);
+    }
+}
+
+// where the screen goes (x, y, width, height from the bottom left), the
+// biggest whole multiple of the screen that fits under the menu bar, in the
+// middle. it's a multiple of the filter's scale too when there's room so
+// filtered pixels aren't stretched unevenly
+fn screen_rect(window: &Window, filter: Filter) -> [i32; 4] {
+    let (width, height) = window.size();
+    let height = height.saturating_sub(MENU_BAR_HEIGHT);
+
+    let fit = (width / WIDTH).min(height / HEIGHT).max(1);
+    let filter_scale = filter.scale() as u32;
+    let scale = if fit >= filter_scale { fit - fit % filter_scale } else { fit };
+
+    let (screen_width, screen_height) = (WIDTH * scale, HEIGHT * scale);
+    [
+        (width.saturating_sub(screen_width) / 2) as i32,
+        (height.saturating_sub(screen_height) / 2) as i32,
+        screen_width as i32,
+        screen_height as i32
+    ]
+}
+
+fn render_frame(frame: &[u8], fb_id: GLuint, tex_id: GLuint, filter: Filter, rect: [i32; 4]) {
+    let scale = filter.scale() as u32;
+    let tex_data = filter.apply(frame, WIDTH as usize, HEIGHT as usize);
+
     unsafe {
+        // the size changes with the filter, so the whole texture is replaced
         gl::BindTexture(gl::TEXTURE_2D, tex_id);
-        gl::TexSubImage2D(
+        gl::TexImage2D(
             gl::TEXTURE_2D,
             0,
+            gl::RGBA as i32,
+            (WIDTH * scale) as i32,
+            (HEIGHT * scale) as i32,
             0,
-            0,
-            WIDTH as i32,
-            HEIGHT as i32,
-            gl::RGB,
+            gl::RGBA,
             gl::UNSIGNED_BYTE,
             tex_data.as_ptr() as *const c_void
         );
         gl::BindTexture(gl::TEXTURE_2D, 0);
-
-        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fb_id);
-        gl::FramebufferTexture2D(
-            gl::READ_FRAMEBUFFER,
-            gl::COLOR_ATTACHMENT0,
-            gl::TEXTURE_2D,
-            tex_id, 
-            0
-        );
-
-        gl::BlitFramebuffer(
-            0, 
-            0, 
-            WIDTH as i32, 
-            HEIGHT as i32, 
-            0, 
-            ((HEIGHT * SCALE)) as i32, 
-            (WIDTH * SCALE) as i32, 
-            0, 
-            gl::COLOR_BUFFER_BIT, 
-            gl::NEAREST
-        );
-        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
     }
+
+    render_paused_frame(fb_id, tex_id, filter, rect);
 }
 
-fn render_paused_frame(fb_id: GLuint, tex_id: GLuint) {
+fn render_paused_frame(fb_id: GLuint, tex_id: GLuint, filter: Filter, rect: [i32; 4]) {
+    let scale = filter.scale() as u32;
+    let [x, y, width, height] = rect;
+
     unsafe {
         gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fb_id);
         gl::FramebufferTexture2D(
@@ -379,12 +879,12 @@ fn render_paused_frame(fb_id: GLuint, tex_id: GLuint) {
         gl::BlitFramebuffer(
             0, 
             0, 
-            WIDTH as i32, 
-            HEIGHT as i32, 
-            0, 
-            ((HEIGHT * SCALE)) as i32, 
-            (WIDTH * SCALE) as i32, 
-            0, 
+            (WIDTH * scale) as i32, 
+            (HEIGHT * scale) as i32, 
+            x, 
+            y + height, 
+            x + width, 
+            y, 
             gl::COLOR_BUFFER_BIT, 
             gl::NEAREST
         );
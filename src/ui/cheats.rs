@@ -0,0 +1,131 @@
+use std::path::{Path, PathBuf};
+
+use gameboy_rs::gameboy::{GameBoy, cheats::Cheats};
+use imgui::{Condition, ImString, Ui, Window, im_str};
+
+pub struct CheatManager {
+    pub open: bool,
+
+    name: ImString,
+    code: ImString,
+    error: Option<String>,
+
+    // <rom name>.cht, next to the saves
+    cheat_path: Option<PathBuf>
+}
+
+impl CheatManager {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+
+            name: ImString::with_capacity(64),
+            code: ImString::with_capacity(64),
+            error: None,
+
+            cheat_path: None
+        }
+    }
+
+    // named after the rom like the save is, so the one inside an archive
+    pub fn load_for_rom(&mut self, gb: &mut GameBoy, rom_path: &Path, save_dir: Option<&PathBuf>) {
+        let cheat_dir = match save_dir {
+            Some(save_dir) => save_dir.as_path(),
+            None => rom_path.parent().unwrap_or_else(|| Path::new(""))
+        };
+
+        let cheat_path = cheat_dir.join(format!("{}.cht", gb.rom_name()));
+        if cheat_path.is_file() {
+            match Cheats::load(&cheat_path) {
+                Ok(cheats) => *gb.cheats_mut() = cheats,
+                Err(err) => println!("Unable to load cheats from {}: {}", cheat_path.display(), err)
+            }
+        }
+
+        self.cheat_path = Some(cheat_path);
+        self.error = None;
+    }
+
+    fn save(&self, gb: &GameBoy) {
+        if let Some(cheat_path) = &self.cheat_path {
+            if let Err(err) = gb.cheats().save(cheat_path) {
+                println!("Unable to save cheats to {}: {}", cheat_path.display(), err);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+        let mut changed = false;
+
+        Window::new(im_str!("Cheats"))
+            .opened(&mut open)
+            .size([300.0, 220.0], Condition::FirstUseEver)
+            .build(ui, || {
+                let mut toggled: Option<(usize, bool)> = None;
+                let mut removed: Option<usize> = None;
+
+                for (i, cheat) in gb.cheats().list().iter().enumerate() {
+                    let id = ui.push_id(i as i32);
+
+                    let mut enabled = cheat.enabled;
+                    let label = ImString::new(format!("{}##enabled", cheat.name));
+                    if ui.checkbox(&label, &mut enabled) {
+                        toggled = Some((i, enabled));
+                    }
+
+                    ui.same_line(0.0);
+                    ui.text_disabled(&cheat.code);
+
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Remove")) {
+                        removed = Some(i);
+                    }
+
+                    id.pop(ui);
+                }
+
+                if let Some((i, enabled)) = toggled {
+                    gb.cheats_mut().set_enabled(i, enabled);
+                    changed = true;
+                }
+
+                if let Some(i) = removed {
+                    gb.cheats_mut().remove(i);
+                    changed = true;
+                }
+
+                ui.separator();
+
+                ui.input_text(im_str!("Name"), &mut self.name).build();
+                ui.input_text(im_str!("Code"), &mut self.code).build();
+
+                if ui.button(im_str!("Add"), [0.0, 0.0]) {
+                    let name = if self.name.to_str().trim().is_empty() { self.code.to_str() } else { self.name.to_str() };
+
+                    match gb.cheats_mut().add(name, self.code.to_str()) {
+                        Ok(_) => {
+                            self.name.clear();
+                            self.code.clear();
+                            self.error = None;
+                            changed = true;
+                        }
+
+                        Err(err) => self.error = Some(err.to_string())
+                    }
+                }
+
+                if let Some(error) = &self.error {
+                    ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+                }
+            });
+
+        self.open = open;
+
+        if changed {
+            self.save(gb);
+        }
+    }
+}
@@ -0,0 +1,464 @@
+use gameboy_rs::gameboy::{GameBoy, cpu::{Flag, Registers}, debugger::{BreakReason, expr::{self, LogMessage}, watchpoint::{Access, ValueCondition, WatchKind, Watchpoint}}};
+use imgui::{Condition, ImString, MouseButton, Selectable, Ui, Window, im_str};
+
+use super::parse_hex;
+
+const DISASSEMBLY_LINES: usize = 16;
+const LOG_LINES: usize = 8;
+
+pub struct DebuggerWindow {
+    pub open: bool,
+
+    cursor: Option<u16>,
+    breakpoint_input: ImString,
+    breakpoint_condition: ImString,
+    breakpoint_hits: ImString,
+    breakpoint_log: ImString,
+    watchpoint_input: ImString,
+    watchpoint_condition: ImString,
+    watchpoint_kind: WatchKind,
+    error: Option<String>
+}
+
+impl DebuggerWindow {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+
+            cursor: None,
+            breakpoint_input: ImString::with_capacity(16),
+            breakpoint_condition: ImString::with_capacity(128),
+            breakpoint_hits: ImString::with_capacity(16),
+            breakpoint_log: ImString::with_capacity(128),
+            watchpoint_input: ImString::with_capacity(16),
+            watchpoint_condition: ImString::with_capacity(16),
+            watchpoint_kind: WatchKind::Write,
+            error: None
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("Debugger"))
+            .opened(&mut open)
+            .size([340.0, 480.0], Condition::FirstUseEver)
+            .build(ui, || {
+                self.draw_controls(ui, gb);
+                ui.separator();
+
+                if gb.debugger().is_paused() {
+                    self.draw_registers(ui, gb);
+                    ui.separator();
+                    self.draw_disassembly(ui, gb);
+                    ui.separator();
+                    self.draw_call_stack(ui, gb);
+                    ui.separator();
+                }
+
+                self.draw_breakpoints(ui, gb);
+                ui.separator();
+                self.draw_watchpoints(ui, gb);
+
+                if let Some(error) = &self.error {
+                    ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+                }
+            });
+
+        self.open = open;
+    }
+
+    fn draw_controls(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let paused = gb.debugger().is_paused();
+
+        if paused {
+            if ui.button(im_str!("Continue"), [0.0, 0.0]) {
+                gb.debugger_mut().resume();
+            }
+
+            ui.same_line(0.0);
+            if ui.button(im_str!("Step"), [0.0, 0.0]) {
+                gb.step_instruction();
+            }
+
+            ui.same_line(0.0);
+            if ui.button(im_str!("Step over"), [0.0, 0.0]) {
+                gb.step_over();
+            }
+
+            ui.same_line(0.0);
+            if ui.button(im_str!("Step out"), [0.0, 0.0]) {
+                gb.step_out();
+            }
+
+            if let Some(cursor) = self.cursor {
+                ui.same_line(0.0);
+                if ui.button(im_str!("Run to cursor"), [0.0, 0.0]) {
+                    gb.run_to(cursor);
+                }
+            }
+        } else if ui.button(im_str!("Break"), [0.0, 0.0]) {
+            gb.debugger_mut().pause();
+        }
+
+        let status = match gb.debugger().break_reason() {
+            Some(BreakReason::Breakpoint(i)) => format!("Paused: breakpoint {}", i),
+            Some(BreakReason::Watchpoint(hit)) => {
+                let access = match hit.access {
+                    Access::Read => "read from",
+                    Access::Write => "write to",
+                    Access::MbcWrite => "mbc register write to"
+                };
+
+                format!(
+                    "Paused: watchpoint {}, {} {:04X} ({:02X}) by {:04X} at cycle {}",
+                    hit.index, access, hit.addr, hit.value, hit.pc, hit.cycle
+                )
+            }
+            Some(BreakReason::Step) => String::from("Paused"),
+            Some(BreakReason::RunToCursor) => String::from("Paused: reached cursor"),
+            Some(BreakReason::TraceCompare) => String::from("Paused: trace comparison finished, see the trace window"),
+            None => String::from("Running")
+        };
+        ui.text(status);
+
+        if gb.is_cpu_locked() {
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], "The CPU is locked up (illegal opcode), reload the ROM to reset it");
+        }
+    }
+
+    fn draw_registers(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let mut registers = gb.registers();
+        let mut changed = false;
+
+        {
+            let mut register8 = |label: &str, value: &mut u8, same_line: bool| {
+                if same_line { ui.same_line(0.0); }
+                changed |= edit_hex(ui, label, value, 2);
+            };
+
+            register8("A", &mut registers.a, false);
+            register8("F", &mut registers.f, true);
+            register8("B", &mut registers.b, false);
+            register8("C", &mut registers.c, true);
+            register8("D", &mut registers.d, false);
+            register8("E", &mut registers.e, true);
+            register8("H", &mut registers.h, false);
+            register8("L", &mut registers.l, true);
+        }
+
+        changed |= edit_hex(ui, "SP", &mut registers.sp, 4);
+        ui.same_line(0.0);
+        changed |= edit_hex(ui, "PC", &mut registers.pc, 4);
+
+        changed |= edit_flag(ui, "Z", &mut registers, Flag::Z);
+        ui.same_line(0.0);
+        changed |= edit_flag(ui, "N", &mut registers, Flag::N);
+        ui.same_line(0.0);
+        changed |= edit_flag(ui, "H##flag", &mut registers, Flag::H);
+        ui.same_line(0.0);
+        changed |= edit_flag(ui, "C##flag", &mut registers, Flag::C);
+
+        if changed {
+            gb.set_registers(registers);
+        }
+    }
+
+    fn draw_disassembly(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let pc = gb.registers().pc;
+        let mut addr = pc;
+
+        for _ in 0..DISASSEMBLY_LINES {
+            if let Some(label) = gb.label_at(addr) {
+                ui.text_colored([0.6, 0.8, 1.0, 1.0], format!("{}:", label));
+            }
+
+            let instr = gb.disassemble_instruction(addr);
+
+            let marker = match (addr == pc, gb.debugger().has_breakpoint(addr)) {
+                (true, true) => "*>",
+                (true, false) => " >",
+                (false, true) => "* ",
+                (false, false) => "  "
+            };
+
+            let label = ImString::new(format!("{} {:04X}  {}##{}", marker, addr, gb.format_instruction(&instr), addr));
+            if Selectable::new(&label).selected(self.cursor == Some(addr)).allow_double_click(true).build(ui) {
+                self.cursor = Some(addr);
+
+                if ui.is_mouse_double_clicked(MouseButton::Left) {
+                    gb.debugger_mut().toggle_breakpoint(addr);
+                }
+            }
+
+            addr = addr.wrapping_add(instr.length);
+        }
+
+        ui.text_disabled("Double click to toggle a breakpoint");
+    }
+
+    fn draw_call_stack(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        ui.text("Call stack:");
+        ui.text(format!("  {}", gb.describe_address(gb.registers().pc)));
+
+        for frame in gb.debugger().call_stack().frames().iter().rev() {
+            let kind = if frame.interrupt { "interrupted at" } else { "called from" };
+            ui.text(format!("  {} ({} {})", gb.describe_address(frame.target), kind, gb.describe_address(frame.from)));
+        }
+    }
+
+    fn draw_breakpoints(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let mut toggled: Option<(usize, bool)> = None;
+        let mut removed: Option<usize> = None;
+
+        for (i, bp) in gb.debugger().breakpoints().iter().enumerate() {
+            let id = ui.push_id(i as i32);
+
+            let mut text = match bp.bank {
+                Some(bank) => format!("{:02X}:{:04X}", bank, bp.addr),
+                None => format!("{:04X}", bp.addr)
+            };
+
+            if let Some(condition) = &bp.condition {
+                text.push_str(&format!(" if {}", condition.text()));
+            }
+
+            if let Some(break_after) = bp.break_after {
+                text.push_str(&format!(" after {}", break_after));
+            }
+
+            if let Some(log_message) = &bp.log_message {
+                text.push_str(&format!(" log \"{}\"", log_message.text()));
+            }
+
+            text.push_str(&format!(" ({} hits)", bp.hit_count));
+
+            let mut enabled = bp.enabled;
+            if ui.checkbox(&ImString::new(text), &mut enabled) {
+                toggled = Some((i, enabled));
+            }
+
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Remove")) {
+                removed = Some(i);
+            }
+
+            id.pop(ui);
+        }
+
+        if let Some((i, enabled)) = toggled {
+            gb.debugger_mut().set_breakpoint_enabled(i, enabled);
+        }
+
+        if let Some(i) = removed {
+            gb.debugger_mut().remove_breakpoint(i);
+        }
+
+        ui.input_text(im_str!("[bank:]addr or label"), &mut self.breakpoint_input).build();
+        ui.input_text(im_str!("condition"), &mut self.breakpoint_condition).build();
+        ui.input_text(im_str!("break after hits"), &mut self.breakpoint_hits)
+            .chars_decimal(true)
+            .build();
+        ui.input_text(im_str!("log message"), &mut self.breakpoint_log).build();
+
+        if ui.button(im_str!("Add breakpoint"), [0.0, 0.0]) {
+            match self.add_breakpoint(gb) {
+                Ok(()) => {
+                    self.breakpoint_input.clear();
+                    self.breakpoint_condition.clear();
+                    self.breakpoint_hits.clear();
+                    self.breakpoint_log.clear();
+                    self.error = None;
+                }
+
+                Err(error) => self.error = Some(error)
+            }
+        }
+
+        let log = gb.debugger().log();
+        if !log.is_empty() {
+            ui.text("Log:");
+            for line in log.iter().rev().take(LOG_LINES) {
+                ui.text(line);
+            }
+
+            if ui.small_button(im_str!("Clear log")) {
+                gb.debugger_mut().clear_log();
+            }
+        }
+    }
+
+    fn add_breakpoint(&self, gb: &mut GameBoy) -> Result<(), String> {
+        let input = self.breakpoint_input.to_str().trim();
+        let (addr, bank) = gb.debugger().symbols().resolve(input)
+            .or_else(|| parse_breakpoint(input))
+            .ok_or_else(|| format!("Invalid breakpoint: {}", self.breakpoint_input.to_str()))?;
+
+        let condition = match self.breakpoint_condition.to_str().trim() {
+            "" => None,
+            text => Some(expr::Condition::parse(text).map_err(|err| format!("Invalid condition: {}", err))?)
+        };
+
+        let break_after = match self.breakpoint_hits.to_str().trim() {
+            "" => None,
+            text => Some(text.parse().map_err(|_| format!("Invalid hit count: {}", text))?)
+        };
+
+        let log_message = match self.breakpoint_log.to_str() {
+            "" => None,
+            text => Some(LogMessage::parse(text).map_err(|err| format!("Invalid log message: {}", err))?)
+        };
+
+        let debugger = gb.debugger_mut();
+        let index = debugger.add_breakpoint(addr, bank);
+        if let Some(bp) = debugger.breakpoint_mut(index) {
+            bp.condition = condition;
+            bp.break_after = break_after;
+            bp.log_message = log_message;
+        }
+
+        Ok(())
+    }
+
+    fn draw_watchpoints(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let mut toggled: Option<(usize, bool)> = None;
+        let mut removed: Option<usize> = None;
+
+        let section = ui.push_id("watchpoints");
+        for (i, watchpoint) in gb.watchpoints().list().iter().enumerate() {
+            let id = ui.push_id(i as i32);
+
+            let kind = match watchpoint.kind {
+                WatchKind::Read => "r",
+                WatchKind::Write => "w",
+                WatchKind::ReadWrite => "rw"
+            };
+
+            let mut text = if watchpoint.start == watchpoint.end {
+                format!("{:04X} {}", watchpoint.start, kind)
+            } else {
+                format!("{:04X}-{:04X} {}", watchpoint.start, watchpoint.end, kind)
+            };
+
+            match watchpoint.condition {
+                Some(ValueCondition::Equals(value)) => text.push_str(&format!(" ={:02X}", value)),
+                Some(ValueCondition::NotEquals(value)) => text.push_str(&format!(" !={:02X}", value)),
+                Some(ValueCondition::BitsSet(mask)) => text.push_str(&format!(" set {:02X}", mask)),
+                Some(ValueCondition::BitsClear(mask)) => text.push_str(&format!(" clear {:02X}", mask)),
+                None => {}
+            }
+
+            let mut enabled = watchpoint.enabled;
+            if ui.checkbox(&ImString::new(text), &mut enabled) {
+                toggled = Some((i, enabled));
+            }
+
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Remove")) {
+                removed = Some(i);
+            }
+
+            id.pop(ui);
+        }
+
+        section.pop(ui);
+
+        if let Some((i, enabled)) = toggled {
+            gb.watchpoints_mut().set_enabled(i, enabled);
+        }
+
+        if let Some(i) = removed {
+            gb.watchpoints_mut().remove(i);
+        }
+
+        ui.radio_button(im_str!("Read"), &mut self.watchpoint_kind, WatchKind::Read);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("Write"), &mut self.watchpoint_kind, WatchKind::Write);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("Both"), &mut self.watchpoint_kind, WatchKind::ReadWrite);
+
+        ui.input_text(im_str!("addr[-end]"), &mut self.watchpoint_input).build();
+        ui.input_text(im_str!("=42, !=42, set 80, clear 80"), &mut self.watchpoint_condition).build();
+
+        if ui.button(im_str!("Add watchpoint"), [0.0, 0.0]) {
+            let condition_text = self.watchpoint_condition.to_str().trim();
+            let condition = if condition_text.is_empty() {
+                Some(None)
+            } else {
+                ValueCondition::parse(condition_text).map(Some)
+            };
+
+            match (parse_range(self.watchpoint_input.to_str()), condition) {
+                (Some((start, end)), Some(condition)) => {
+                    let mut watchpoint = Watchpoint::new(start, end, self.watchpoint_kind);
+                    watchpoint.condition = condition;
+                    gb.watchpoints_mut().add(watchpoint);
+
+                    self.watchpoint_input.clear();
+                    self.watchpoint_condition.clear();
+                    self.error = None;
+                }
+
+                (None, _) => self.error = Some(format!("Invalid address range: {}", self.watchpoint_input.to_str())),
+                (_, None) => self.error = Some(format!("Invalid condition: {}", condition_text))
+            }
+        }
+    }
+}
+
+// "0150" or "03:4000"
+fn parse_breakpoint(text: &str) -> Option<(u16, Option<u16>)> {
+    match text.split_once(':') {
+        Some((bank, addr)) => Some((parse_hex(addr)?, Some(parse_hex(bank)?))),
+        None => Some((parse_hex(text)?, None))
+    }
+}
+
+// "FF40" or "C000-C0FF"
+fn parse_range(text: &str) -> Option<(u16, u16)> {
+    match text.split_once('-') {
+        Some((start, end)) => Some((parse_hex(start.trim())?, parse_hex(end.trim())?)),
+        None => {
+            let addr = parse_hex(text.trim())?;
+            Some((addr, addr))
+        }
+    }
+}
+
+fn edit_hex<T>(ui: &Ui, label: &str, value: &mut T, digits: usize) -> bool
+where
+    T: Copy + Into<u32> + std::convert::TryFrom<u32>
+{
+    let mut text = ImString::with_capacity(digits + 1);
+    text.push_str(&format!("{:0width$X}", (*value).into(), width = digits));
+
+    let token = ui.push_item_width(digits as f32 * 10.0 + 12.0);
+    let entered = ui.input_text(&ImString::new(label), &mut text)
+        .chars_hexadecimal(true)
+        .enter_returns_true(true)
+        .build();
+    token.pop(ui);
+
+    if !entered { return false }
+
+    match u32::from_str_radix(text.to_str(), 16).ok().and_then(|v| T::try_from(v).ok()) {
+        Some(new_value) => {
+            *value = new_value;
+            true
+        }
+
+        None => false
+    }
+}
+
+fn edit_flag(ui: &Ui, label: &str, registers: &mut Registers, flag: Flag) -> bool {
+    let mut set = registers.is_flag_set(flag);
+    if ui.checkbox(&ImString::new(label), &mut set) {
+        registers.set_flag(flag, set);
+        return true;
+    }
+
+    false
+}
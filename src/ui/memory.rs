@@ -0,0 +1,364 @@
+use std::borrow::Cow;
+
+use gameboy_rs::gameboy::{GameBoy, debugger::memory::{self, MemoryBanks, MemoryRegion}};
+use imgui::{ChildWindow, ComboBox, Condition, ImStr, ImString, ListClipper, Selectable, StyleColor, Ui, Window, im_str};
+
+use super::parse_hex;
+
+const BYTES_PER_ROW: usize = 16;
+
+// writes stay highlighted for about a second
+const HIGHLIGHT_CYCLES: u64 = 70224 * 60;
+
+// only the first few search results get listed
+const MAX_LISTED_RESULTS: usize = 64;
+
+pub struct MemoryViewer {
+    pub open: bool,
+
+    // index into regions(), 0 is the whole address space
+    region: usize,
+
+    // None follows whatever the cartridge has switched in
+    rom_bank: Option<usize>,
+    ram_bank: Option<usize>,
+
+    selected: Option<u16>,
+    edit_value: ImString,
+    goto: ImString,
+    scroll_to: Option<u16>,
+
+    search: ImString,
+    // lowest address first
+    results: Vec<u16>,
+
+    error: Option<String>
+}
+
+impl MemoryViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+
+            region: 0,
+            rom_bank: None,
+            ram_bank: None,
+
+            selected: None,
+            edit_value: ImString::with_capacity(4),
+            goto: ImString::with_capacity(16),
+            scroll_to: None,
+
+            search: ImString::with_capacity(64),
+            results: Vec::new(),
+
+            error: None
+        }
+    }
+
+    fn banks(&self) -> MemoryBanks {
+        MemoryBanks {
+            rom: self.rom_bank,
+            ram: self.ram_bank
+        }
+    }
+
+    // first and last address shown
+    fn range(&self) -> (u16, u16) {
+        match regions()[self.region] {
+            Some(region) => (region.start(), region.end()),
+            None => (0x0000, 0xFFFF)
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("Memory"))
+            .opened(&mut open)
+            .size([560.0, 480.0], Condition::FirstUseEver)
+            .build(ui, || {
+                self.draw_controls(ui, gb);
+                ui.separator();
+                self.draw_search(ui, gb);
+                ui.separator();
+                self.draw_editor(ui, gb);
+                ui.separator();
+
+                if let Some(error) = &self.error {
+                    ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+                }
+
+                ChildWindow::new("bytes")
+                    .build(ui, || self.draw_bytes(ui, gb));
+            });
+
+        self.open = open;
+    }
+
+    fn draw_controls(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let token = ui.push_item_width(140.0);
+        if ComboBox::new(im_str!("Region")).build_simple(ui, &mut self.region, &regions(), &region_label) {
+            self.scroll_to = Some(self.range().0);
+        }
+        token.pop(ui);
+
+        ui.same_line(0.0);
+        let token = ui.push_item_width(80.0);
+        let entered = ui.input_text(im_str!("Go to"), &mut self.goto)
+            .enter_returns_true(true)
+            .build();
+        token.pop(ui);
+
+        if entered {
+            match parse_hex(self.goto.to_str()) {
+                Some(addr) => self.go_to(addr, gb),
+                None => self.error = Some(format!("Invalid address: {}", self.goto.to_str()))
+            }
+        }
+
+        let (start, end) = self.range();
+        if start <= 0x7FFF && end >= 0x4000 {
+            let current = gb.current_rom_bank() as usize;
+            draw_bank_selector(ui, "ROM bank", &mut self.rom_bank, current, gb.rom_bank_count());
+        }
+
+        if start <= 0xBFFF && end >= 0xA000 && gb.ram_bank_count() > 0 {
+            let current = gb.current_ram_bank();
+            draw_bank_selector(ui, "RAM bank", &mut self.ram_bank, current, gb.ram_bank_count());
+        }
+    }
+
+    fn draw_search(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let token = ui.push_item_width(200.0);
+        let entered = ui.input_text(im_str!("##search"), &mut self.search)
+            .enter_returns_true(true)
+            .build();
+        token.pop(ui);
+
+        ui.same_line(0.0);
+        if ui.button(im_str!("Search"), [0.0, 0.0]) || entered {
+            match memory::parse_byte_pattern(self.search.to_str()) {
+                Ok(pattern) => {
+                    self.results = gb.search_memory(&pattern, self.banks());
+                    self.error = None;
+
+                    if let Some(first) = self.results.first() {
+                        self.go_to(*first, gb);
+                    }
+                }
+
+                Err(err) => self.error = Some(err)
+            }
+        }
+
+        if self.results.is_empty() {
+            ui.same_line(0.0);
+            ui.text_disabled("hex bytes, ?? for any");
+            return;
+        }
+
+        ui.same_line(0.0);
+        if ui.button(im_str!("Next"), [0.0, 0.0]) {
+            let after = self.selected.unwrap_or(0);
+            let next = self.results.iter().find(|addr| **addr > after).or_else(|| self.results.first());
+            if let Some(next) = next.copied() {
+                self.go_to(next, gb);
+            }
+        }
+
+        ui.same_line(0.0);
+        ui.text(format!("{} found", self.results.len()));
+
+        let mut clicked = None;
+        for (i, addr) in self.results.iter().take(MAX_LISTED_RESULTS).enumerate() {
+            if i % 8 != 0 { ui.same_line(0.0); }
+
+            let label = ImString::new(format!("{:04X}##result{}", addr, i));
+            if ui.small_button(&label) {
+                clicked = Some(*addr);
+            }
+        }
+
+        if let Some(addr) = clicked {
+            self.go_to(addr, gb);
+        }
+    }
+
+    fn draw_editor(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let addr = match self.selected {
+            Some(addr) => addr,
+            None => {
+                ui.text_disabled("Click a byte to edit it");
+                return;
+            }
+        };
+
+        ui.text(format!("{} {:04X} =", MemoryRegion::at(addr).name(), addr));
+        ui.same_line(0.0);
+
+        let token = ui.push_item_width(40.0);
+        let entered = ui.input_text(im_str!("##value"), &mut self.edit_value)
+            .chars_hexadecimal(true)
+            .enter_returns_true(true)
+            .build();
+        token.pop(ui);
+
+        if entered {
+            match u8::from_str_radix(self.edit_value.to_str().trim(), 16) {
+                Ok(value) => {
+                    gb.debug_write(addr, self.banks(), value);
+                    self.error = None;
+
+                    // move along so a run of bytes can be typed in
+                    self.select(addr.wrapping_add(1), gb);
+                }
+
+                Err(_) => self.error = Some(format!("Invalid byte: {}", self.edit_value.to_str()))
+            }
+        }
+
+        if let Some(cycle) = gb.last_write(addr) {
+            ui.same_line(0.0);
+            ui.text_disabled(format!("last written {} cycles ago", gb.cycles() - cycle));
+        }
+    }
+
+    fn draw_bytes(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let (start, end) = self.range();
+        let rows = (end as usize - start as usize) / BYTES_PER_ROW + 1;
+        let row_height = ui.text_line_height_with_spacing();
+
+        if let Some(addr) = self.scroll_to.take() {
+            let row = (addr.saturating_sub(start)) as usize / BYTES_PER_ROW;
+            ui.set_scroll_y(row as f32 * row_height);
+        }
+
+        let banks = self.banks();
+        let cycles = gb.cycles();
+        let mut clicked = None;
+
+        let mut clipper = ListClipper::new(rows as i32).items_height(row_height).begin(ui);
+        while clipper.step() {
+            for row in clipper.display_start()..clipper.display_end() {
+                let row_start = start as usize + row as usize * BYTES_PER_ROW;
+                let row_end = (row_start + BYTES_PER_ROW - 1).min(end as usize);
+
+                ui.text_disabled(self.row_label(row_start as u16, gb));
+
+                let mut ascii = String::new();
+                for addr in row_start..=row_end {
+                    let addr = addr as u16;
+                    let value = gb.debug_read(addr, banks);
+
+                    ui.same_line(0.0);
+                    if (addr as usize - row_start) == BYTES_PER_ROW / 2 {
+                        ui.text(" ");
+                        ui.same_line(0.0);
+                    }
+
+                    // the write tracker goes by address, so this is for
+                    // whatever bank was switched in at the time
+                    let color = gb.last_write(addr)
+                        .map(|cycle| cycles - cycle)
+                        .filter(|age| *age < HIGHLIGHT_CYCLES)
+                        .map(|age| {
+                            let fade = 0.7 * (1.0 - age as f32 / HIGHLIGHT_CYCLES as f32);
+                            ui.push_style_color(StyleColor::Text, [1.0, 1.0 - fade, 1.0 - fade, 1.0])
+                        });
+
+                    let label = ImString::new(format!("{:02X}##{}", value, addr));
+                    let selected = self.selected == Some(addr) || self.results.binary_search(&addr).is_ok();
+                    if Selectable::new(&label).selected(selected).size([16.0, 0.0]).build(ui) {
+                        clicked = Some(addr);
+                    }
+
+                    if let Some(color) = color {
+                        color.pop(ui);
+                    }
+
+                    ascii.push(if (0x20..0x7F).contains(&value) { value as char } else { '.' });
+                }
+
+                ui.same_line(0.0);
+                ui.text_disabled(format!(" {}", ascii));
+            }
+        }
+
+        if let Some(addr) = clicked {
+            self.select(addr, gb);
+        }
+    }
+
+    // the bank goes in front of banked addresses, like the debugger shows them
+    fn row_label(&self, addr: u16, gb: &GameBoy) -> String {
+        let region = MemoryRegion::at(addr);
+        match region {
+            MemoryRegion::RomX => {
+                let bank = self.rom_bank.unwrap_or(gb.current_rom_bank() as usize);
+                format!("{:<4} {:02X}:{:04X}", region.name(), bank, addr)
+            }
+
+            MemoryRegion::Sram if gb.ram_bank_count() > 0 => {
+                let bank = self.ram_bank.unwrap_or_else(|| gb.current_ram_bank());
+                format!("{:<4} {:02X}:{:04X}", region.name(), bank, addr)
+            }
+
+            _ => format!("{:<4}    {:04X}", region.name(), addr)
+        }
+    }
+
+    fn select(&mut self, addr: u16, gb: &GameBoy) {
+        self.selected = Some(addr);
+        self.edit_value.clear();
+        self.edit_value.push_str(&format!("{:02X}", gb.debug_read(addr, self.banks())));
+    }
+
+    // switches to the whole address space if addr isn't in the current region
+    fn go_to(&mut self, addr: u16, gb: &GameBoy) {
+        let (start, end) = self.range();
+        if addr < start || addr > end {
+            self.region = 0;
+        }
+
+        self.select(addr, gb);
+        self.scroll_to = Some(addr);
+        self.error = None;
+    }
+}
+
+fn regions() -> Vec<Option<MemoryRegion>> {
+    let mut regions = vec![None];
+    regions.extend(MemoryRegion::ALL.iter().copied().map(Some));
+    regions
+}
+
+fn region_label(region: &Option<MemoryRegion>) -> Cow<'_, ImStr> {
+    match region {
+        Some(region) => Cow::Owned(ImString::new(format!("{} {:04X}-{:04X}", region.name(), region.start(), region.end()))),
+        None => Cow::Borrowed(im_str!("Everything"))
+    }
+}
+
+fn draw_bank_selector(ui: &Ui, label: &str, bank: &mut Option<usize>, current: usize, count: usize) {
+    let mut follow = bank.is_none();
+    if ui.checkbox(&ImString::new(format!("Current {}", label)), &mut follow) {
+        *bank = if follow { None } else { Some(current) };
+    }
+
+    ui.same_line(0.0);
+    match bank {
+        Some(bank) => {
+            let mut value = *bank as i32;
+            let token = ui.push_item_width(80.0);
+            if ui.input_int(&ImString::new(format!("of {}##{}", count, label)), &mut value).build() {
+                *bank = value.clamp(0, count.max(1) as i32 - 1) as usize;
+            }
+            token.pop(ui);
+        }
+
+        None => ui.text_disabled(format!("{:02X} of {}", current, count))
+    }
+}
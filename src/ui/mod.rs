@@ -0,0 +1,20 @@
+pub mod cheats;
+pub mod debugger;
+pub mod memory;
+pub mod oam;
+pub mod raster;
+pub mod timeline;
+pub mod texture;
+pub mod trace;
+pub mod vram;
+
+// "1A2B", "0x1A2B" or "$1A2B"
+pub fn parse_hex(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let text = text.strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+
+    u16::from_str_radix(text, 16).ok()
+}
@@ -0,0 +1,146 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::{memory::MemoryBanks, oam::{self, MAX_SPRITES_PER_LINE, OamEntry, SPRITE_COUNT}}};
+use imgui::{Condition, Image, Ui, Window, im_str};
+
+use super::texture::ShadeTexture;
+
+const SCALE: f32 = 2.0;
+
+// all 40 sprites side by side, 8x16 each
+const ATLAS_WIDTH: usize = SPRITE_COUNT * 8;
+const ATLAS_HEIGHT: usize = 16;
+
+const PICKED_COLOUR: [f32; 4] = [0.3, 1.0, 0.3, 1.0];
+const DROPPED_COLOUR: [f32; 4] = [1.0, 0.6, 0.2, 1.0];
+
+// LCDC bit 2
+const TALL_SPRITES: u8 = 0b0000_0100;
+
+pub struct OamViewer {
+    pub open: bool,
+
+    only_current_line: bool,
+    texture: Option<ShadeTexture>
+}
+
+impl OamViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+
+            only_current_line: false,
+            texture: None
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("OAM"))
+            .opened(&mut open)
+            .size([420.0, 520.0], Condition::FirstUseEver)
+            .build(ui, || self.draw_sprites(ui, gb));
+
+        self.open = open;
+    }
+
+    fn draw_sprites(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let tall = gb.debug_read(0xFF40, MemoryBanks::default()) & TALL_SPRITES != 0;
+        let palettes = [gb.debug_read(0xFF48, MemoryBanks::default()), gb.debug_read(0xFF49, MemoryBanks::default())];
+        let entries = oam::entries(&gb.oam());
+        let scan = gb.oam_scan().clone();
+
+        // anything on the line the ppu didn't pick got dropped by the 10 sprite limit
+        let dropped: Vec<usize> = entries.iter()
+            .filter(|entry| entry.on_line(scan.line, tall) && !scan.contains(entry.index))
+            .map(|entry| entry.index)
+            .collect();
+
+        ui.text(format!("Line {}: {} of {} sprites picked", scan.line, scan.sprites.len(), MAX_SPRITES_PER_LINE));
+        if !dropped.is_empty() {
+            ui.same_line(0.0);
+            ui.text_colored(DROPPED_COLOUR, format!("{} dropped", dropped.len()));
+        }
+
+        ui.text_disabled(format!("{} sprites", if tall { "8x16" } else { "8x8" }));
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Only this line"), &mut self.only_current_line);
+
+        let vram = gb.vram();
+        let texture = self.texture.get_or_insert_with(|| ShadeTexture::new(ATLAS_WIDTH, ATLAS_HEIGHT));
+        texture.update(&render_atlas(&vram, &entries, tall, palettes));
+
+        ui.separator();
+        ui.columns(7, im_str!("oam"), true);
+        for heading in ["#", "Y", "X", "Tile", "Palette", "Flags", ""].iter() {
+            ui.text(heading);
+            ui.next_column();
+        }
+        ui.separator();
+
+        let height = OamEntry::height(tall) as f32;
+        for entry in entries.iter() {
+            let picked = scan.contains(entry.index);
+            let was_dropped = dropped.contains(&entry.index);
+
+            if self.only_current_line && !picked && !was_dropped {
+                continue;
+            }
+
+            let index = format!("{}", entry.index);
+            if picked {
+                ui.text_colored(PICKED_COLOUR, index);
+            } else if was_dropped {
+                ui.text_colored(DROPPED_COLOUR, index);
+            } else {
+                ui.text(index);
+            }
+            ui.next_column();
+
+            ui.text(format!("{:02X} ({})", entry.y, entry.y as i16 - 16));
+            ui.next_column();
+            ui.text(format!("{:02X} ({})", entry.x, entry.x as i16 - 8));
+            ui.next_column();
+            ui.text(format!("{:02X}", entry.tile));
+            ui.next_column();
+            ui.text(format!("OBP{}", entry.palette()));
+            ui.next_column();
+
+            let mut flags = Vec::new();
+            if entry.xflip() { flags.push("X flip"); }
+            if entry.yflip() { flags.push("Y flip"); }
+            if entry.behind_bg() { flags.push("behind BG"); }
+            ui.text(flags.join(", "));
+            ui.next_column();
+
+            let x = (entry.index * 8) as f32 / ATLAS_WIDTH as f32;
+            let width = 8.0 / ATLAS_WIDTH as f32;
+            Image::new(texture.texture_id(), [8.0 * SCALE, height * SCALE])
+                .uv0([x, 0.0])
+                .uv1([x + width, height / ATLAS_HEIGHT as f32])
+                .build(ui);
+            ui.next_column();
+        }
+
+        ui.columns(1, im_str!("oam"), false);
+        ui.separator();
+        ui.text_colored(PICKED_COLOUR, "picked for this line");
+        ui.same_line(0.0);
+        ui.text_colored(DROPPED_COLOUR, "on the line but dropped");
+    }
+}
+
+fn render_atlas(vram: &[u8], entries: &[OamEntry], tall: bool, palettes: [u8; 2]) -> Vec<u8> {
+    let mut pixels = vec![0; ATLAS_WIDTH * ATLAS_HEIGHT];
+
+    for entry in entries {
+        let sprite = oam::render_sprite(vram, entry, tall, palettes[entry.palette()]);
+        for (row, line) in sprite.chunks(8).enumerate() {
+            let offset = row * ATLAS_WIDTH + entry.index * 8;
+            pixels[offset..offset + 8].copy_from_slice(line);
+        }
+    }
+
+    pixels
+}
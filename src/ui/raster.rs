@@ -0,0 +1,190 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::raster::{LINES, LineHistory, LineRegisters, RasterRegister}};
+use imgui::{ChildWindow, Condition, ListClipper, Ui, Window, im_str};
+
+const DOTS_PER_LINE: f32 = 456.0;
+
+// the timeline is one pixel per dot, this many per line
+const LINE_HEIGHT: f32 = 2.0;
+
+// mode 3 can't start before dot 80
+const MODE3_START: f32 = 80.0;
+
+const MODE3_COLOUR: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+const LY_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 0.5];
+const CHANGED_COLOUR: [f32; 4] = [1.0, 0.8, 0.3, 1.0];
+
+fn register_colour(register: RasterRegister) -> [f32; 4] {
+    match register {
+        RasterRegister::Lcdc => [1.0, 0.3, 0.3, 1.0],
+        RasterRegister::Scy => [0.3, 1.0, 0.3, 1.0],
+        RasterRegister::Scx => [0.3, 0.6, 1.0, 1.0],
+        RasterRegister::Bgp => [1.0, 0.3, 1.0, 1.0],
+        RasterRegister::Wy => [1.0, 1.0, 0.3, 1.0],
+        RasterRegister::Wx => [0.3, 1.0, 1.0, 1.0]
+    }
+}
+
+pub struct RasterViewer {
+    pub open: bool,
+
+    // the frame being drawn instead of the last whole one
+    current_frame: bool,
+    only_writes: bool
+}
+
+impl RasterViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+
+            current_frame: false,
+            only_writes: false
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("Raster"))
+            .opened(&mut open)
+            .size([500.0, 700.0], Condition::FirstUseEver)
+            .build(ui, || {
+                let state = gb.ppu_state();
+                ui.text(format!(
+                    "LY {} dot {} mode {:?}, window line {}{}",
+                    state.ly, state.dot, state.mode, state.window_line,
+                    if state.lcd_on { "" } else { " (lcd off)" }
+                ));
+
+                ui.checkbox(im_str!("Frame being drawn"), &mut self.current_frame);
+                ui.same_line(0.0);
+                ui.checkbox(im_str!("Only lines with writes"), &mut self.only_writes);
+
+                let history = gb.raster_history();
+                let lines = if self.current_frame { history.current_frame() } else { history.last_frame() };
+                let ly = if self.current_frame && state.lcd_on { Some(state.ly) } else { None };
+
+                self.draw_timeline(ui, lines, ly);
+                ui.separator();
+                self.draw_table(ui, lines);
+            });
+
+        self.open = open;
+    }
+
+    // every line as a strip of dots, with mode 3 shaded and a tick for each write
+    fn draw_timeline(&self, ui: &Ui, lines: &[LineHistory], ly: Option<u8>) {
+        let origin = ui.cursor_screen_pos();
+        let size = [DOTS_PER_LINE, LINES as f32 * LINE_HEIGHT];
+        ui.invisible_button(im_str!("timeline"), size);
+        let hovered = ui.is_item_hovered();
+
+        {
+            let draw_list = ui.get_window_draw_list();
+            draw_list.add_rect(origin, [origin[0] + size[0], origin[1] + size[1]], [0.1, 0.1, 0.1, 1.0]).filled(true).build();
+
+            for (line, history) in lines.iter().enumerate() {
+                let y = origin[1] + line as f32 * LINE_HEIGHT;
+
+                if history.mode3.is_some() {
+                    let start = origin[0] + MODE3_START;
+                    draw_list.add_rect([start, y], [start + 172.0, y + LINE_HEIGHT], MODE3_COLOUR).filled(true).build();
+                }
+
+                for write in history.writes.iter() {
+                    let x = origin[0] + write.dot as f32;
+                    draw_list.add_rect([x, y], [x + 2.0, y + LINE_HEIGHT], register_colour(write.register)).filled(true).build();
+                }
+            }
+
+            if let Some(ly) = ly {
+                let y = origin[1] + ly as f32 * LINE_HEIGHT;
+                draw_list.add_line([origin[0], y], [origin[0] + size[0], y], LY_COLOUR).build();
+            }
+        }
+
+        if hovered {
+            let line = ((ui.io().mouse_pos[1] - origin[1]) / LINE_HEIGHT) as usize;
+            if let Some(history) = lines.get(line) {
+                ui.tooltip(|| {
+                    ui.text(format!("Line {}", line));
+                    if let Some(registers) = &history.mode3 {
+                        ui.text(describe_registers(registers));
+                    }
+
+                    for write in history.writes.iter() {
+                        ui.text_colored(register_colour(write.register), format!("{} = {:02X} at dot {}", write.register.name(), write.value, write.dot));
+                    }
+                });
+            }
+        }
+
+        for (i, register) in RasterRegister::ALL.iter().enumerate() {
+            if i > 0 { ui.same_line(0.0); }
+            ui.text_colored(register_colour(*register), register.name());
+        }
+    }
+
+    // the values each line started drawing with, changes from the line above highlighted
+    fn draw_table(&self, ui: &Ui, lines: &[LineHistory]) {
+        let shown: Vec<usize> = (0..lines.len())
+            .filter(|line| !self.only_writes || !lines[*line].writes.is_empty())
+            .collect();
+
+        ChildWindow::new("lines").build(ui, || {
+            ui.columns(8, im_str!("raster"), true);
+            for heading in ["Line", "LCDC", "SCY", "SCX", "BGP", "WY", "WX", "Writes"].iter() {
+                ui.text(heading);
+                ui.next_column();
+            }
+            ui.separator();
+
+            let mut clipper = ListClipper::new(shown.len() as i32).begin(ui);
+            while clipper.step() {
+                for row in clipper.display_start()..clipper.display_end() {
+                    let line = shown[row as usize];
+                    let history = &lines[line];
+                    let above = line.checked_sub(1).and_then(|above| lines[above].mode3);
+
+                    ui.text(format!("{}", line));
+                    ui.next_column();
+
+                    for register in RasterRegister::ALL.iter() {
+                        match history.mode3 {
+                            Some(registers) => {
+                                let value = format!("{:02X}", registers.get(*register));
+                                let changed = above.map(|above| above.get(*register) != registers.get(*register)).unwrap_or(false);
+
+                                if changed {
+                                    ui.text_colored(CHANGED_COLOUR, value);
+                                } else {
+                                    ui.text(value);
+                                }
+                            }
+
+                            None => ui.text_disabled("--")
+                        }
+                        ui.next_column();
+                    }
+
+                    let writes: Vec<String> = history.writes.iter()
+                        .map(|write| format!("{}={:02X}@{}", write.register.name(), write.value, write.dot))
+                        .collect();
+                    ui.text(writes.join(" "));
+                    ui.next_column();
+                }
+            }
+
+            ui.columns(1, im_str!("raster"), false);
+        });
+    }
+}
+
+fn describe_registers(registers: &LineRegisters) -> String {
+    RasterRegister::ALL.iter()
+        .map(|register| format!("{} {:02X}", register.name(), registers.get(*register)))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
@@ -0,0 +1,79 @@
+use std::ffi::c_void;
+
+use gl::types::GLuint;
+use imgui::TextureId;
+
+// A gl texture for showing grey shades (like the frame buffer) in an imgui
+// Image. The opengl renderer binds TextureIds as gl texture names, so the
+// id can be passed straight through.
+pub struct ShadeTexture {
+    id: GLuint,
+    width: usize,
+    height: usize
+}
+
+impl ShadeTexture {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut id = 0;
+        let data = vec![0u8; width * height * 3];
+
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const c_void
+            );
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Self { id, width, height }
+    }
+
+    pub fn update(&self, shades: &[u8]) {
+        let mut data = Vec::with_capacity(self.width * self.height * 3);
+        for shade in shades.iter().take(self.width * self.height) {
+            data.extend_from_slice(&[*shade, *shade, *shade]);
+        }
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const c_void
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    pub fn texture_id(&self) -> TextureId {
+        TextureId::from(self.id as usize)
+    }
+}
+
+impl Drop for ShadeTexture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
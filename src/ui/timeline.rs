@@ -0,0 +1,209 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::{raster::LINES, timeline::{self, TimelineEntry, TimelineEvent}}, interupt::InterruptFlag};
+use imgui::{ChildWindow, Condition, ImString, ListClipper, Ui, Window, im_str};
+
+const DOTS_PER_LINE: f32 = 456.0;
+const LINE_HEIGHT: f32 = 2.0;
+
+// how close (in pixels) the mouse has to be for an event to show in the tooltip
+const HOVER_DISTANCE: f32 = 4.0;
+
+fn interrupt_colour(flag: InterruptFlag) -> [f32; 4] {
+    match flag {
+        InterruptFlag::VBlank => [1.0, 0.3, 0.3, 1.0],
+        InterruptFlag::Stat => [0.3, 1.0, 0.3, 1.0],
+        InterruptFlag::Timer => [0.3, 0.6, 1.0, 1.0],
+        InterruptFlag::Serial => [1.0, 0.3, 1.0, 1.0],
+        InterruptFlag::Joypad => [1.0, 1.0, 0.3, 1.0]
+    }
+}
+
+fn event_colour(event: &TimelineEvent) -> [f32; 4] {
+    match event {
+        TimelineEvent::InterruptRequested(flag) | TimelineEvent::InterruptServiced(flag) => interrupt_colour(*flag),
+        TimelineEvent::HaltEntered | TimelineEvent::HaltExited => [0.8, 0.8, 0.8, 1.0],
+        TimelineEvent::OamDma { .. } => [1.0, 0.6, 0.2, 1.0],
+        TimelineEvent::LcdOn | TimelineEvent::LcdOff => [1.0, 1.0, 1.0, 1.0]
+    }
+}
+
+pub struct TimelineWindow {
+    pub open: bool,
+
+    // show the last whole frame as it goes
+    follow: bool,
+    frame: i32,
+
+    path: ImString,
+    status: Option<String>
+}
+
+impl TimelineWindow {
+    pub fn new() -> Self {
+        let mut path = ImString::with_capacity(256);
+        path.push_str("timeline.csv");
+
+        Self {
+            open: false,
+
+            follow: true,
+            frame: 0,
+
+            path,
+            status: None
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("Timeline"))
+            .opened(&mut open)
+            .size([500.0, 720.0], Condition::FirstUseEver)
+            .build(ui, || {
+                self.draw_controls(ui, gb);
+                ui.separator();
+
+                if self.follow {
+                    self.frame = gb.frames().saturating_sub(1) as i32;
+                }
+
+                let entries = gb.timeline().frame(self.frame.max(0) as u64);
+                self.draw_timeline(ui, &entries);
+                ui.separator();
+                self.draw_list(ui, &entries);
+            });
+
+        self.open = open;
+    }
+
+    fn draw_controls(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let timeline = gb.timeline_mut();
+        if timeline.is_recording() {
+            if ui.button(im_str!("Stop"), [0.0, 0.0]) {
+                timeline.stop();
+            }
+        } else if ui.button(im_str!("Record"), [0.0, 0.0]) {
+            timeline.start();
+        }
+
+        ui.same_line(0.0);
+        if ui.button(im_str!("Clear"), [0.0, 0.0]) {
+            timeline.clear();
+        }
+
+        ui.same_line(0.0);
+        ui.text(format!("{} events", timeline.entries().len()));
+
+        ui.checkbox(im_str!("Follow"), &mut self.follow);
+        if !self.follow {
+            ui.same_line(0.0);
+            ui.input_int(im_str!("Frame"), &mut self.frame).build();
+        }
+
+        ui.input_text(im_str!("CSV"), &mut self.path).build();
+        ui.same_line(0.0);
+        if ui.button(im_str!("Save"), [0.0, 0.0]) {
+            let path = self.path.to_str().trim();
+            self.status = Some(match timeline.save_csv(path) {
+                Ok(()) => format!("Saved {} events to {}", timeline.entries().len(), path),
+                Err(err) => format!("Unable to write {}: {}", path, err)
+            });
+        }
+
+        if let Some(status) = &self.status {
+            ui.text_disabled(status);
+        }
+    }
+
+    // a frame as a grid of lines and dots (like the raster viewer), requests
+    // are outlined and services are filled in
+    fn draw_timeline(&self, ui: &Ui, entries: &[TimelineEntry]) {
+        let origin = ui.cursor_screen_pos();
+        let size = [DOTS_PER_LINE, LINES as f32 * LINE_HEIGHT];
+        ui.invisible_button(im_str!("timeline"), size);
+        let hovered = ui.is_item_hovered();
+
+        let position = |entry: &TimelineEntry| [origin[0] + entry.dot as f32, origin[1] + entry.ly as f32 * LINE_HEIGHT];
+
+        {
+            let draw_list = ui.get_window_draw_list();
+            draw_list.add_rect(origin, [origin[0] + size[0], origin[1] + size[1]], [0.1, 0.1, 0.1, 1.0]).filled(true).build();
+
+            // where vblank starts
+            let vblank = origin[1] + 144.0 * LINE_HEIGHT;
+            draw_list.add_line([origin[0], vblank], [origin[0] + size[0], vblank], [0.4, 0.4, 0.4, 1.0]).build();
+
+            for entry in entries {
+                let [x, y] = position(entry);
+                let colour = event_colour(&entry.event);
+                let filled = !matches!(entry.event, TimelineEvent::InterruptRequested(_));
+
+                draw_list.add_rect([x - 1.0, y - 1.0], [x + 2.0, y + LINE_HEIGHT + 1.0], colour).filled(filled).build();
+            }
+        }
+
+        if hovered {
+            let mouse = ui.io().mouse_pos;
+            let near: Vec<&TimelineEntry> = entries.iter()
+                .filter(|entry| {
+                    let [x, y] = position(entry);
+                    (x - mouse[0]).abs() <= HOVER_DISTANCE && (y - mouse[1]).abs() <= HOVER_DISTANCE
+                })
+                .collect();
+
+            ui.tooltip(|| {
+                let line = ((mouse[1] - origin[1]) / LINE_HEIGHT).max(0.0) as u32;
+                let dot = (mouse[0] - origin[0]).max(0.0) as u32;
+                ui.text(format!("LY {} dot {}", line, dot));
+
+                for entry in near {
+                    ui.text_colored(event_colour(&entry.event), format!("{} (cycle {})", entry.event, entry.frame_cycle));
+                }
+            });
+        }
+
+        for flag in [InterruptFlag::VBlank, InterruptFlag::Stat, InterruptFlag::Timer, InterruptFlag::Serial, InterruptFlag::Joypad].iter() {
+            ui.text_colored(interrupt_colour(*flag), timeline::interrupt_name(*flag));
+            ui.same_line(0.0);
+        }
+        ui.text_disabled("(outlined: requested, filled: serviced)");
+    }
+
+    fn draw_list(&self, ui: &Ui, entries: &[TimelineEntry]) {
+        ChildWindow::new("events").build(ui, || {
+            ui.columns(5, im_str!("timeline"), true);
+            for heading in ["Cycle", "LY", "Dot", "Event", "Latency"].iter() {
+                ui.text(heading);
+                ui.next_column();
+            }
+            ui.separator();
+
+            let mut clipper = ListClipper::new(entries.len() as i32).begin(ui);
+            while clipper.step() {
+                for row in clipper.display_start()..clipper.display_end() {
+                    let entry = &entries[row as usize];
+
+                    ui.text(format!("{}", entry.frame_cycle));
+                    ui.next_column();
+                    ui.text(format!("{}", entry.ly));
+                    ui.next_column();
+                    ui.text(format!("{}", entry.dot));
+                    ui.next_column();
+                    ui.text_colored(event_colour(&entry.event), format!("{}", entry.event));
+                    ui.next_column();
+
+                    // only within the frame, a request from the frame before won't be found
+                    match timeline::latency(entries, row as usize) {
+                        Some(cycles) => ui.text(format!("{}", cycles)),
+                        None => ui.text("")
+                    }
+                    ui.next_column();
+                }
+            }
+
+            ui.columns(1, im_str!("timeline"), false);
+        });
+    }
+}
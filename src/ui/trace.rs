@@ -0,0 +1,171 @@
+use gameboy_rs::gameboy::{GameBoy, trace::{TraceFormat, TraceOptions, TraceStart, compare::CompareOptions}};
+use imgui::{Condition, ImString, Ui, Window, im_str};
+
+use super::parse_hex;
+
+pub struct TraceWindow {
+    pub open: bool,
+
+    path: ImString,
+    format: TraceFormat,
+
+    // 0 = boot, 1 = pc, 2 = frame
+    start_kind: u8,
+    start_pc: ImString,
+    start_frame: i32,
+
+    reference_path: ImString,
+    report: Option<String>,
+
+    error: Option<String>
+}
+
+impl TraceWindow {
+    pub fn new() -> Self {
+        let mut path = ImString::with_capacity(256);
+        path.push_str("trace.log");
+
+        Self {
+            open: false,
+
+            path,
+            format: TraceFormat::GameboyDoctor,
+
+            start_kind: 0,
+            start_pc: ImString::with_capacity(8),
+            start_frame: 0,
+
+            reference_path: ImString::with_capacity(256),
+            report: None,
+
+            error: None
+        }
+    }
+
+    fn options(&self) -> Result<TraceOptions, String> {
+        let path = self.path.to_str().trim();
+        if path.is_empty() {
+            return Err("Missing trace path".to_owned());
+        }
+
+        let start = match self.start_kind {
+            1 => match parse_hex(self.start_pc.to_str()) {
+                Some(pc) => TraceStart::Pc(pc),
+                None => return Err(format!("Invalid pc: {}", self.start_pc.to_str()))
+            },
+            2 => TraceStart::Frame(self.start_frame.max(0) as u64),
+            _ => TraceStart::Boot
+        };
+
+        Ok(TraceOptions {
+            start,
+            format: self.format,
+            ..TraceOptions::new(path)
+        })
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("Trace log"))
+            .opened(&mut open)
+            .size([320.0, 240.0], Condition::FirstUseEver)
+            .build(ui, || {
+                let mut doctor_ly = gb.doctor_ly();
+                if ui.checkbox(im_str!("LY reads 0x90 (for Gameboy Doctor)"), &mut doctor_ly) {
+                    gb.set_doctor_ly(doctor_ly);
+                }
+
+                self.draw_trace(ui, gb);
+
+                ui.separator();
+                self.draw_compare(ui, gb);
+
+                if let Some(error) = &self.error {
+                    ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+                }
+            });
+
+        self.open = open;
+    }
+
+    fn draw_trace(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if let Some(trace) = gb.trace() {
+            ui.text(format!("Tracing to {}", trace.options().path.display()));
+
+            if trace.is_started() {
+                ui.text(format!("{} instructions logged", trace.instructions_logged()));
+            } else {
+                ui.text_disabled("Waiting to start");
+            }
+
+            if ui.button(im_str!("Stop"), [0.0, 0.0]) {
+                gb.stop_trace();
+            }
+
+            return;
+        }
+
+        ui.input_text(im_str!("Path"), &mut self.path).build();
+
+        ui.text("Format");
+        ui.radio_button(im_str!("Gameboy Doctor"), &mut self.format, TraceFormat::GameboyDoctor);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("Readable"), &mut self.format, TraceFormat::Disassembly);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("Binary"), &mut self.format, TraceFormat::Binary);
+
+        ui.text("Start");
+        ui.radio_button(im_str!("Now"), &mut self.start_kind, 0);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("At PC"), &mut self.start_kind, 1);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("At frame"), &mut self.start_kind, 2);
+
+        match self.start_kind {
+            1 => { ui.input_text(im_str!("PC"), &mut self.start_pc).build(); }
+            2 => { ui.input_int(im_str!("Frame"), &mut self.start_frame).build(); }
+            _ => {}
+        }
+
+        if ui.button(im_str!("Start"), [0.0, 0.0]) {
+            let started = self.options().and_then(|options| {
+                gb.start_trace(options).map_err(|err| format!("Unable to create trace: {}", err))
+            });
+
+            self.error = started.err();
+        }
+    }
+
+    fn draw_compare(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        ui.text("Compare with a reference trace");
+
+        if let Some(compare) = gb.trace_compare() {
+            match compare.result() {
+                Some(result) => self.report = Some(result.to_string()),
+                None if compare.is_started() => ui.text(format!("{} instructions matched", compare.compared())),
+                None => ui.text_disabled("Waiting to start")
+            }
+
+            if ui.button(im_str!("Stop comparing"), [0.0, 0.0]) {
+                gb.stop_trace_compare();
+            }
+        } else {
+            ui.input_text(im_str!("Reference"), &mut self.reference_path).build();
+
+            if ui.button(im_str!("Compare"), [0.0, 0.0]) {
+                let options = CompareOptions::new(self.reference_path.to_str().trim());
+                self.report = None;
+                self.error = gb.start_trace_compare(options)
+                    .err()
+                    .map(|err| format!("Unable to read {}: {}", self.reference_path.to_str(), err));
+            }
+        }
+
+        if let Some(report) = &self.report {
+            ui.text(report);
+        }
+    }
+}
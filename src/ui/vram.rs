@@ -0,0 +1,263 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::{memory::MemoryBanks, vram::{self, TILE_COUNT, TILEMAP_SIZE, TILES_PER_ROW, TILESET_HEIGHT, TILESET_WIDTH, TileMap}}};
+use imgui::{Condition, DrawListMut, Image, Ui, Window, im_str};
+
+use super::texture::ShadeTexture;
+
+const SCALE: f32 = 2.0;
+
+// size of a tile in the hover tooltip
+const PREVIEW_SIZE: f32 = 64.0;
+
+const VIEWPORT_COLOUR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+const WINDOW_COLOUR: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
+
+// LCDC bits
+const BG_MAP: u8 = 0b0000_1000;
+const TILE_DATA: u8 = 0b0001_0000;
+const WINDOW_ENABLE: u8 = 0b0010_0000;
+const WINDOW_MAP: u8 = 0b0100_0000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Palette {
+    Bgp,
+    Obp0,
+    Obp1,
+
+    // colour 0 white to colour 3 black
+    Raw
+}
+
+impl Palette {
+    fn value(&self, gb: &GameBoy) -> u8 {
+        match self {
+            Palette::Bgp => gb.debug_read(0xFF47, MemoryBanks::default()),
+            Palette::Obp0 => gb.debug_read(0xFF48, MemoryBanks::default()),
+            Palette::Obp1 => gb.debug_read(0xFF49, MemoryBanks::default()),
+            Palette::Raw => 0b1110_0100
+        }
+    }
+}
+
+fn draw_palette_selector(ui: &Ui, palette: &mut Palette) {
+    ui.radio_button(im_str!("BGP"), palette, Palette::Bgp);
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("OBP0"), palette, Palette::Obp0);
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("OBP1"), palette, Palette::Obp1);
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("Raw"), palette, Palette::Raw);
+}
+
+// pixel under the mouse in the last item (an image drawn at SCALE)
+fn hovered_pixel(ui: &Ui, width: usize, height: usize) -> Option<(usize, usize)> {
+    if !ui.is_item_hovered() { return None }
+
+    let origin = ui.item_rect_min();
+    let mouse = ui.io().mouse_pos;
+    let x = ((mouse[0] - origin[0]) / SCALE) as usize;
+    let y = ((mouse[1] - origin[1]) / SCALE) as usize;
+
+    if x < width && y < height { Some((x, y)) } else { None }
+}
+
+// one tile out of the tileset texture
+fn draw_tile_preview(ui: &Ui, texture: &ShadeTexture, tile: usize) {
+    let x = (tile % TILES_PER_ROW * 8) as f32;
+    let y = (tile / TILES_PER_ROW * 8) as f32;
+    let (width, height) = (TILESET_WIDTH as f32, TILESET_HEIGHT as f32);
+
+    Image::new(texture.texture_id(), [PREVIEW_SIZE, PREVIEW_SIZE])
+        .uv0([x / width, y / height])
+        .uv1([(x + 8.0) / width, (y + 8.0) / height])
+        .build(ui);
+}
+
+// where a tile can be used from, tiles 128-255 are shared by both modes
+fn describe_tile(tile: usize) -> String {
+    let index = tile & 0xFF;
+    match tile {
+        0..=127 => format!("${:02X} from 8000", index),
+        128..=255 => format!("${:02X} from 8000 or 8800", index),
+        _ => format!("${:02X} from 8800", index)
+    }
+}
+
+pub struct TileViewer {
+    pub open: bool,
+
+    palette: Palette,
+    texture: Option<ShadeTexture>
+}
+
+impl TileViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+
+            palette: Palette::Bgp,
+            texture: None
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("Tiles"))
+            .opened(&mut open)
+            .size([290.0, 470.0], Condition::FirstUseEver)
+            .build(ui, || {
+                draw_palette_selector(ui, &mut self.palette);
+
+                let texture = self.texture.get_or_insert_with(|| ShadeTexture::new(TILESET_WIDTH, TILESET_HEIGHT));
+                texture.update(&vram::render_tileset(&gb.vram(), self.palette.value(gb)));
+
+                Image::new(texture.texture_id(), [TILESET_WIDTH as f32 * SCALE, TILESET_HEIGHT as f32 * SCALE]).build(ui);
+
+                if let Some((x, y)) = hovered_pixel(ui, TILESET_WIDTH, TILESET_HEIGHT) {
+                    let tile = (y / 8) * TILES_PER_ROW + x / 8;
+                    if tile < TILE_COUNT {
+                        ui.tooltip(|| {
+                            ui.text(format!("Tile {} at {:04X}", tile, 0x8000 + tile * 16));
+                            ui.text(describe_tile(tile));
+                            draw_tile_preview(ui, texture, tile);
+                        });
+                    }
+                }
+            });
+
+        self.open = open;
+    }
+}
+
+pub struct TilemapViewer {
+    pub open: bool,
+
+    map: TileMap,
+    palette: Palette,
+    show_viewport: bool,
+    show_window: bool,
+
+    texture: Option<ShadeTexture>,
+
+    // for the tile previews in the tooltip
+    tiles_texture: Option<ShadeTexture>
+}
+
+impl TilemapViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+
+            map: TileMap::Low,
+            palette: Palette::Bgp,
+            show_viewport: true,
+            show_window: true,
+
+            texture: None,
+            tiles_texture: None
+        }
+    }
+
+    pub fn draw(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        if !self.open { return }
+
+        let mut open = self.open;
+
+        Window::new(im_str!("Tilemaps"))
+            .opened(&mut open)
+            .size([540.0, 640.0], Condition::FirstUseEver)
+            .build(ui, || self.draw_map(ui, gb));
+
+        self.open = open;
+    }
+
+    fn draw_map(&mut self, ui: &Ui, gb: &mut GameBoy) {
+        let lcdc = gb.debug_read(0xFF40, MemoryBanks::default());
+        let bg_map = TileMap::from_lcdc(lcdc, BG_MAP);
+        let window_map = TileMap::from_lcdc(lcdc, WINDOW_MAP);
+        let signed_addressing = lcdc & TILE_DATA == 0;
+
+        ui.radio_button(im_str!("9800"), &mut self.map, TileMap::Low);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("9C00"), &mut self.map, TileMap::High);
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Viewport"), &mut self.show_viewport);
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Window"), &mut self.show_window);
+
+        draw_palette_selector(ui, &mut self.palette);
+
+        ui.text_disabled(format!(
+            "BG {:04X}, window {:04X}{}, tiles from {}",
+            bg_map.addr(),
+            window_map.addr(),
+            if lcdc & WINDOW_ENABLE != 0 { "" } else { " (off)" },
+            if signed_addressing { "8800" } else { "8000" }
+        ));
+
+        let vram = gb.vram();
+        let palette = self.palette.value(gb);
+
+        let texture = self.texture.get_or_insert_with(|| ShadeTexture::new(TILEMAP_SIZE, TILEMAP_SIZE));
+        texture.update(&vram::render_tilemap(&vram, self.map, signed_addressing, palette));
+
+        let size = TILEMAP_SIZE as f32 * SCALE;
+        Image::new(texture.texture_id(), [size, size]).build(ui);
+        let origin = ui.item_rect_min();
+        let hovered = hovered_pixel(ui, TILEMAP_SIZE, TILEMAP_SIZE);
+
+        {
+            let draw_list = ui.get_window_draw_list();
+
+            if self.show_viewport && self.map == bg_map {
+                let scx = gb.debug_read(0xFF43, MemoryBanks::default()) as f32;
+                let scy = gb.debug_read(0xFF42, MemoryBanks::default()) as f32;
+                draw_wrapped_rect(&draw_list, origin, [scx, scy], [160.0, 144.0], VIEWPORT_COLOUR);
+            }
+
+            // the window always starts at the top left of its map, and
+            // covers the screen from (WX - 7, WY)
+            let wx = gb.debug_read(0xFF4B, MemoryBanks::default()) as f32;
+            let wy = gb.debug_read(0xFF4A, MemoryBanks::default()) as f32;
+            let window_visible = lcdc & WINDOW_ENABLE != 0 && wx <= 166.0 && wy <= 143.0;
+
+            if self.show_window && self.map == window_map && window_visible {
+                let width = 160.0 - (wx - 7.0).max(0.0);
+                let height = 144.0 - wy;
+                draw_list.add_rect(origin, [origin[0] + width * SCALE, origin[1] + height * SCALE], WINDOW_COLOUR).build();
+            }
+        }
+
+        if let Some((x, y)) = hovered {
+            let tile = vram::map_tile_at(&vram, self.map, signed_addressing, x / 8, y / 8);
+            let tile_number = ((tile.tile_addr - 0x8000) / 16) as usize;
+
+            let tiles_texture = self.tiles_texture.get_or_insert_with(|| ShadeTexture::new(TILESET_WIDTH, TILESET_HEIGHT));
+            tiles_texture.update(&vram::render_tileset(&vram, palette));
+
+            ui.tooltip(|| {
+                ui.text(format!("{}, {} at {:04X}", x / 8, y / 8, tile.map_addr));
+                ui.text(format!("Tile ${:02X} at {:04X}", tile.tile_index, tile.tile_addr));
+                draw_tile_preview(ui, tiles_texture, tile_number);
+            });
+        }
+    }
+}
+
+// the viewport wraps around the edges of the map
+fn draw_wrapped_rect(draw_list: &DrawListMut, origin: [f32; 2], pos: [f32; 2], size: [f32; 2], colour: [f32; 4]) {
+    let map_size = TILEMAP_SIZE as f32;
+    let max = [origin[0] + map_size * SCALE, origin[1] + map_size * SCALE];
+
+    draw_list.with_clip_rect_intersect(origin, max, || {
+        for offset_x in [0.0, -map_size].iter() {
+            for offset_y in [0.0, -map_size].iter() {
+                let x = origin[0] + (pos[0] + offset_x) * SCALE;
+                let y = origin[1] + (pos[1] + offset_y) * SCALE;
+                draw_list.add_rect([x, y], [x + size[0] * SCALE, y + size[1] * SCALE], colour).build();
+            }
+        }
+    });
+}
@@ -39,7 +39,7 @@ macro_rules! blargg_test {
                     s.tick();
                 }
 
-                let fb = s.get_frame_buffer();
+                let fb = s.get_shade_buffer();
 
                 pb.pop();
                 pb.pop();
@@ -0,0 +1,108 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::{BreakReason, expr::{Condition, Expr, ExprError, LogMessage}, memory::MemoryBanks}};
+use common::{create_gameboy, run_until_paused};
+
+mod common;
+
+const PROGRAM: [u8; 8] = [
+    0x3E, 0x00,       // 0x150: ld a, 0
+    0x3C,             // 0x152: inc a
+    0xEA, 0x00, 0xC0, // 0x153: ld (0xC000), a
+    0x18, 0xFA        // 0x156: jr 0x152
+];
+
+fn evaluate(gb: &GameBoy, text: &str) -> i64 {
+    gb.evaluate(&Expr::parse(text).unwrap())
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(Expr::parse("a =="), Err(ExprError::UnexpectedEnd));
+    assert_eq!(Expr::parse("foo"), Err(ExprError::UnknownName(String::from("foo"))));
+    assert_eq!(Expr::parse("(a"), Err(ExprError::UnexpectedEnd));
+    assert_eq!(Expr::parse("a # 1"), Err(ExprError::InvalidCharacter('#')));
+    assert_eq!(Expr::parse("0xZZ"), Err(ExprError::InvalidNumber(String::from("0xzz"))));
+    assert_eq!(Expr::parse("1 2"), Err(ExprError::Expected("end of expression")));
+    assert_eq!(Expr::parse(&format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000))), Err(ExprError::TooDeep));
+    assert_eq!(Expr::parse(&format!("{}1", "-".repeat(100_000))), Err(ExprError::TooDeep));
+    assert!(Expr::parse(&format!("{}1{}", "[".repeat(40), "]".repeat(40))).is_ok());
+    assert!(LogMessage::parse("a is {a").is_err());
+}
+
+#[test]
+fn condition_stops_when_true() {
+    let mut gb = create_gameboy("cond_true.gb", &PROGRAM);
+    let index = gb.debugger_mut().add_breakpoint(0x0153, None);
+    gb.debugger_mut().breakpoint_mut(index).unwrap().condition = Some(Condition::parse("a == 5 && [$C000] == 4").unwrap());
+    run_until_paused(&mut gb);
+
+    assert_eq!(gb.debugger().break_reason(), Some(BreakReason::Breakpoint(0)));
+    assert_eq!(gb.registers().a, 5);
+    assert_eq!(gb.debugger().breakpoints()[0].hit_count, 1);
+
+    assert_eq!(evaluate(&gb, "1 + 2 * 3"), 7);
+    assert_eq!(evaluate(&gb, "(1 + 2) * 3"), 9);
+    assert_eq!(evaluate(&gb, "1 + 1 == 2 && !0"), 1);
+    assert_eq!(evaluate(&gb, "-a + 0x10 >> 1"), 5);
+    assert_eq!(evaluate(&gb, "w[0xC000] & 0xFF"), 4);
+    assert_eq!(evaluate(&gb, "pc == 0x153 && bank == 1"), 1);
+    assert_eq!(evaluate(&gb, "zf || nf"), 0);
+    assert_eq!(evaluate(&gb, "7 / 0"), 0);
+}
+
+#[test]
+fn break_after_hit_count() {
+    let mut gb = create_gameboy("cond_hits.gb", &PROGRAM);
+    let index = gb.debugger_mut().add_breakpoint(0x0152, None);
+    gb.debugger_mut().breakpoint_mut(index).unwrap().break_after = Some(3);
+    run_until_paused(&mut gb);
+
+    assert_eq!(gb.registers().a, 2);
+    assert_eq!(gb.debugger().breakpoints()[0].hit_count, 3);
+}
+
+#[test]
+fn log_points_dont_stop() {
+    let mut gb = create_gameboy("cond_log.gb", &PROGRAM);
+
+    let log_point = gb.debugger_mut().add_breakpoint(0x0156, None);
+    gb.debugger_mut().breakpoint_mut(log_point).unwrap().log_message = Some(LogMessage::parse("a={a} [c000]={[0xC000]:x}").unwrap());
+
+    let stop = gb.debugger_mut().add_breakpoint(0x0153, None);
+    gb.debugger_mut().breakpoint_mut(stop).unwrap().condition = Some(Condition::parse("a == 3").unwrap());
+    run_until_paused(&mut gb);
+
+    assert_eq!(gb.debugger().break_reason(), Some(BreakReason::Breakpoint(1)));
+    let log: Vec<&str> = gb.debugger().log().iter().map(|line| line.as_str()).collect();
+    assert_eq!(log, ["a=1 [c000]=01", "a=2 [c000]=02"]);
+}
+
+#[test]
+fn ppu_values() {
+    let mut gb = create_gameboy("cond_ppu.gb", &PROGRAM);
+    let index = gb.debugger_mut().add_breakpoint(0x0152, None);
+    gb.debugger_mut().breakpoint_mut(index).unwrap().condition = Some(Condition::parse("ly >= 144").unwrap());
+    run_until_paused(&mut gb);
+
+    assert!(evaluate(&gb, "ly") >= 144);
+    assert_eq!(evaluate(&gb, "mode"), 1);
+}
+
+#[test]
+fn memory_reads_see_past_the_ppu() {
+    let mut gb = create_gameboy("cond_ppu_locked.gb", &PROGRAM);
+    let index = gb.debugger_mut().add_breakpoint(0x0152, None);
+    gb.debugger_mut().breakpoint_mut(index).unwrap().condition = Some(Condition::parse("mode == 3").unwrap());
+    run_until_paused(&mut gb);
+
+    // vram is locked while the ppu draws, but not to the debugger
+    gb.debug_write(0x8000, MemoryBanks::default(), 0x5A);
+    assert_eq!(gb.peek_byte(0x8000), 0xFF);
+    assert_eq!(evaluate(&gb, "[$8000]"), 0x5A);
+
+    // and it sees the real ly when gameboy doctor's is faked
+    gb.set_doctor_ly(true);
+    let ly = gb.ppu_state().ly as i64;
+    assert_ne!(ly, 0x90);
+    assert_eq!(evaluate(&gb, "ly"), ly);
+    assert_eq!(evaluate(&gb, "[$FF44]"), ly);
+}
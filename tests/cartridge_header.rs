@@ -0,0 +1,52 @@
+use gameboy_rs::gameboy::cartridge::header::CartridgeHeader;
+use common::create_test_rom;
+
+mod common;
+
+#[test]
+fn parses_test_rom_header() {
+    let rom = create_test_rom(0x13, 0x03, &[]);
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(header.title, "TEST");
+    assert_eq!(header.manufacturer_code, None);
+    assert_eq!(header.mapper_name(), "MBC3+RAM+BATTERY");
+    assert!(header.has_battery());
+    assert_eq!(header.num_rom_banks(), Some(2));
+    assert_eq!(header.num_ram_banks(), Some(4));
+    assert!(header.header_checksum_valid);
+    assert!(header.global_checksum_valid);
+    assert!(header.logo_valid);
+}
+
+#[test]
+fn detects_bad_checksums_and_logo() {
+    let mut rom = create_test_rom(0x00, 0x00, &[]);
+    rom[0x104] ^= 0xFF;
+    rom[0x14C] = 0x01; // version is covered by the header checksum
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+    assert!(!header.header_checksum_valid);
+    assert!(!header.global_checksum_valid);
+    assert!(!header.logo_valid);
+}
+
+#[test]
+fn cgb_title_with_manufacturer_code() {
+    let mut rom = create_test_rom(0x1B, 0x02, &[]);
+    rom[0x134..0x143].copy_from_slice(b"POKEMON YELAPSE");
+    rom[0x143] = 0x80;
+    rom[0x14B] = 0x33;
+    rom[0x144..0x146].copy_from_slice(b"01");
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+    assert_eq!(header.title, "POKEMON YEL");
+    assert_eq!(header.manufacturer_code.as_deref(), Some("APSE"));
+    assert_eq!(header.licensee(), "01");
+    assert!(!header.is_cgb_only());
+}
+
+#[test]
+fn rom_too_small_for_header() {
+    assert!(CartridgeHeader::parse(&[0u8; 0x100]).is_none());
+}
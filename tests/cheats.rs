@@ -0,0 +1,127 @@
+use gameboy_rs::gameboy::{GameBoy, cartridge::{LoadOptions, save_storage::MemorySaveStorage}, cheats::{CheatCode, CheatError, Cheats}, debugger::watchpoint::{WatchKind, Watchpoint}};
+use common::{CYCLES_PER_SCREEN_DRAW, create_test_rom, run_frames, write_test_rom};
+
+mod common;
+
+// enable cart ram, then keep copying the rom byte at 0x0200 into 0xA000
+const COPY_ROM_BYTE_PROGRAM: [u8; 13] = [
+    0x3E, 0x0A,       // ld a, 0x0A
+    0xEA, 0x00, 0x00, // ld (0x0000), a
+    0xFA, 0x00, 0x02, // ld a, (0x0200)
+    0xEA, 0x00, 0xA0, // ld (0xA000), a
+    0x18, 0xF8        // jr -8
+];
+
+fn run_with_cheats(rom_name: &str, codes: &[&str]) -> Vec<u8> {
+    let rom = create_test_rom(0x1B, 0x02, &COPY_ROM_BYTE_PROGRAM);
+    let rom_path = write_test_rom(rom_name, &rom);
+
+    let storage = MemorySaveStorage::new();
+    {
+        let options = LoadOptions {
+            save_storage: Some(Box::new(storage.clone())),
+            ..LoadOptions::default()
+        };
+
+        let mut gb = GameBoy::new_with_options(rom_path.to_str().unwrap(), None, options).unwrap();
+        for code in codes {
+            gb.cheats_mut().add(code, code).unwrap();
+        }
+
+        run_frames(&mut gb, 60 * 2);
+    }
+
+    storage.get(&format!("{}.sav", rom_name.trim_end_matches(".gb"))).unwrap()
+}
+
+#[test]
+fn parses_game_genie_codes() {
+    assert_eq!(
+        CheatCode::parse("992-00F"),
+        Ok(CheatCode::GameGenie { addr: 0x0200, value: 0x99, compare: None })
+    );
+
+    assert_eq!(
+        CheatCode::parse("00A-17B-C49"),
+        Ok(CheatCode::GameGenie { addr: 0x4A17, value: 0x00, compare: Some(0xC8) })
+    );
+
+    // F ^ 0xF puts this in vram, which the game genie can't touch
+    assert_eq!(CheatCode::parse("992-007"), Err(CheatError::InvalidAddress(0x8200)));
+    assert!(CheatCode::parse("99Z-00F").is_err());
+}
+
+#[test]
+fn parses_game_shark_codes() {
+    assert_eq!(
+        CheatCode::parse("01FF10C1"),
+        Ok(CheatCode::GameShark { ram_bank: None, addr: 0xC110, value: 0xFF })
+    );
+
+    assert_eq!(
+        CheatCode::parse("834201A0"),
+        Ok(CheatCode::GameShark { ram_bank: Some(3), addr: 0xA001, value: 0x42 })
+    );
+
+    // banked codes only make sense for cart ram
+    assert_eq!(CheatCode::parse("834210C1"), Err(CheatError::InvalidAddress(0xC110)));
+    assert_eq!(CheatCode::parse("904210C1"), Err(CheatError::UnsupportedType(0x90)));
+}
+
+#[test]
+fn game_genie_patches_rom_reads() {
+    assert_eq!(run_with_cheats("genie.gb", &[])[0], 0x00);
+    assert_eq!(run_with_cheats("genie_patched.gb", &["992-00F"])[0], 0x99);
+
+    // compare byte matches the rom (0x00)
+    assert_eq!(run_with_cheats("genie_compare.gb", &["992-00F-E6A"])[0], 0x99);
+
+    // compare byte doesn't match, so the read is left alone
+    assert_eq!(run_with_cheats("genie_compare_miss.gb", &["992-00F-E6E"])[0], 0x00);
+}
+
+#[test]
+fn game_shark_writes_ram_every_frame() {
+    let save = run_with_cheats("shark.gb", &["804201A0", "01FF02A0"]);
+    assert_eq!(save[1], 0x42);
+    assert_eq!(save[2], 0xFF);
+}
+
+#[test]
+fn game_shark_writes_arent_seen_by_the_debugger() {
+    let rom = create_test_rom(0x1B, 0x02, &COPY_ROM_BYTE_PROGRAM);
+    let rom_path = write_test_rom("shark_quiet.gb", &rom);
+
+    let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    gb.cheats_mut().add("wram", "01FF10C1").unwrap();
+    gb.watchpoints_mut().add(Watchpoint::new(0xC110, 0xC110, WatchKind::Write));
+
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 2 {
+        assert!(!gb.tick(), "stopped for {:?}", gb.debugger().break_reason());
+    }
+
+    assert_eq!(gb.peek_byte(0xC110), 0xFF);
+}
+
+#[test]
+fn cheats_can_be_toggled_and_saved() {
+    let mut cheats = Cheats::new();
+    cheats.add("Infinite lives", "992-00F").unwrap();
+    cheats.add("Two codes", "01FF10C1 + 804201a0").unwrap();
+    assert!(cheats.add("Broken", "1234").is_err());
+
+    assert_eq!(cheats.patch_rom_read(0x0200, 0x00), 0x99);
+    cheats.set_enabled(0, false);
+    assert_eq!(cheats.patch_rom_read(0x0200, 0x00), 0x00);
+
+    let path = write_test_rom("cheats.cht", &[]);
+    cheats.save(&path).unwrap();
+
+    let loaded = Cheats::load(&path).unwrap();
+    assert_eq!(loaded.list().len(), 2);
+    assert_eq!(loaded.list()[0].name, "Infinite lives");
+    assert!(!loaded.list()[0].enabled);
+    assert_eq!(loaded.list()[1].code, "01FF10C1+804201A0");
+    assert_eq!(loaded.list()[1].codes().len(), 2);
+    assert_eq!(loaded.game_shark_writes(), vec![(None, 0xC110, 0xFF), (Some(0), 0xA001, 0x42)]);
+}
@@ -0,0 +1,115 @@
+use gameboy_rs::gameboy::{GameBoy, cartridge::header::CartridgeHeader, ppu::{PixelSource, colourise::{self, Colourisation}, palette::DmgPalette}};
+use common::{create_gameboy, create_test_rom, run_frames};
+
+mod common;
+
+// the boot rom's logo, plus its (R) tile as an OBP0 and an OBP1 sprite
+const PROGRAM: [u8; 38] = [
+    0xAF,             // 0x150: xor a
+    0xE0, 0x40,       // 0x151: ldh [$40], a (lcd off)
+    0x21, 0x00, 0xFE, // 0x153: ld hl, $FE00
+    0x3E, 0x10,       // 0x156: ld a, $10
+    0x22,             // 0x158: ld [hl+], a (y)
+    0x22,             // 0x159: ld [hl+], a (x)
+    0x3E, 0x19,       // 0x15A: ld a, $19
+    0x22,             // 0x15C: ld [hl+], a (tile)
+    0x23,             // 0x15D: inc hl (flags 0, OBP0)
+    0x3E, 0x10,       // 0x15E: ld a, $10
+    0x22,             // 0x160: ld [hl+], a (y)
+    0x3E, 0x20,       // 0x161: ld a, $20
+    0x22,             // 0x163: ld [hl+], a (x)
+    0x3E, 0x19,       // 0x164: ld a, $19
+    0x22,             // 0x166: ld [hl+], a (tile)
+    0x3E, 0x10,       // 0x167: ld a, $10
+    0x77,             // 0x169: ld [hl], a (flags, OBP1)
+    0x3E, 0xE4,       // 0x16A: ld a, $E4
+    0xE0, 0x48,       // 0x16C: ldh [$48], a (obp0)
+    0xE0, 0x49,       // 0x16E: ldh [$49], a (obp1)
+    0x3E, 0x93,       // 0x170: ld a, $93 (lcd, sprites and bg on)
+    0xE0, 0x40,       // 0x172: ldh [$40], a
+    0x18, 0xFE        // 0x174: jr -2
+];
+
+fn header_with_title(title: &str, old_licensee: u8, new_licensee: &[u8; 2]) -> CartridgeHeader {
+    let mut rom = create_test_rom(0x00, 0x00, &[]);
+    rom[0x134..0x144].copy_from_slice(&[0; 16]);
+    rom[0x134..0x134 + title.len()].copy_from_slice(title.as_bytes());
+    rom[0x144..0x146].copy_from_slice(new_licensee);
+    rom[0x14B] = old_licensee;
+
+    CartridgeHeader::parse(&rom).unwrap()
+}
+
+fn raw_title(title: &str) -> [u8; 16] {
+    let mut raw = [0u8; 16];
+    raw[..title.len()].copy_from_slice(title.as_bytes());
+    raw
+}
+
+#[test]
+fn title_lookup() {
+    assert_eq!(colourise::title_checksum(&raw_title("POKEMON RED")), 0x14);
+    assert_eq!(colourise::lookup(&raw_title("TETRIS")), Some(colourise::combination(3)));
+
+    // same checksum, told apart by the 4th letter, in the first row or a later one
+    assert_eq!(colourise::title_checksum(&raw_title("MOGURANYA")), colourise::title_checksum(&raw_title("MOGBRANlA")));
+    assert_eq!(colourise::lookup(&raw_title("MOGBRANlA")), Some(colourise::combination(36)));
+    assert_eq!(colourise::lookup(&raw_title("MOGURANYA")), Some(colourise::combination(17)));
+    assert_eq!(colourise::lookup(&raw_title("BATMAN")), None);
+    assert_eq!(colourise::lookup(&raw_title("UNKNOWN")), None);
+
+    let red = header_with_title("POKEMON RED", 0x33, b"01");
+    assert!(colourise::is_nintendo(&red));
+    assert_eq!(Colourisation::for_header(&red), colourise::combination(13));
+    assert_eq!(Colourisation::for_header(&header_with_title("POKEMON BLUE", 0x01, b"00")), colourise::combination(11));
+
+    // only nintendo's games get looked up
+    let other = header_with_title("POKEMON RED", 0x33, b"08");
+    assert_eq!(Colourisation::for_header(&other), Colourisation::DEFAULT);
+    assert_eq!(Colourisation::for_header(&header_with_title("UNKNOWN", 0x01, b"00")), Colourisation::DEFAULT);
+}
+
+#[test]
+fn manual_palettes() {
+    let names: Vec<&str> = Colourisation::MANUAL.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names.len(), 12);
+    assert_eq!(Colourisation::MANUAL[10], ("Right + A", Colourisation::DEFAULT));
+
+    // the window shares the background's colours
+    let colours = Colourisation::MANUAL[1].1;
+    assert_eq!(colours.palette_for(PixelSource::Window), &colours.bg);
+    assert_eq!(colours.palette_for(PixelSource::Obj1), &colours.obj1);
+    assert_ne!(colours.obj0, colours.obj1);
+
+    // RGB555 from the boot rom, up + A has the red background
+    assert_eq!(colours.bg.shades, [[0xFF, 0xFF, 0xFF, 0xFF], [0xFF, 0x84, 0x84, 0xFF], [0x94, 0x3A, 0x3A, 0xFF], [0x00, 0x00, 0x00, 0xFF]]);
+}
+
+#[test]
+fn layers_get_their_own_colours() {
+    let mut gb = create_gameboy("colourise.gb", &PROGRAM);
+    assert_eq!(gb.colourisation(), None);
+    assert_eq!(gb.title_colourisation(), Colourisation::DEFAULT);
+
+    let colours = Colourisation::MANUAL[1].1;
+    gb.set_colourisation(Some(colours));
+    run_frames(&mut gb, 60 * 3);
+
+    let sources = gb.get_pixel_sources().to_vec();
+    for source in [PixelSource::Background, PixelSource::Obj0, PixelSource::Obj1].iter() {
+        assert!(sources.contains(source), "nothing from {:?}", source);
+    }
+
+    let check = |gb: &GameBoy, colours: Option<Colourisation>| {
+        let rgba = gb.get_frame_buffer();
+        for (i, grey) in gb.get_shade_buffer().iter().enumerate() {
+            let palette = colours.map(|colours| *colours.palette_for(sources[i])).unwrap_or(DmgPalette::GREY);
+            assert_eq!(&rgba[i * 4..i * 4 + 4], &palette.colour_for_grey(*grey)[..]);
+        }
+    };
+    check(&gb, Some(colours));
+
+    // back to the plain palette straight away
+    gb.set_colourisation(None);
+    check(&gb, None);
+}
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use gameboy_rs::gameboy::{GameBoy, cartridge::header::NINTENDO_LOGO};
 use image::{ImageBuffer, RgbImage, RgbaImage, io::Reader};
 
 pub const WIDTH: u32 = 160;
@@ -111,4 +112,91 @@ pub fn get_base_dir() -> PathBuf {
             PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         }
     }
-}
\ No newline at end of file
+}
+
+// Builds a 32KB rom with a valid header that runs `program` from 0x150
+#[allow(dead_code)]
+pub fn create_test_rom(cartridge_type: u8, ram_size_code: u8, program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0u8; 0x8000];
+
+    // nop; jp 0x150
+    rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+    rom[0x134..0x138].copy_from_slice(b"TEST");
+    rom[0x147] = cartridge_type;
+    rom[0x148] = 0x00;
+    rom[0x149] = ram_size_code;
+
+    let mut checksum: u8 = 0;
+    for byte in &rom[0x134..0x14D] {
+        checksum = checksum.wrapping_sub(*byte).wrapping_sub(1);
+    }
+    rom[0x14D] = checksum;
+
+    rom[0x150..0x150 + program.len()].copy_from_slice(program);
+
+    let mut global_checksum: u16 = 0;
+    for byte in &rom {
+        global_checksum = global_checksum.wrapping_add(*byte as u16);
+    }
+    rom[0x14E] = (global_checksum >> 8) as u8;
+    rom[0x14F] = global_checksum as u8;
+
+    rom
+}
+
+// enable cart ram, write 0x42 to 0xA000 and then spin
+#[allow(dead_code)]
+pub const WRITE_SRAM_PROGRAM: [u8; 12] = [
+    0x3E, 0x0A,       // ld a, 0x0A
+    0xEA, 0x00, 0x00, // ld (0x0000), a
+    0x3E, 0x42,       // ld a, 0x42
+    0xEA, 0x00, 0xA0, // ld (0xA000), a
+    0x18, 0xFE        // jr -2
+];
+
+#[allow(dead_code)]
+pub fn write_test_rom(name: &str, rom: &[u8]) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push("frosty_tests");
+    std::fs::create_dir_all(&path).unwrap();
+    path.push(name);
+    std::fs::write(&path, rom).unwrap();
+    path
+}
+
+// a rom only cartridge running `program`, straight from power on
+#[allow(dead_code)]
+pub fn create_gameboy(name: &str, program: &[u8]) -> GameBoy {
+    let rom = create_test_rom(0x00, 0x00, program);
+    let rom_path = write_test_rom(name, &rom);
+    GameBoy::new(rom_path.to_str().unwrap(), None)
+}
+
+#[allow(dead_code)]
+pub fn run_frames(gb: &mut GameBoy, frames: u64) {
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * frames {
+        gb.tick();
+    }
+}
+
+// create_gameboy, then past the boot rom to the start of `program`
+#[allow(dead_code)]
+pub fn boot_rom(name: &str, program: &[u8]) -> GameBoy {
+    let mut gb = create_gameboy(name, program);
+    run_frames(&mut gb, 60 * 3);
+    gb
+}
+
+// ticks until the debugger stops us, gives up after a few seconds
+#[allow(dead_code)]
+pub fn run_until_paused(gb: &mut GameBoy) {
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 10 {
+        if gb.tick() {
+            assert!(gb.debugger().is_paused());
+            return;
+        }
+    }
+
+    panic!("The debugger never paused");
+}
@@ -0,0 +1,73 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use gameboy_rs::gameboy::{GameBoy, crash};
+use common::{create_gameboy, create_test_rom, run_frames, write_test_rom};
+
+mod common;
+
+const PROGRAM: [u8; 7] = [
+    0x3E, 0x05,       // 0x150: ld a, 5
+    0x06, 0x07,       // 0x152: ld b, 7
+    0x3C,             // 0x154: inc a
+    0x18, 0xFD        // 0x155: jr -3
+];
+
+// mbc1 banking mode 1 isn't implemented and panics
+const CRASHING_PROGRAM: [u8; 8] = [
+    0x3E, 0x01,       // 0x150: ld a, 1
+    0xEA, 0x00, 0x60, // 0x152: ld [$6000], a
+    0xEA, 0x00, 0x40  // 0x155: ld [$4000], a
+];
+
+#[test]
+fn history_keeps_the_last_instructions() {
+    let mut gb = create_gameboy("history.gb", &PROGRAM);
+    gb.debugger_mut().history_mut().set_capacity(8);
+
+    run_frames(&mut gb, 60 * 2);
+
+    let history: Vec<_> = gb.debugger().history().iter().copied().collect();
+    assert_eq!(history.len(), 8);
+
+    // stuck in the inc a / jr loop by now
+    for pair in history.windows(2) {
+        assert!(pair[0].cycle < pair[1].cycle);
+        assert!(pair[0].pc() == 0x0154 || pair[0].pc() == 0x0155);
+
+        if pair[0].pc() == 0x0154 {
+            assert_eq!(pair[0].opcode, 0x3C);
+            assert_eq!(pair[1].registers.a, pair[0].registers.a.wrapping_add(1));
+        }
+    }
+
+    assert_eq!(history[0].bank, 0);
+    assert_eq!(history[0].registers.b, 0x07);
+}
+
+#[test]
+fn crash_report_after_a_panic() {
+    let rom = create_test_rom(0x01, 0x00, &CRASHING_PROGRAM);
+    let rom_path = write_test_rom("crash_report.gb", &rom);
+
+    let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_frames(&mut gb, 60 * 5);
+    }));
+
+    let reason = crash::panic_message(&*result.expect_err("expected the mbc1 to panic"));
+    assert_eq!(reason, "mode 1 unimpl");
+
+    let report_path = rom_path.with_extension("crash.txt");
+    gb.write_crash_report(&report_path, &reason).unwrap();
+    let report = std::fs::read_to_string(report_path).unwrap();
+
+    assert!(report.contains("Reason: mode 1 unimpl"), "{}", report);
+    assert!(report.contains("ROM: TEST"), "{}", report);
+    assert!(report.contains("LCDC  (FF40) = "), "{}", report);
+    assert!(report.contains("  FF40:"), "{}", report);
+
+    let last = gb.debugger().history().iter().last().unwrap();
+    assert_eq!(last.pc(), 0x0155);
+    assert!(report.trim_end().lines().last().unwrap().contains("00:0155  ld [$4000], a"), "{}", report);
+    assert!(report.contains("00:0152  ld [$6000], a"), "{}", report);
+}
@@ -0,0 +1,99 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::BreakReason};
+use common::{create_gameboy, run_until_paused};
+
+mod common;
+
+const PROGRAM: [u8; 21] = [
+    0x3E, 0x05,       // 0x150: ld a, 5
+    0xCD, 0x60, 0x01, // 0x152: call 0x0160
+    0x3C,             // 0x155: inc a
+    0x18, 0xFE,       // 0x156: jr -2
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x06, 0x07,       // 0x160: ld b, 7
+    0x0E, 0x09,       // 0x162: ld c, 9
+    0xC9              // 0x164: ret
+];
+
+fn break_at_program_start(name: &str) -> GameBoy {
+    let mut gb = create_gameboy(name, &PROGRAM);
+    gb.debugger_mut().add_breakpoint(0x0150, None);
+    run_until_paused(&mut gb);
+    gb
+}
+
+#[test]
+fn breakpoint_stops_before_instruction() {
+    let gb = break_at_program_start("dbg_breakpoint.gb");
+
+    assert_eq!(gb.debugger().break_reason(), Some(BreakReason::Breakpoint(0)));
+    assert_eq!(gb.registers().pc, 0x0150);
+    let instr = gb.disassemble_instruction(0x0152);
+    assert_eq!(instr.to_string(), "call $0160");
+    assert_eq!(instr.length, 3);
+}
+
+#[test]
+fn breakpoint_bank_must_match() {
+    let mut gb = create_gameboy("dbg_bank.gb", &PROGRAM);
+    gb.debugger_mut().add_breakpoint(0x0150, Some(1));
+    gb.debugger_mut().add_breakpoint(0x0152, Some(0));
+    run_until_paused(&mut gb);
+
+    assert_eq!(gb.debugger().break_reason(), Some(BreakReason::Breakpoint(1)));
+    assert_eq!(gb.registers().pc, 0x0152);
+}
+
+#[test]
+fn step_and_step_over() {
+    let mut gb = break_at_program_start("dbg_step_over.gb");
+
+    gb.step_instruction();
+    run_until_paused(&mut gb);
+    assert_eq!(gb.registers().pc, 0x0152);
+    assert_eq!(gb.registers().a, 5);
+
+    gb.step_over();
+    run_until_paused(&mut gb);
+    let registers = gb.registers();
+    assert_eq!(registers.pc, 0x0155);
+    assert_eq!(registers.b, 7);
+    assert_eq!(registers.c, 9);
+}
+
+#[test]
+fn step_into_and_out() {
+    let mut gb = break_at_program_start("dbg_step_out.gb");
+    gb.run_to(0x0152);
+    run_until_paused(&mut gb);
+    assert_eq!(gb.debugger().break_reason(), Some(BreakReason::RunToCursor));
+
+    let sp = gb.registers().sp;
+    gb.step_instruction();
+    run_until_paused(&mut gb);
+    assert_eq!(gb.registers().pc, 0x0160);
+    assert_eq!(gb.registers().sp, sp.wrapping_sub(2));
+
+    gb.step_out();
+    run_until_paused(&mut gb);
+    assert_eq!(gb.registers().pc, 0x0155);
+    assert_eq!(gb.registers().sp, sp);
+}
+
+#[test]
+fn registers_can_be_edited() {
+    let mut gb = break_at_program_start("dbg_registers.gb");
+
+    // skip straight to the subroutine and step one instruction
+    let mut registers = gb.registers();
+    registers.pc = 0x0160;
+    registers.d = 0x42;
+    gb.set_registers(registers);
+
+    gb.step_instruction();
+    run_until_paused(&mut gb);
+
+    let registers = gb.registers();
+    assert_eq!(registers.pc, 0x0162);
+    assert_eq!(registers.b, 7);
+    assert_eq!(registers.d, 0x42);
+}
@@ -0,0 +1,79 @@
+use gameboy_rs::gameboy::cpu::decode::{decode, disassemble_range};
+
+fn text(bytes: &[u8]) -> String {
+    decode(bytes, 0x0150).to_string()
+}
+
+#[test]
+fn rgbds_syntax() {
+    assert_eq!(text(&[0x00]), "nop");
+    assert_eq!(text(&[0x3E, 0x3C]), "ld a, $3C");
+    assert_eq!(text(&[0x21, 0x00, 0xC0]), "ld hl, $C000");
+    assert_eq!(text(&[0xEA, 0x00, 0xC0]), "ld [$C000], a");
+    assert_eq!(text(&[0x08, 0xFE, 0xFF]), "ld [$FFFE], sp");
+    assert_eq!(text(&[0x22]), "ld [hl+], a");
+    assert_eq!(text(&[0x3A]), "ld a, [hl-]");
+    assert_eq!(text(&[0x34]), "inc [hl]");
+    assert_eq!(text(&[0x7E]), "ld a, [hl]");
+    assert_eq!(text(&[0x76]), "halt");
+    assert_eq!(text(&[0x96]), "sub [hl]");
+    assert_eq!(text(&[0x8F]), "adc a, a");
+    assert_eq!(text(&[0xFE, 0x90]), "cp $90");
+    assert_eq!(text(&[0xE0, 0x40]), "ldh [$FF40], a");
+    assert_eq!(text(&[0xF2]), "ldh a, [c]");
+    assert_eq!(text(&[0xE8, 0xFE]), "add sp, -2");
+    assert_eq!(text(&[0xF8, 0x02]), "ld hl, sp+$02");
+    assert_eq!(text(&[0xF8, 0xFF]), "ld hl, sp-$01");
+    assert_eq!(text(&[0xC5]), "push bc");
+    assert_eq!(text(&[0xF1]), "pop af");
+    assert_eq!(text(&[0xE9]), "jp hl");
+    assert_eq!(text(&[0xFF]), "rst $38");
+    assert_eq!(text(&[0xD3]), "db $D3");
+}
+
+#[test]
+fn cb_instructions() {
+    assert_eq!(text(&[0xCB, 0x37]), "swap a");
+    assert_eq!(text(&[0xCB, 0x7E]), "bit 7, [hl]");
+    assert_eq!(text(&[0xCB, 0x87]), "res 0, a");
+    assert_eq!(text(&[0xCB, 0xFE]), "set 7, [hl]");
+
+    assert_eq!(decode(&[0xCB, 0x7E], 0).cycles, 12);
+    assert_eq!(decode(&[0xCB, 0xFE], 0).cycles, 16);
+    assert_eq!(decode(&[0xCB, 0x11], 0).length, 2);
+}
+
+#[test]
+fn branches() {
+    let jr = decode(&[0x20, 0xFE], 0x0150);
+    assert_eq!(jr.to_string(), "jr nz, $0150");
+    assert_eq!(jr.length, 2);
+    assert_eq!((jr.cycles, jr.branch_cycles), (8, Some(12)));
+    assert_eq!(jr.branch_targets, vec![0x0150]);
+
+    let call = decode(&[0xCD, 0x00, 0x40], 0x0150);
+    assert_eq!(call.to_string(), "call $4000");
+    assert_eq!((call.cycles, call.branch_cycles), (24, None));
+    assert_eq!(call.branch_targets, vec![0x4000]);
+
+    let ret = decode(&[0xD8], 0x0150);
+    assert_eq!(ret.to_string(), "ret c");
+    assert_eq!((ret.cycles, ret.branch_cycles), (8, Some(20)));
+    assert!(ret.branch_targets.is_empty());
+
+    assert_eq!(decode(&[0xEF], 0).branch_targets, vec![0x28]);
+}
+
+#[test]
+fn range() {
+    let rom: Vec<u8> = vec![0x00, 0xC3, 0x50, 0x01, 0xCB, 0x37, 0x3E];
+    let instructions = disassemble_range(&rom, 0x0000, 0x0006);
+
+    let lines: Vec<(u16, String)> = instructions.iter().map(|instr| (instr.addr, instr.to_string())).collect();
+    assert_eq!(lines, vec![
+        (0x0000, String::from("nop")),
+        (0x0001, String::from("jp $0150")),
+        (0x0004, String::from("swap a")),
+        (0x0006, String::from("ld a, $00"))
+    ]);
+}
@@ -16,7 +16,7 @@ fn dmg_acid2() {
         s.tick();
     }
 
-    let fb = s.get_frame_buffer();
+    let fb = s.get_shade_buffer();
 
     pb.pop();
     pb.pop();
@@ -0,0 +1,110 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gameboy_rs::gameboy::{GameBoy, debugger::memory::MemoryBanks, events::EmulatorEvent};
+use common::{CYCLES_PER_SCREEN_DRAW, create_gameboy, create_test_rom, run_frames, write_test_rom};
+
+mod common;
+
+const ILLEGAL_OPCODES: [u8; 11] = [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
+#[test]
+fn illegal_opcodes_lock_the_cpu() {
+    for opcode in ILLEGAL_OPCODES.iter() {
+        let program = [
+            0x3E, 0x01,       // 0x150: ld a, 1
+            0xE0, 0xFF,       // 0x152: ldh [$FF], a (vblank interrupt on)
+            0xFB,             // 0x154: ei
+            0x3E, 0x05,       // 0x155: ld a, 5
+            *opcode,          // 0x157: illegal
+            0x3E, 0x06        // 0x158: ld a, 6
+        ];
+
+        let mut gb = create_gameboy(&format!("illegal_{:02x}.gb", opcode), &program);
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let callback_events = events.clone();
+        gb.set_event_callback(move |event| callback_events.borrow_mut().push(*event));
+
+        for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 5 {
+            gb.tick();
+            if gb.is_cpu_locked() { break }
+        }
+
+        assert!(gb.is_cpu_locked(), "{:02X} didn't lock the cpu", opcode);
+        assert_eq!(*events.borrow(), vec![EmulatorEvent::CpuLocked { pc: 0x0157, opcode: *opcode }]);
+
+        // nothing runs after it, not even the vblank interrupt, but the ppu keeps going
+        let registers = gb.registers();
+        let frames = gb.frames();
+        run_frames(&mut gb, 3);
+
+        assert_eq!(gb.registers(), registers);
+        assert_eq!(gb.registers().a, 0x05);
+        assert!(gb.frames() > frames);
+        assert_eq!(events.borrow().len(), 1);
+    }
+}
+
+#[test]
+fn locked_event_message() {
+    let event = EmulatorEvent::CpuLocked { pc: 0x0157, opcode: 0xD3 };
+    assert_eq!(event.to_string(), "CPU locked up at $0157 (illegal opcode $D3)");
+}
+
+#[test]
+fn missing_cartridge_ram_is_open_bus() {
+    let program = [
+        0x3E, 0x0A,       // 0x150: ld a, $0A
+        0xEA, 0x00, 0x00, // 0x152: ld [$0000], a (enable ram, if there was any)
+        0xFA, 0x00, 0xA0, // 0x155: ld a, [$A000]
+        0x47,             // 0x158: ld b, a
+        0x18, 0xFE        // 0x159: jr -2
+    ];
+
+    // rom only, then mbc1 without any ram
+    for cartridge_type in [0x00, 0x01].iter() {
+        let rom = create_test_rom(*cartridge_type, 0x00, &program);
+        let rom_path = write_test_rom(&format!("open_bus_{:02x}.gb", cartridge_type), &rom);
+
+        let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+        run_frames(&mut gb, 60 * 2);
+
+        assert_eq!(gb.registers().b, 0xFF, "cartridge type {:02X}", cartridge_type);
+    }
+}
+
+#[test]
+fn unimplemented_io_registers_are_open_bus() {
+    let program = [
+        0x3E, 0x12,       // 0x150: ld a, $12
+        0xE0, 0x4C,       // 0x152: ldh [$4C], a
+        0xF0, 0x4C,       // 0x154: ldh a, [$4C]
+        0x47,             // 0x156: ld b, a
+        0x18, 0xFE        // 0x157: jr -2
+    ];
+
+    let mut gb = create_gameboy("open_bus_io.gb", &program);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let callback_events = events.clone();
+    gb.set_event_callback(move |event| callback_events.borrow_mut().push(*event));
+    run_frames(&mut gb, 60 * 2);
+
+    assert_eq!(gb.registers().b, 0xFF);
+    assert!(events.borrow().contains(&EmulatorEvent::UnmappedWrite { addr: 0xFF4C, value: 0x12, pc: 0x0152 }));
+    assert!(events.borrow().contains(&EmulatorEvent::UnmappedRead { addr: 0xFF4C, pc: 0x0154 }));
+}
+
+#[test]
+fn looking_at_unmapped_memory_isnt_reported() {
+    let mut gb = create_gameboy("open_bus_peek.gb", &[0x18, 0xFE]); // jr -2
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let callback_events = events.clone();
+    gb.set_event_callback(move |event| callback_events.borrow_mut().push(*event));
+
+    // the debugger, memory viewer, traces and so on all read like this
+    assert_eq!(gb.peek_byte(0xFF4C), 0xFF);
+    assert_eq!(gb.debug_read(0xFF4C, MemoryBanks::default()), 0xFF);
+    gb.debug_write(0xFF4C, MemoryBanks::default(), 0x12);
+    run_frames(&mut gb, 1);
+
+    assert!(events.borrow().is_empty(), "{:?}", events.borrow());
+}
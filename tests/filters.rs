@@ -0,0 +1,100 @@
+use gameboy_rs::filters::{Filter, FrameBlender};
+
+const WHITE: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+const BLACK: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+
+fn image(pixels: &[[u8; 4]]) -> Vec<u8> {
+    pixels.iter().flat_map(|pixel| pixel.to_vec()).collect()
+}
+
+fn pixel_at(rgba: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+    let i = (y * width + x) * 4;
+    [rgba[i], rgba[i + 1], rgba[i + 2], rgba[i + 3]]
+}
+
+#[test]
+fn filters_scale_the_screen_and_leave_flat_colour_alone() {
+    let grey = [0x60, 0x60, 0x60, 0xFF];
+    let frame = image(&vec![grey; 160 * 144]);
+
+    for filter in Filter::ALL.iter().filter(|filter| **filter != Filter::LcdGrid) {
+        let scale = filter.scale();
+        let out = filter.apply(&frame, 160, 144);
+
+        assert_eq!(out.len(), 160 * scale * 144 * scale * 4, "{}", filter.name());
+        assert!(out.chunks_exact(4).all(|pixel| pixel == grey), "{} changed a flat colour", filter.name());
+    }
+}
+
+#[test]
+fn scale2x_rounds_off_corners() {
+    // the middle pixel has black above and to the left, so its top left
+    // corner turns black and the rest stays white
+    let src = image(&[
+        BLACK, BLACK, WHITE,
+        BLACK, WHITE, WHITE,
+        WHITE, WHITE, WHITE
+    ]);
+    let out = Filter::Scale2x.apply(&src, 3, 3);
+
+    assert_eq!(pixel_at(&out, 6, 2, 2), BLACK);
+    assert_eq!(pixel_at(&out, 6, 3, 2), WHITE);
+    assert_eq!(pixel_at(&out, 6, 2, 3), WHITE);
+    assert_eq!(pixel_at(&out, 6, 3, 3), WHITE);
+}
+
+#[test]
+fn lcd_grid_darkens_the_gaps_between_dots() {
+    let out = Filter::LcdGrid.apply(&image(&[WHITE]), 1, 1);
+
+    for y in 0..3 {
+        for x in 0..3 {
+            let pixel = pixel_at(&out, 3, x, y);
+            if x == 2 || y == 2 {
+                assert!(pixel[0] < 0xFF && pixel[0] > 0, "({}, {}) should be grid", x, y);
+                assert_eq!(pixel[3], 0xFF);
+            } else {
+                assert_eq!(pixel, WHITE);
+            }
+        }
+    }
+}
+
+#[test]
+fn frame_blending_mixes_with_the_frame_before() {
+    let mut blender = FrameBlender::new();
+
+    // nothing to mix the first frame with
+    assert_eq!(blender.blend(&image(&[WHITE])), image(&[WHITE]));
+
+    // something flickering every other frame shows at half strength
+    assert_eq!(blender.blend(&image(&[BLACK])), vec![0x7F, 0x7F, 0x7F, 0xFF]);
+    assert_eq!(blender.blend(&image(&[WHITE])), vec![0x7F, 0x7F, 0x7F, 0xFF]);
+
+    // drawing the same frame again doesn't use it up as the frame before
+    assert_eq!(blender.reblend(&image(&[WHITE])), vec![0x7F, 0x7F, 0x7F, 0xFF]);
+}
+
+#[test]
+fn hq2x_blends_along_diagonal_edges() {
+    // a diagonal edge between black and white: the middle pixel's corner
+    // towards the black is blended, the one away from it is left white
+    let src = image(&[
+        BLACK, BLACK, WHITE,
+        BLACK, WHITE, WHITE,
+        WHITE, WHITE, WHITE
+    ]);
+    let out = Filter::Hq2x.apply(&src, 3, 3);
+
+    let corner = pixel_at(&out, 6, 2, 2);
+    assert!(corner[0] > 0 && corner[0] < 0xFF, "{:?} should be blended", corner);
+    assert_eq!(pixel_at(&out, 6, 3, 3), WHITE);
+
+    // the pattern is the same turned round, so the corners match
+    let turned = image(&[
+        WHITE, BLACK, BLACK,
+        WHITE, WHITE, BLACK,
+        WHITE, WHITE, WHITE
+    ]);
+    assert_eq!(pixel_at(&Filter::Hq2x.apply(&turned, 3, 3), 6, 3, 2), corner);
+}
@@ -0,0 +1,71 @@
+use std::{io::Write, process::{Command, Stdio}};
+
+use common::{create_test_rom, write_test_rom};
+
+mod common;
+
+const PROGRAM: [u8; 8] = [
+    0x3E, 0x00,       // 0x150: ld a, 0
+    0x3C,             // 0x152: inc a
+    0xEA, 0x00, 0xC0, // 0x153: ld (0xC000), a
+    0x18, 0xFA        // 0x156: jr 0x152
+];
+
+// runs a scripted session and returns everything it printed
+fn run_session(name: &str, commands: &str) -> String {
+    let rom = create_test_rom(0x00, 0x00, &PROGRAM);
+    let rom_path = write_test_rom(name, &rom);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_frosty-dbg"))
+        .arg(&rom_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(commands.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn breakpoints_and_stepping() {
+    let output = run_session("frosty_dbg_break.gb", "b 0153 if a == 3\nc\nregs\ns\n\nx/2b c000\np [0xC000] + 1\nq\n");
+
+    assert!(output.contains("Breakpoint 0 at 0153"));
+    assert!(output.contains("0153: ld [$C000], a"));
+    assert!(output.contains("AF=03"));
+    assert!(output.contains("0156: jr $0152"));
+    assert!(output.contains("0152: inc a"));
+    assert!(output.contains("C000: 03"));
+    assert!(output.contains("4 (0x4)"));
+}
+
+#[test]
+fn watchpoints_disassembly_and_screenshots() {
+    let screenshot = std::env::temp_dir().join("frosty_tests").join("frosty_dbg.ppm");
+    let commands = format!("watch w c000 =02\nc\ndisas 0150 4\nframe 2\nscreenshot {}\n", screenshot.display());
+    let output = run_session("frosty_dbg_watch.gb", &commands);
+
+    assert!(output.contains("Watchpoint 0: Write to C000 (02) by 0153"));
+    assert!(output.contains("  0152: inc a\n  0153: ld [$C000], a\n> 0156: jr $0152"));
+
+    let ppm = std::fs::read(&screenshot).unwrap();
+    assert!(ppm.starts_with(b"P6\n160 144\n255\n"));
+    assert_eq!(ppm.len(), 15 + 160 * 144 * 3);
+}
+
+#[test]
+fn labels() {
+    let rom_path = std::env::temp_dir().join("frosty_tests").join("frosty_dbg_labels.gb");
+    std::fs::create_dir_all(rom_path.parent().unwrap()).unwrap();
+    std::fs::write(rom_path.with_extension("sym"), "00:0150 Start\n00:0152 Start.loop\n").unwrap();
+
+    let output = run_session("frosty_dbg_labels.gb", "b Start.loop\nc\ndisas Start 2\n");
+
+    assert!(output.contains("Breakpoint 0 at Start.loop"));
+    assert!(output.contains("0152 in Start.loop: inc a"));
+    assert!(output.contains("Start:\n  0150: ld a, $00\nStart.loop:\n> 0152: inc a"));
+}
@@ -0,0 +1,72 @@
+use gameboy_rs::gameboy::{GameBoy, ppu::{Layers, PixelSource}};
+use common::{boot_rom, run_frames};
+
+mod common;
+
+// the boot rom's logo on the bg (left half) and the window (right half,
+// from line 16), and its (R) tile as a sprite in the top left
+const PROGRAM: [u8; 33] = [
+    0xAF,             // 0x150: xor a
+    0xE0, 0x40,       // 0x151: ldh [$40], a (lcd off)
+    0x21, 0x00, 0xFE, // 0x153: ld hl, $FE00
+    0x3E, 0x10,       // 0x156: ld a, $10
+    0x22,             // 0x158: ld [hl+], a (y)
+    0x22,             // 0x159: ld [hl+], a (x)
+    0x3E, 0x19,       // 0x15A: ld a, $19
+    0x22,             // 0x15C: ld [hl+], a (tile)
+    0xAF,             // 0x15D: xor a
+    0x22,             // 0x15E: ld [hl+], a (flags)
+    0x3E, 0xE4,       // 0x15F: ld a, $E4
+    0xE0, 0x48,       // 0x161: ldh [$48], a (obp0)
+    0x3E, 0x10,       // 0x163: ld a, $10
+    0xE0, 0x4A,       // 0x165: ldh [$4A], a (wy)
+    0x3E, 0x57,       // 0x167: ld a, $57
+    0xE0, 0x4B,       // 0x169: ldh [$4B], a (wx)
+    0x3E, 0xB3,       // 0x16B: ld a, $B3 (lcd, window, sprites and bg on)
+    0xE0, 0x40,       // 0x16D: ldh [$40], a
+    0x18, 0xFE        // 0x16F: jr -2
+];
+
+const WHITE: u8 = 255;
+
+// the shades of the pixels that came from source
+fn shades_from(gb: &GameBoy, source: PixelSource) -> Vec<u8> {
+    gb.get_shade_buffer().iter()
+        .zip(gb.get_pixel_sources().iter())
+        .filter(|(_, pixel_source)| **pixel_source == source)
+        .map(|(shade, _)| *shade)
+        .collect()
+}
+
+#[test]
+fn every_layer_drawn_by_default() {
+    let gb = boot_rom("layers_default.gb", &PROGRAM);
+    assert_eq!(gb.layers(), Layers::default());
+
+    assert!(shades_from(&gb, PixelSource::Background).iter().any(|shade| *shade != WHITE));
+    assert!(shades_from(&gb, PixelSource::Window).iter().any(|shade| *shade != WHITE));
+    assert!(!shades_from(&gb, PixelSource::Obj0).is_empty());
+    assert!(shades_from(&gb, PixelSource::Obj1).is_empty());
+}
+
+#[test]
+fn hidden_layers_dont_touch_lcdc() {
+    let mut gb = boot_rom("layers_hidden.gb", &PROGRAM);
+    let lcdc = gb.peek_byte(0xFF40);
+
+    gb.set_layers(Layers { background: false, ..Layers::default() });
+    run_frames(&mut gb, 2);
+    assert!(shades_from(&gb, PixelSource::Background).iter().all(|shade| *shade == WHITE));
+    assert!(shades_from(&gb, PixelSource::Window).iter().any(|shade| *shade != WHITE));
+
+    gb.set_layers(Layers { window: false, ..Layers::default() });
+    run_frames(&mut gb, 2);
+    assert!(shades_from(&gb, PixelSource::Window).iter().all(|shade| *shade == WHITE));
+    assert!(shades_from(&gb, PixelSource::Background).iter().any(|shade| *shade != WHITE));
+
+    gb.set_layers(Layers { sprites: false, ..Layers::default() });
+    run_frames(&mut gb, 2);
+    assert!(shades_from(&gb, PixelSource::Obj0).is_empty());
+
+    assert_eq!(gb.peek_byte(0xFF40), lcdc);
+}
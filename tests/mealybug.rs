@@ -32,7 +32,7 @@ macro_rules! mealybug_test {
                     s.tick();
                 }
 
-                let fb = s.get_frame_buffer();
+                let fb = s.get_shade_buffer();
 
                 // create file in expected
                 let bin_file_path = format!("./tests/expected/mealybug/{}.png", &path[0..path.len()-3]);
@@ -0,0 +1,112 @@
+use gameboy_rs::gameboy::{GameBoy, cartridge::{LoadOptions, save_storage::MemorySaveStorage}, debugger::memory::{self, MemoryBanks}};
+use common::{CYCLES_PER_SCREEN_DRAW, create_gameboy, create_test_rom, run_frames, write_test_rom};
+
+mod common;
+
+// turns the lcd off, puts a byte in vram and turns it back on
+const VRAM_PROGRAM: [u8; 15] = [
+    0xAF,             // 0x150: xor a
+    0xE0, 0x40,       // 0x151: ldh [$40], a
+    0x3E, 0x5A,       // 0x153: ld a, $5A
+    0xEA, 0xF0, 0x9F, // 0x155: ld [$9FF0], a
+    0x3E, 0x91,       // 0x158: ld a, $91
+    0xE0, 0x40,       // 0x15A: ldh [$40], a
+    0x00,             // 0x15C: nop
+    0x18, 0xFD        // 0x15D: jr -3
+];
+
+#[test]
+fn debug_reads_ignore_locked_vram() {
+    let mut gb = create_gameboy("memory_viewer_vram.gb", &VRAM_PROGRAM);
+
+    let mut written = false;
+    let mut locked_reads = 0;
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 3 {
+        gb.tick();
+
+        // the boot rom clears vram too, wait for ours
+        let pc = gb.registers().pc;
+        written |= (0x015A..0x0160).contains(&pc);
+
+        if written {
+            assert_eq!(gb.debug_read(0x9FF0, MemoryBanks::default()), 0x5A);
+
+            if gb.peek_byte(0x9FF0) == 0xFF {
+                locked_reads += 1;
+            }
+        }
+    }
+
+    // the cpu's view was locked out during mode 3
+    assert!(written);
+    assert!(locked_reads > 0);
+}
+
+#[test]
+fn edits_go_into_the_selected_bank() {
+    // mbc1 with 4 banks of ram
+    let rom = create_test_rom(0x03, 0x03, &VRAM_PROGRAM);
+    let rom_path = write_test_rom("memory_viewer_banks.gb", &rom);
+
+    let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    run_frames(&mut gb, 60);
+
+    assert_eq!(gb.rom_bank_count(), 2);
+    assert_eq!(gb.ram_bank_count(), 4);
+
+    let bank = |bank| MemoryBanks { rom: None, ram: Some(bank) };
+    gb.debug_write(0xA010, bank(0), 0x11);
+    gb.debug_write(0xA010, bank(2), 0x22);
+
+    assert_eq!(gb.debug_read(0xA010, bank(0)), 0x11);
+    assert_eq!(gb.debug_read(0xA010, bank(2)), 0x22);
+
+    // the ram isn't enabled, so the cpu doesn't see any of it
+    assert_eq!(gb.peek_byte(0xA010), 0xFF);
+    assert_eq!(gb.debug_read(0xA010, MemoryBanks::default()), 0x11);
+
+    // rom bank 0 at 0x4000
+    let rom_bank_0 = MemoryBanks { rom: Some(0), ram: None };
+    assert_eq!(gb.debug_read(0x4150, rom_bank_0), 0xAF);
+
+    // patching the rom changes what the cpu reads
+    gb.debug_write(0x0200, MemoryBanks::default(), 0x77);
+    assert_eq!(gb.peek_byte(0x0200), 0x77);
+    assert!(gb.last_write(0x0200).is_some());
+}
+
+#[test]
+fn search_for_byte_patterns() {
+    assert_eq!(memory::parse_byte_pattern("3E 5A"), Ok(vec![Some(0x3E), Some(0x5A)]));
+    assert_eq!(memory::parse_byte_pattern("ea??9f"), Ok(vec![Some(0xEA), None, Some(0x9F)]));
+    assert!(memory::parse_byte_pattern("3E 5").is_err());
+    assert!(memory::parse_byte_pattern("ZZ").is_err());
+    assert!(memory::parse_byte_pattern("  ").is_err());
+
+    assert_eq!(memory::find_pattern(&[1, 2, 3, 1, 2], &[Some(1), Some(2)]), vec![0, 3]);
+    assert_eq!(memory::find_pattern(&[1, 2, 3, 1, 5], &[Some(1), None, Some(3)]), vec![0]);
+    assert!(memory::find_pattern(&[1, 2], &[]).is_empty());
+
+    let gb = create_gameboy("memory_viewer_search.gb", &VRAM_PROGRAM);
+    let pattern = memory::parse_byte_pattern("3E 5A EA ?? 9F").unwrap();
+    assert_eq!(gb.search_memory(&pattern, MemoryBanks::default()), vec![0x0153]);
+}
+
+#[test]
+fn sram_edits_are_saved() {
+    // mbc1 + ram + battery
+    let rom = create_test_rom(0x03, 0x02, &VRAM_PROGRAM);
+    let rom_path = write_test_rom("memory_viewer_save.gb", &rom);
+
+    let storage = MemorySaveStorage::new();
+    let options = LoadOptions {
+        save_storage: Some(Box::new(storage.clone())),
+        ..LoadOptions::default()
+    };
+
+    let mut gb = GameBoy::new_with_options(rom_path.to_str().unwrap(), None, options).unwrap();
+    gb.debug_write(0xA005, MemoryBanks::default(), 0x3C);
+    gb.save();
+
+    assert_eq!(storage.get("memory_viewer_save.sav").unwrap()[5], 0x3C);
+}
@@ -31,7 +31,7 @@ macro_rules! mooneye_test {
                     s.tick();
                 }
 
-                let fb = s.get_frame_buffer();
+                let fb = s.get_shade_buffer();
 
                 let bin_file_path = format!("./tests/expected/mooneye/acceptance/{}.png", &path);
                 let comparison = compare_image_rgb8(fb, bin_file_path);
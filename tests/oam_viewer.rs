@@ -0,0 +1,95 @@
+use gameboy_rs::gameboy::{debugger::oam::{self, OamEntry}};
+use common::{CYCLES_PER_SCREEN_DRAW, boot_rom};
+
+mod common;
+
+// 12 sprites on lines 16-23, two more than the ppu can draw
+const PROGRAM: [u8; 26] = [
+    0xAF,             // 0x150: xor a
+    0xE0, 0x40,       // 0x151: ldh [$40], a (lcd off)
+    0x21, 0x00, 0xFE, // 0x153: ld hl, $FE00
+    0x06, 0x0C,       // 0x156: ld b, 12
+    0x3E, 0x20,       // 0x158: ld a, $20
+    0x22,             // 0x15A: ld [hl+], a (y)
+    0x3E, 0x10,       // 0x15B: ld a, $10
+    0x22,             // 0x15D: ld [hl+], a (x)
+    0xAF,             // 0x15E: xor a
+    0x22,             // 0x15F: ld [hl+], a (tile)
+    0x22,             // 0x160: ld [hl+], a (flags)
+    0x05,             // 0x161: dec b
+    0x20, 0xF4,       // 0x162: jr nz, -12
+    0x3E, 0x93,       // 0x164: ld a, $93 (lcd, bg and sprites on)
+    0xE0, 0x40,       // 0x166: ldh [$40], a
+    0x18, 0xFE        // 0x168: jr -2
+];
+
+#[test]
+fn decode_entries() {
+    let mut table = [0u8; 0xA0];
+    table[4..8].copy_from_slice(&[0x20, 0x10, 0x05, 0b1111_0000]);
+
+    let entry = OamEntry::read(&table, 1);
+    assert_eq!((entry.index, entry.y, entry.x, entry.tile), (1, 0x20, 0x10, 0x05));
+    assert_eq!(entry.palette(), 1);
+    assert!(entry.xflip() && entry.yflip() && entry.behind_bg());
+
+    assert!(entry.on_line(16, false));
+    assert!(entry.on_line(23, false));
+    assert!(!entry.on_line(24, false));
+    assert!(entry.on_line(31, true));
+    assert!(!entry.on_line(15, true));
+
+    // 8x16 sprites ignore bit 0 of the tile
+    assert_eq!(entry.tiles(true), vec![0x04, 0x05]);
+    assert_eq!(entry.tiles(false), vec![0x05]);
+
+    // x = 0 hides it
+    table[5] = 0;
+    assert!(!OamEntry::read(&table, 1).on_line(16, false));
+    assert_eq!(oam::entries(&table).len(), 40);
+}
+
+#[test]
+fn render_flipped_sprites() {
+    // tile 0: colour 3 in the top left pixel only
+    let mut vram = [0u8; 0x2000];
+    vram[0] = 0x80;
+    vram[1] = 0x80;
+
+    let mut entry = OamEntry { index: 0, y: 16, x: 8, tile: 0, flags: 0 };
+    let pixels = oam::render_sprite(&vram, &entry, false, 0xE4);
+    assert_eq!(pixels.len(), 64);
+    assert_eq!(pixels[0], 0);
+    assert_eq!(pixels[1], 255);
+
+    entry.flags = 0b0110_0000;
+    let pixels = oam::render_sprite(&vram, &entry, false, 0xE4);
+    assert_eq!(pixels[63], 0);
+    assert_eq!(pixels[0], 255);
+
+    // y flip on a tall sprite swaps the tiles too
+    let pixels = oam::render_sprite(&vram, &entry, true, 0xE4);
+    assert_eq!(pixels.len(), 128);
+    assert_eq!(pixels[127], 0);
+}
+
+#[test]
+fn oam_scan_picks_the_first_ten() {
+    let mut gb = boot_rom("oam_viewer.gb", &PROGRAM);
+
+    for _ in 0..CYCLES_PER_SCREEN_DRAW {
+        gb.tick();
+        if gb.oam_scan().line == 20 { break }
+    }
+
+    let scan = gb.oam_scan();
+    assert_eq!(scan.line, 20);
+    assert_eq!(scan.sprites, (0..10).collect::<Vec<usize>>());
+
+    let entries = oam::entries(&gb.oam());
+    let dropped: Vec<usize> = entries.iter()
+        .filter(|entry| entry.on_line(20, false) && !scan.contains(entry.index))
+        .map(|entry| entry.index)
+        .collect();
+    assert_eq!(dropped, vec![10, 11]);
+}
@@ -0,0 +1,54 @@
+use gameboy_rs::gameboy::{GameBoy, ppu::palette::DmgPalette};
+use common::{boot_rom, run_frames};
+
+mod common;
+
+// leaves the boot rom's logo up
+const PROGRAM: [u8; 2] = [
+    0x18, 0xFE // 0x150: jr -2
+];
+
+fn assert_matches_shades(gb: &GameBoy, palette: &DmgPalette) {
+    let rgba = gb.get_frame_buffer();
+    let shades = gb.get_shade_buffer();
+    assert_eq!(rgba.len(), shades.len() * 4);
+
+    for (pixel, grey) in rgba.chunks(4).zip(shades.iter()) {
+        assert_eq!(pixel, &palette.colour_for_grey(*grey)[..]);
+    }
+}
+
+#[test]
+fn parse_palettes() {
+    assert_eq!(DmgPalette::parse("green"), Ok(DmgPalette::CLASSIC_GREEN));
+    assert_eq!(DmgPalette::parse(" Pocket "), Ok(DmgPalette::POCKET));
+    assert_eq!(DmgPalette::parse("light").unwrap().name(), Some("Light"));
+
+    let custom = DmgPalette::parse("#FFFFFF, AA5500 005500,#000000").unwrap();
+    assert_eq!(custom.shades[0], [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(custom.shades[1], [0xAA, 0x55, 0x00, 0xFF]);
+    assert_eq!(custom.shades[3], [0x00, 0x00, 0x00, 0xFF]);
+    assert_eq!(custom.name(), None);
+
+    assert!(DmgPalette::parse("FFFFFF,AAAAAA,555555").is_err());
+    assert!(DmgPalette::parse("FFFFFF,AAAAAA,555555,GG0000").is_err());
+    assert!(DmgPalette::parse("FFF,AAAAAA,555555,000000").is_err());
+}
+
+#[test]
+fn rgba_follows_the_palette() {
+    let mut gb = boot_rom("palette.gb", &PROGRAM);
+
+    assert_eq!(gb.palette(), DmgPalette::GREY);
+    assert!(gb.get_shade_buffer().contains(&0));
+    assert_matches_shades(&gb, &DmgPalette::GREY);
+
+    // switching repaints the frame that's already there
+    let shades = gb.get_shade_buffer().to_vec();
+    gb.set_palette(DmgPalette::CLASSIC_GREEN);
+    assert_eq!(gb.get_shade_buffer(), &shades[..]);
+    assert_matches_shades(&gb, &DmgPalette::CLASSIC_GREEN);
+
+    run_frames(&mut gb, 1);
+    assert_matches_shades(&gb, &DmgPalette::CLASSIC_GREEN);
+}
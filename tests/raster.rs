@@ -0,0 +1,62 @@
+use gameboy_rs::gameboy::{debugger::raster::{LINES, RasterRegister}, ppu::PpuMode};
+use common::{CYCLES_PER_SCREEN_DRAW, boot_rom};
+
+mod common;
+
+// writes a new scx every 28 dots, forever
+const PROGRAM: [u8; 6] = [
+    0xAF,       // 0x150: xor a
+    0x3C,       // 0x151: inc a
+    0xE0, 0x43, // 0x152: ldh [$43], a
+    0x18, 0xFB  // 0x154: jr -5
+];
+
+#[test]
+fn scx_writes_recorded_per_line() {
+    let gb = boot_rom("raster_history.gb", &PROGRAM);
+    let lines = gb.raster_history().last_frame();
+    assert_eq!(lines.len(), LINES);
+
+    for (ly, line) in lines.iter().enumerate() {
+        assert!(!line.writes.is_empty(), "no writes on line {}", ly);
+        assert!(line.writes.iter().all(|write| write.register == RasterRegister::Scx && write.dot < 456));
+
+        // only the visible lines reach mode 3
+        assert_eq!(line.mode3.is_some(), ly < 144, "line {}", ly);
+    }
+
+    // the value drawn with is the last one written before mode 3
+    for line in lines[..144].iter() {
+        if let Some(write) = line.writes.iter().rfind(|write| write.dot < 80) {
+            assert_eq!(line.mode3.unwrap().scx, write.value);
+        }
+    }
+}
+
+#[test]
+fn ppu_state_follows_the_ppu() {
+    let mut gb = boot_rom("raster_state.gb", &PROGRAM);
+
+    let state = gb.ppu_state();
+    assert!(state.lcd_on);
+    assert!(state.ly < LINES as u8);
+    assert!(state.dot < 456);
+    assert_eq!(state.ly, gb.peek_byte(0xFF44));
+
+    // one dot per tick until the line rolls over
+    for _ in 0..CYCLES_PER_SCREEN_DRAW {
+        let before = gb.ppu_state();
+        gb.tick();
+        let after = gb.ppu_state();
+
+        if after.dot == 0 {
+            assert_eq!(before.dot, 455);
+        } else {
+            assert_eq!(after.dot, before.dot + 1);
+        }
+
+        if after.ly >= 144 {
+            assert_eq!(after.mode, PpuMode::VBlank);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::io::Write;
+
+use flate2::{Compression, GzBuilder, write::GzEncoder};
+use gameboy_rs::gameboy::{GameBoy, cartridge::{LoadOptions, loader::load_rom_file, save_storage::MemorySaveStorage}};
+use common::{WRITE_SRAM_PROGRAM, create_test_rom, run_frames, write_test_rom};
+use zip::{ZipWriter, write::FileOptions};
+
+mod common;
+
+fn create_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(data).unwrap();
+    }
+
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn loads_first_rom_in_zip() {
+    let rom = create_test_rom(0x1B, 0x02, &WRITE_SRAM_PROGRAM);
+    let zip = create_zip(&[
+        ("readme.txt", b"not a rom"),
+        ("roms/Inner Game.gb", &rom)
+    ]);
+    let zip_path = write_test_rom("archive.zip", &zip);
+
+    let loaded = load_rom_file(&zip_path).unwrap();
+    assert_eq!(loaded.file_name, "Inner Game.gb");
+    assert_eq!(loaded.data, rom);
+}
+
+#[test]
+fn zip_without_rom_is_an_error() {
+    let zip = create_zip(&[("readme.txt", b"not a rom")]);
+    let zip_path = write_test_rom("empty_archive.zip", &zip);
+
+    assert!(load_rom_file(&zip_path).is_err());
+}
+
+#[test]
+fn loads_gz_with_and_without_stored_name() {
+    let rom = create_test_rom(0x00, 0x00, &[]);
+
+    let mut encoder = GzBuilder::new()
+        .filename("stored.gb")
+        .write(Vec::new(), Compression::default());
+    encoder.write_all(&rom).unwrap();
+    let gz_path = write_test_rom("renamed.gz", &encoder.finish().unwrap());
+
+    let loaded = load_rom_file(&gz_path).unwrap();
+    assert_eq!(loaded.file_name, "stored.gb");
+    assert_eq!(loaded.data, rom);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&rom).unwrap();
+    let gz_path = write_test_rom("plain.gb.gz", &encoder.finish().unwrap());
+
+    let loaded = load_rom_file(&gz_path).unwrap();
+    assert_eq!(loaded.file_name, "plain.gb");
+    assert_eq!(loaded.file_stem(), "plain");
+}
+
+#[test]
+fn save_is_named_after_rom_in_zip() {
+    let rom = create_test_rom(0x1B, 0x02, &WRITE_SRAM_PROGRAM);
+    let zip = create_zip(&[("zipped_game.gb", &rom)]);
+    let zip_path = write_test_rom("zipped_game_archive.zip", &zip);
+
+    let storage = MemorySaveStorage::new();
+    {
+        let options = LoadOptions {
+            save_storage: Some(Box::new(storage.clone())),
+            ..LoadOptions::default()
+        };
+
+        let mut gb = GameBoy::new_with_options(zip_path.to_str().unwrap(), None, options).unwrap();
+        run_frames(&mut gb, 60 * 2);
+    }
+
+    assert_eq!(storage.get("zipped_game.sav").unwrap()[0], 0x42);
+    assert!(storage.get("zipped_game_archive.sav").is_none());
+
+    let gb = GameBoy::new(zip_path.to_str().unwrap(), None);
+    assert_eq!(gb.rom_name(), "zipped_game");
+}
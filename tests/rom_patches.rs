@@ -0,0 +1,215 @@
+use std::fs;
+
+use gameboy_rs::gameboy::{GameBoy, cartridge::{LoadOptions, patch::{PatchError, apply_patch, crc32}}};
+use common::{create_test_rom, write_test_rom};
+
+mod common;
+
+fn encode_varint(mut value: usize, out: &mut Vec<u8>) {
+    loop {
+        let x = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(0x80 | x);
+            break;
+        }
+
+        out.push(x);
+        value -= 1;
+    }
+}
+
+fn add_footer(patch: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    let patch_crc = crc32(patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+}
+
+fn create_ups(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut patch = b"UPS1".to_vec();
+    encode_varint(source.len(), &mut patch);
+    encode_varint(target.len(), &mut patch);
+
+    let xor = |i: usize| target[i] ^ source.get(i).copied().unwrap_or(0);
+
+    // one hunk per run of changed bytes
+    let mut i = 0;
+    let mut offset = 0;
+    while i < target.len() {
+        if xor(i) == 0 {
+            i += 1;
+            continue;
+        }
+
+        encode_varint(i - offset, &mut patch);
+        while i < target.len() && xor(i) != 0 {
+            patch.push(xor(i));
+            i += 1;
+        }
+
+        patch.push(0);
+        i += 1;
+        offset = i;
+    }
+
+    add_footer(&mut patch, source, target);
+    patch
+}
+
+fn create_ips_title_patch(title: &[u8]) -> Vec<u8> {
+    let mut patch = b"PATCH".to_vec();
+    patch.extend_from_slice(&[0x00, 0x01, 0x34]);
+    patch.extend_from_slice(&(title.len() as u16).to_be_bytes());
+    patch.extend_from_slice(title);
+    patch.extend_from_slice(b"EOF");
+    patch
+}
+
+#[test]
+fn ips_records_rle_and_truncation() {
+    let rom = vec![0; 0x20];
+
+    let mut patch = b"PATCH".to_vec();
+    // 3 bytes at 0x04
+    patch.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x03, 0xAA, 0xBB, 0xCC]);
+    // run of 4 0x11s at 0x10
+    patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x04, 0x11]);
+    // 2 bytes past the end of the rom
+    patch.extend_from_slice(&[0x00, 0x00, 0x20, 0x00, 0x02, 0x01, 0x02]);
+    patch.extend_from_slice(b"EOF");
+
+    let patched = apply_patch(&rom, &patch).unwrap();
+    assert_eq!(patched.len(), 0x22);
+    assert_eq!(&patched[0x04..0x07], &[0xAA, 0xBB, 0xCC]);
+    assert_eq!(&patched[0x10..0x14], &[0x11; 4]);
+    assert_eq!(&patched[0x20..0x22], &[0x01, 0x02]);
+
+    patch.extend_from_slice(&[0x00, 0x00, 0x08]);
+    let patched = apply_patch(&rom, &patch).unwrap();
+    assert_eq!(patched.len(), 0x08);
+
+    assert_eq!(apply_patch(&rom, b"PATCH\x00\x00"), Err(PatchError::Truncated));
+}
+
+#[test]
+fn ups_patch_round_trip() {
+    let source: Vec<u8> = (0..0x100).map(|i| i as u8).collect();
+    let mut target = source.clone();
+    target[0x00] = 0xFF;
+    target[0x42] = 0x00;
+    target[0x43] = 0x99;
+    target.extend_from_slice(&[0x12, 0x34]);
+
+    let patch = create_ups(&source, &target);
+    assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+}
+
+#[test]
+fn ups_checks_checksums() {
+    let source = vec![0x55; 0x40];
+    let mut target = source.clone();
+    target[0x10] = 0xAA;
+    let patch = create_ups(&source, &target);
+
+    let mut other_rom = source.clone();
+    other_rom[0] = 0;
+    match apply_patch(&other_rom, &patch) {
+        Err(PatchError::SourceChecksumMismatch { .. }) => {}
+        result => panic!("Expected a source checksum error, got {:?}", result)
+    }
+
+    let mut corrupt_patch = patch.clone();
+    corrupt_patch[8] ^= 0xFF;
+    match apply_patch(&source, &corrupt_patch) {
+        Err(PatchError::PatchChecksumMismatch { .. }) => {}
+        result => panic!("Expected a patch checksum error, got {:?}", result)
+    }
+}
+
+#[test]
+fn bps_patch_with_every_action() {
+    let source = b"ABCDEFGH".to_vec();
+    let target = b"ABCDxyxyxyFG".to_vec();
+
+    let mut patch = b"BPS1".to_vec();
+    encode_varint(source.len(), &mut patch);
+    encode_varint(target.len(), &mut patch);
+    encode_varint(0, &mut patch);
+
+    // SourceRead 4 -> ABCD
+    encode_varint(3 << 2, &mut patch);
+    // TargetRead 2 -> xy
+    encode_varint((1 << 2) | 1, &mut patch);
+    patch.extend_from_slice(b"xy");
+    // TargetCopy 4 from offset 4 -> xyxy
+    encode_varint((3 << 2) | 3, &mut patch);
+    encode_varint(4 << 1, &mut patch);
+    // SourceCopy 2 from offset 5 -> FG
+    encode_varint((1 << 2) | 2, &mut patch);
+    encode_varint(5 << 1, &mut patch);
+
+    add_footer(&mut patch, &source, &target);
+    assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+
+    // wrong target checksum
+    let len = patch.len();
+    patch[len - 8] ^= 0xFF;
+    let patch_crc = crc32(&patch[..len - 4]);
+    patch[len - 4..].copy_from_slice(&patch_crc.to_le_bytes());
+    match apply_patch(&source, &patch) {
+        Err(PatchError::TargetChecksumMismatch { .. }) => {}
+        result => panic!("Expected a target checksum error, got {:?}", result)
+    }
+}
+
+#[test]
+fn patch_next_to_rom_is_applied() {
+    let rom = create_test_rom(0x00, 0x00, &[]);
+    let rom_path = write_test_rom("auto_patched.gb", &rom);
+    fs::write(rom_path.with_extension("ips"), create_ips_title_patch(b"PATCHED")).unwrap();
+
+    let gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    assert_eq!(gb.cartridge_header().title, "PATCHED");
+}
+
+#[test]
+fn explicit_patch_is_applied() {
+    let rom = create_test_rom(0x00, 0x00, &[]);
+    let rom_path = write_test_rom("explicit_patch.gb", &rom);
+
+    let mut target = rom.clone();
+    target[0x134..0x13C].copy_from_slice(b"UPSTITLE");
+    let patch_path = write_test_rom("explicit_patch_translation.ups", &create_ups(&rom, &target));
+
+    let options = LoadOptions {
+        patch_path: Some(patch_path),
+        ..LoadOptions::default()
+    };
+
+    let gb = GameBoy::new_with_options(rom_path.to_str().unwrap(), None, options).unwrap();
+    assert_eq!(gb.cartridge_header().title, "UPSTITLE");
+}
+
+#[test]
+fn explicit_patch_that_doesnt_fit_is_an_error() {
+    let rom = create_test_rom(0x00, 0x00, &[]);
+    let rom_path = write_test_rom("mismatched_patch.gb", &rom);
+
+    // made for a different rom, the source checksum won't match
+    let mut other = rom.clone();
+    other[0x200] = 0x42;
+    let mut target = other.clone();
+    target[0x134..0x13C].copy_from_slice(b"UPSTITLE");
+    let patch_path = write_test_rom("mismatched_patch.ups", &create_ups(&other, &target));
+
+    let options = LoadOptions {
+        patch_path: Some(patch_path.clone()),
+        ..LoadOptions::default()
+    };
+
+    match GameBoy::new_with_options(rom_path.to_str().unwrap(), None, options) {
+        Ok(_) => panic!("loaded with a patch for another rom"),
+        Err(err) => assert!(err.contains(&patch_path.display().to_string()), "{}", err)
+    }
+}
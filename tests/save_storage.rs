@@ -0,0 +1,74 @@
+use gameboy_rs::gameboy::{GameBoy, cartridge::{LoadOptions, save_storage::{FileSaveStorage, MemorySaveStorage}}};
+use common::{WRITE_SRAM_PROGRAM, create_test_rom, run_frames, write_test_rom};
+
+mod common;
+
+fn run_with_storage(rom_name: &str, cartridge_type: u8, storage: MemorySaveStorage) {
+    let rom = create_test_rom(cartridge_type, 0x02, &WRITE_SRAM_PROGRAM);
+    let rom_path = write_test_rom(rom_name, &rom);
+
+    let options = LoadOptions {
+        save_storage: Some(Box::new(storage)),
+        ..LoadOptions::default()
+    };
+
+    let mut gb = GameBoy::new_with_options(rom_path.to_str().unwrap(), None, options).unwrap();
+    run_frames(&mut gb, 60 * 2);
+}
+
+#[test]
+fn battery_cart_saves_full_stem() {
+    let storage = MemorySaveStorage::new();
+    run_with_storage("battery_save.gb", 0x1B, storage.clone());
+
+    let save = storage.get("battery_save.sav").expect("no save was written");
+    assert_eq!(save.len(), 0x2000);
+    assert_eq!(save[0], 0x42);
+}
+
+#[test]
+fn cart_without_battery_does_not_save() {
+    let storage = MemorySaveStorage::new();
+    run_with_storage("no_battery_save.gb", 0x1A, storage.clone());
+
+    assert!(storage.get("no_battery_save.sav").is_none());
+}
+
+#[test]
+fn save_is_loaded_back() {
+    let storage = MemorySaveStorage::new();
+    let mut save = vec![0u8; 0x2000];
+    save[0x10] = 0x99;
+    storage.insert("load_save.sav", save);
+
+    run_with_storage("load_save.gb", 0x1B, storage.clone());
+
+    let save = storage.get("load_save.sav").unwrap();
+    assert_eq!(save[0], 0x42);
+    assert_eq!(save[0x10], 0x99);
+}
+
+#[test]
+fn file_storage_writes_into_save_dir() {
+    let rom = create_test_rom(0x1B, 0x02, &WRITE_SRAM_PROGRAM);
+    let rom_path = write_test_rom("file_save.gb", &rom);
+
+    let mut save_dir = std::env::temp_dir();
+    save_dir.push("frosty_tests");
+    save_dir.push("saves");
+    let _ = std::fs::remove_file(save_dir.join("file_save.sav"));
+
+    {
+        let options = LoadOptions {
+            save_storage: Some(Box::new(FileSaveStorage::new(&save_dir))),
+            ..LoadOptions::default()
+        };
+
+        let mut gb = GameBoy::new_with_options(rom_path.to_str().unwrap(), None, options).unwrap();
+        run_frames(&mut gb, 60 * 2);
+    }
+
+    let save = std::fs::read(save_dir.join("file_save.sav")).unwrap();
+    assert_eq!(save[0], 0x42);
+    assert!(!save_dir.join("file_save.sav.tmp").exists());
+}
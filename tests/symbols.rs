@@ -0,0 +1,82 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::{BreakReason, symbols::Symbols}};
+use common::{CYCLES_PER_SCREEN_DRAW, create_test_rom, write_test_rom};
+
+mod common;
+
+const PROGRAM: [u8; 21] = [
+    0x3E, 0x05,       // 0x150: ld a, 5
+    0xCD, 0x60, 0x01, // 0x152: call 0x0160
+    0x3C,             // 0x155: inc a
+    0x18, 0xFE,       // 0x156: jr -2
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x06, 0x07,       // 0x160: ld b, 7
+    0x0E, 0x09,       // 0x162: ld c, 9
+    0xC9              // 0x164: ret
+];
+
+const SYMBOLS: &str = "\
+; File generated by rgblink
+00:0150 Start
+00:0155 Start.loop
+00:0160 Sub
+01:4000 BankedCode
+00:C000 wCounter
+not a symbol
+";
+
+#[test]
+fn parse_sym_file() {
+    let symbols = Symbols::parse(SYMBOLS);
+
+    assert_eq!(symbols.len(), 5);
+    assert_eq!(symbols.lookup("Start.loop"), Some((0, 0x0155)));
+    assert_eq!(symbols.lookup("Missing"), None);
+
+    assert_eq!(symbols.resolve("Sub"), Some((0x0160, None)));
+    assert_eq!(symbols.resolve("BankedCode"), Some((0x4000, Some(1))));
+
+    assert_eq!(symbols.name_at(Some(1), 0x4000), Some("BankedCode"));
+    assert_eq!(symbols.name_at(Some(2), 0x4000), None);
+    assert_eq!(symbols.name_at(None, 0xC000), Some("wCounter"));
+
+    assert_eq!(symbols.nearest(0, 0x0162), Some(("Sub", 2)));
+    assert_eq!(symbols.nearest(1, 0x0162), None);
+}
+
+#[test]
+fn labels_in_the_debugger() {
+    let rom = create_test_rom(0x00, 0x00, &PROGRAM);
+    let rom_path = write_test_rom("symbols.gb", &rom);
+    std::fs::write(rom_path.with_extension("sym"), SYMBOLS).unwrap();
+
+    let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    assert_eq!(gb.debugger().symbols().len(), 5);
+
+    let (addr, bank) = gb.debugger().symbols().resolve("Sub").unwrap();
+    gb.debugger_mut().add_breakpoint(addr, bank);
+
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 10 {
+        if gb.tick() { break }
+    }
+
+    assert_eq!(gb.debugger().break_reason(), Some(BreakReason::Breakpoint(0)));
+    assert_eq!(gb.registers().pc, 0x0160);
+
+    assert_eq!(gb.label_at(0x0155), Some(String::from("Start.loop")));
+    assert_eq!(gb.describe_address(0x0162), "Sub+$2");
+    assert_eq!(gb.describe_address(0x0100), "$0100");
+    assert_eq!(gb.format_instruction(&gb.disassemble_instruction(0x0152)), "call Sub");
+
+    let frames = gb.debugger().call_stack().frames();
+    assert_eq!(frames.len(), 1);
+    assert_eq!((frames[0].from, frames[0].target, frames[0].interrupt), (0x0152, 0x0160, false));
+
+    // the frame goes away once sub returns
+    gb.step_out();
+    for _ in 0..CYCLES_PER_SCREEN_DRAW {
+        if gb.tick() { break }
+    }
+
+    assert_eq!(gb.registers().pc, 0x0155);
+    assert!(gb.debugger().call_stack().frames().is_empty());
+}
@@ -0,0 +1,75 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::timeline::{self, CSV_HEADER, TimelineEvent}, interupt::InterruptFlag};
+use common::{create_test_rom, run_frames, write_test_rom};
+
+mod common;
+
+// one oam dma, then halt until every vblank
+const PROGRAM: [u8; 12] = [
+    0x3E, 0xC0, // 0x150: ld a, $C0
+    0xE0, 0x46, // 0x152: ldh [$46], a
+    0x3E, 0x01, // 0x154: ld a, $01
+    0xE0, 0xFF, // 0x156: ldh [$FF], a (vblank only)
+    0xFB,       // 0x158: ei
+    0x76,       // 0x159: halt
+    0x18, 0xFD  // 0x15A: jr -3
+];
+
+fn run_program(name: &str) -> GameBoy {
+    let mut rom = create_test_rom(0x00, 0x00, &PROGRAM);
+    rom[0x40] = 0xD9; // reti
+
+    let rom_path = write_test_rom(name, &rom);
+
+    let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    gb.timeline_mut().start();
+    run_frames(&mut gb, 60 * 3);
+
+    gb
+}
+
+#[test]
+fn vblank_and_halt_per_frame() {
+    let gb = run_program("timeline_vblank.gb");
+    let entries = gb.timeline().frame(gb.frames() - 2);
+    let events: Vec<TimelineEvent> = entries.iter().map(|entry| entry.event).collect();
+
+    assert_eq!(events, vec![
+        TimelineEvent::InterruptRequested(InterruptFlag::VBlank),
+        TimelineEvent::HaltExited,
+        TimelineEvent::InterruptServiced(InterruptFlag::VBlank),
+        TimelineEvent::HaltEntered
+    ]);
+
+    // frames start at vblank
+    assert_eq!(entries[0].ly, 144);
+    assert_eq!(entries[0].frame_cycle, entries[0].dot);
+
+    let latency = timeline::latency(&entries, 2).unwrap();
+    assert!(latency > 0 && latency < 24, "latency {}", latency);
+    assert_eq!(timeline::latency(&entries, 1), None);
+}
+
+#[test]
+fn dma_lcd_and_csv() {
+    let mut gb = run_program("timeline_csv.gb");
+    let entries = gb.timeline().entries();
+
+    assert!(entries.iter().any(|entry| entry.event == TimelineEvent::LcdOn));
+    assert!(entries.iter().any(|entry| entry.event == TimelineEvent::OamDma { source: 0xC0 }));
+    assert!(entries.iter().zip(entries.iter().skip(1)).all(|(a, b)| a.cycle <= b.cycle));
+
+    let mut csv = Vec::new();
+    gb.timeline().write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], CSV_HEADER);
+    assert_eq!(lines.len(), entries.len() + 1);
+    assert!(lines.iter().any(|line| line.ends_with(",oam_dma,C000")));
+    assert!(lines.iter().any(|line| line.ends_with(",irq_service,VBlank")));
+
+    // nothing more once it's stopped
+    let count = entries.len();
+    gb.timeline_mut().stop();
+    run_frames(&mut gb, 1);
+    assert_eq!(gb.timeline().entries().len(), count);
+}
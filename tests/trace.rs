@@ -0,0 +1,233 @@
+use std::fs;
+
+use gameboy_rs::gameboy::{GameBoy, debugger::BreakReason, trace::{BINARY_ENTRY_SIZE, BINARY_MAGIC, TraceEntry, TraceFormat, TraceOptions, TraceStart, compare::{CompareOptions, CompareResult, ReferenceEntry, TraceField}}};
+use common::{CYCLES_PER_SCREEN_DRAW, create_gameboy, create_test_rom, write_test_rom};
+
+mod common;
+
+const PROGRAM: [u8; 7] = [
+    0x3E, 0x05,       // 0x150: ld a, 5
+    0x06, 0x07,       // 0x152: ld b, 7
+    0x3C,             // 0x154: inc a
+    0x18, 0xFD        // 0x155: jr -3
+];
+
+fn run_trace(name: &str, format: TraceFormat) -> Vec<u8> {
+    trace_rom(name, &create_test_rom(0x00, 0x00, &PROGRAM), format, 10)
+}
+
+fn trace_rom(name: &str, rom: &[u8], format: TraceFormat, instructions: u64) -> Vec<u8> {
+    let rom_path = write_test_rom(name, rom);
+    let trace_path = rom_path.with_extension("trace");
+
+    let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    gb.start_trace(TraceOptions {
+        format,
+        start: TraceStart::Pc(0x0150),
+        ..TraceOptions::new(&trace_path)
+    }).unwrap();
+
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 5 {
+        gb.tick();
+        if gb.trace().unwrap().instructions_logged() >= instructions { break }
+    }
+
+    gb.stop_trace();
+    fs::read(trace_path).unwrap()
+}
+
+// turns on the vblank interrupt then runs through a sled of nops, so the
+// interrupt mostly turns up while a nop is still being fetched
+fn interrupt_rom() -> Vec<u8> {
+    let mut program = vec![
+        0x3E, 0x01,       // 0x150: ld a, 1
+        0xE0, 0xFF,       // 0x152: ldh (IE), a
+        0xFB              // 0x154: ei
+    ];
+    program.extend_from_slice(&[0x00; 0x40]);
+    program.extend_from_slice(&[0xC3, 0x55, 0x01]); // jp 0x155
+
+    let mut rom = create_test_rom(0x00, 0x00, &program);
+    rom[0x40] = 0xD9; // reti
+    rom
+}
+
+fn trace_pc(line: &str) -> &str {
+    let start = line.find("PC:").unwrap() + 3;
+    &line[start..start + 4]
+}
+
+#[test]
+fn gameboy_doctor_format() {
+    let log = String::from_utf8(run_trace("trace_doctor.gb", TraceFormat::GameboyDoctor)).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+
+    assert_eq!(lines.len(), 10);
+    assert!(lines[0].starts_with("A:"));
+    assert!(lines[0].ends_with("SP:FFFE PC:0150 PCMEM:3E,05,06,07"), "{}", lines[0]);
+    assert!(lines[1].starts_with("A:05 "), "{}", lines[1]);
+    assert!(lines[1].ends_with("PC:0152 PCMEM:06,07,3C,18"), "{}", lines[1]);
+    assert!(lines[2].contains(" B:07 "), "{}", lines[2]);
+    assert!(lines[3].starts_with("A:06 "), "{}", lines[3]);
+    assert!(lines[3].contains("PC:0155"), "{}", lines[3]);
+    assert!(lines[4].contains("PC:0154"), "{}", lines[4]);
+}
+
+#[test]
+fn binary_format() {
+    let trace = run_trace("trace_binary.gb", TraceFormat::Binary);
+
+    assert_eq!(&trace[0..4], BINARY_MAGIC);
+    assert_eq!(trace.len(), 8 + 10 * BINARY_ENTRY_SIZE);
+
+    let entries: Vec<TraceEntry> = trace[8..].chunks_exact(BINARY_ENTRY_SIZE)
+        .map(|bytes| {
+            let mut entry = [0u8; BINARY_ENTRY_SIZE];
+            entry.copy_from_slice(bytes);
+            TraceEntry::from_bytes(&entry)
+        })
+        .collect();
+
+    assert_eq!(entries[0].registers.pc, 0x0150);
+    assert_eq!(entries[0].registers.sp, 0xFFFE);
+    assert_eq!(entries[0].pcmem, [0x3E, 0x05, 0x06, 0x07]);
+    assert_eq!(entries[1].registers.a, 0x05);
+    assert_eq!(entries[2].registers.b, 0x07);
+
+    assert_eq!(TraceEntry::from_bytes(&entries[3].to_bytes()), entries[3]);
+}
+
+#[test]
+fn parse_trace_options() {
+    assert_eq!(TraceFormat::parse("doctor"), Some(TraceFormat::GameboyDoctor));
+    assert_eq!(TraceFormat::parse("Readable"), Some(TraceFormat::Disassembly));
+    assert_eq!(TraceFormat::parse("binary"), Some(TraceFormat::Binary));
+    assert_eq!(TraceFormat::parse("xml"), None);
+
+    assert_eq!(TraceStart::parse("boot"), Some(TraceStart::Boot));
+    assert_eq!(TraceStart::parse("pc:0150"), Some(TraceStart::Pc(0x0150)));
+    assert_eq!(TraceStart::parse("pc:$C000"), Some(TraceStart::Pc(0xC000)));
+    assert_eq!(TraceStart::parse("frame:60"), Some(TraceStart::Frame(60)));
+    assert_eq!(TraceStart::parse("frame:soon"), None);
+}
+
+fn run_compare(name: &str, reference: &str) -> (GameBoy, CompareResult) {
+    compare_rom(name, &create_test_rom(0x00, 0x00, &PROGRAM), reference)
+}
+
+fn compare_rom(name: &str, rom: &[u8], reference: &str) -> (GameBoy, CompareResult) {
+    let rom_path = write_test_rom(name, rom);
+    let reference_path = rom_path.with_extension("ref");
+    fs::write(&reference_path, reference).unwrap();
+
+    let mut gb = GameBoy::new(rom_path.to_str().unwrap(), None);
+    gb.start_trace_compare(CompareOptions::new(&reference_path)).unwrap();
+
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 5 {
+        if gb.tick() { break }
+    }
+
+    assert_eq!(gb.debugger().break_reason(), Some(BreakReason::TraceCompare));
+    let result = gb.trace_compare().unwrap().result().unwrap().clone();
+    (gb, result)
+}
+
+#[test]
+fn compare_matching_reference() {
+    let reference = String::from_utf8(run_trace("compare_source.gb", TraceFormat::GameboyDoctor)).unwrap();
+    let (_, result) = run_compare("compare_match.gb", &reference);
+
+    match result {
+        CompareResult::Matched(count) => assert_eq!(count, 10),
+        _ => panic!("Expected a match: {}", result)
+    }
+}
+
+#[test]
+fn compare_stops_at_divergence() {
+    let reference = String::from_utf8(run_trace("compare_source2.gb", TraceFormat::GameboyDoctor)).unwrap();
+
+    // pretend inc a gave 7 the first time round
+    let mut lines: Vec<String> = reference.lines().map(String::from).collect();
+    lines[3] = lines[3].replacen("A:06", "A:07", 1);
+    let (gb, result) = run_compare("compare_diverge.gb", &lines.join("\n"));
+
+    let divergence = match &result {
+        CompareResult::Diverged(divergence) => divergence,
+        _ => panic!("Expected a divergence: {}", result)
+    };
+
+    assert_eq!(divergence.index, 3);
+    assert_eq!(divergence.expected.line, 4);
+    assert_eq!(divergence.mismatched, vec![TraceField::A]);
+    assert_eq!(divergence.actual.registers.a, 0x06);
+    assert_eq!(divergence.history.len(), 3);
+    assert_eq!(divergence.history[0].registers.pc, 0x0150);
+
+    // paused before the instruction that didn't match
+    assert_eq!(gb.registers().pc, 0x0155);
+
+    let report = result.to_string();
+    assert!(report.contains("Diverged at instruction 3 (line 4 of the reference)"), "{}", report);
+    assert!(report.contains("inc a"), "{}", report);
+}
+
+#[test]
+fn interrupted_fetches_stay_out_of_the_trace() {
+    let log = String::from_utf8(trace_rom("trace_interrupt.gb", &interrupt_rom(), TraceFormat::GameboyDoctor, 60_000)).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+
+    let mut interrupts = 0;
+    for i in 1..lines.len() - 2 {
+        if trace_pc(lines[i]) != "0040" { continue }
+        interrupts += 1;
+
+        // the reti goes back to the instruction after the last one that ran,
+        // a fetch the interrupt took over from shouldn't be logged before it
+        assert_ne!(trace_pc(lines[i - 1]), trace_pc(lines[i + 1]), "line {}", i);
+    }
+    assert!(interrupts >= 2, "only {} interrupts", interrupts);
+}
+
+#[test]
+fn compare_through_interrupts() {
+    let reference = String::from_utf8(trace_rom("compare_interrupt_source.gb", &interrupt_rom(), TraceFormat::GameboyDoctor, 60_000)).unwrap();
+    let (_, result) = compare_rom("compare_interrupt.gb", &interrupt_rom(), &reference);
+
+    match result {
+        CompareResult::Matched(count) => assert_eq!(count, 60_000),
+        _ => panic!("Expected a match: {}", result)
+    }
+}
+
+#[test]
+fn parse_other_reference_formats() {
+    let entry = ReferenceEntry::parse(1, "pc=0150 af=01B0 bc=0013 sp=fffe ly=90 cycle=123").unwrap().unwrap();
+
+    assert_eq!(entry.entry.registers.pc, 0x0150);
+    assert_eq!(entry.entry.registers.a, 0x01);
+    assert_eq!(entry.entry.registers.f, 0xB0);
+    assert_eq!(entry.entry.registers.c, 0x13);
+    assert!(entry.has(TraceField::Sp));
+    assert!(!entry.has(TraceField::D));
+    assert!(!entry.has(TraceField::PcMem));
+
+    assert_eq!(ReferenceEntry::parse(2, "-- frame 1 --").unwrap(), None);
+    assert!(ReferenceEntry::parse(3, "A:ZZ").is_err());
+}
+
+#[test]
+fn doctor_ly_reads_0x90() {
+    let mut gb = create_gameboy("doctor_ly.gb", &PROGRAM);
+    assert!(!gb.doctor_ly());
+
+    // the first line is drawing straight after boot
+    for _ in 0..100 {
+        gb.tick();
+    }
+    assert_ne!(gb.peek_byte(0xFF44), 0x90);
+
+    gb.set_doctor_ly(true);
+    assert_eq!(gb.peek_byte(0xFF44), 0x90);
+    assert_ne!(gb.ppu_state().ly, 0x90);
+}
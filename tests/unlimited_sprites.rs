@@ -0,0 +1,164 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::memory::MemoryBanks, ppu::{PixelSource, PpuMode}};
+use common::{create_gameboy, run_frames};
+
+mod common;
+
+// 12 solid sprites on lines 16-23, 12 pixels apart, two more than the ppu draws
+const PROGRAM: [u8; 47] = [
+    0xAF,             // 0x150: xor a
+    0xE0, 0x40,       // 0x151: ldh [$40], a (lcd off)
+    0x21, 0x00, 0x82, // 0x153: ld hl, $8200 (tile $20)
+    0x3E, 0xFF,       // 0x156: ld a, $FF
+    0x06, 0x10,       // 0x158: ld b, 16
+    0x22,             // 0x15A: ld [hl+], a
+    0x05,             // 0x15B: dec b
+    0x20, 0xFC,       // 0x15C: jr nz, -4
+    0x21, 0x00, 0xFE, // 0x15E: ld hl, $FE00
+    0x06, 0x0C,       // 0x161: ld b, 12
+    0x0E, 0x10,       // 0x163: ld c, $10
+    0x3E, 0x20,       // 0x165: ld a, $20
+    0x22,             // 0x167: ld [hl+], a (y)
+    0x79,             // 0x168: ld a, c
+    0x22,             // 0x169: ld [hl+], a (x)
+    0xC6, 0x0C,       // 0x16A: add a, 12
+    0x4F,             // 0x16C: ld c, a
+    0x3E, 0x20,       // 0x16D: ld a, $20
+    0x22,             // 0x16F: ld [hl+], a (tile)
+    0xAF,             // 0x170: xor a
+    0x22,             // 0x171: ld [hl+], a (flags)
+    0x05,             // 0x172: dec b
+    0x20, 0xF0,       // 0x173: jr nz, -16
+    0x3E, 0xE4,       // 0x175: ld a, $E4
+    0xE0, 0x48,       // 0x177: ldh [$48], a (obp0)
+    0x3E, 0x93,       // 0x179: ld a, $93 (lcd, sprites and bg on)
+    0xE0, 0x40,       // 0x17B: ldh [$40], a
+    0x18, 0xFE        // 0x17D: jr -2
+];
+
+// where PROGRAM turns the lcd on
+const LCDC_VALUE: usize = 0x17A - 0x150;
+
+const LINE: usize = 20;
+const BLACK: u8 = 0;
+
+fn boot(name: &str, program: &[u8], unlimited: bool) -> GameBoy {
+    let mut gb = create_gameboy(name, program);
+    gb.set_unlimited_sprites(unlimited);
+    run_frames(&mut gb, 60 * 3);
+
+    gb
+}
+
+fn pixel(gb: &GameBoy, x: usize) -> (u8, PixelSource) {
+    let index = LINE * 160 + x;
+    (gb.get_shade_buffer()[index], gb.get_pixel_sources()[index])
+}
+
+// dots spent in mode 3 on LINE
+fn mode3_length(gb: &mut GameBoy) -> u64 {
+    while !(gb.ppu_state().ly == LINE as u8 && gb.ppu_state().mode == PpuMode::OAM) {
+        gb.tick();
+    }
+
+    let mut dots = 0;
+    while gb.ppu_state().mode != PpuMode::HBlank {
+        if gb.ppu_state().mode == PpuMode::VRAM {
+            dots += 1;
+        }
+        gb.tick();
+    }
+
+    dots
+}
+
+#[test]
+fn ten_sprites_by_default() {
+    let gb = boot("sprites_limited.gb", &PROGRAM, false);
+    assert!(!gb.unlimited_sprites());
+
+    // sprite 0 at x 8, sprites 10 and 11 at 128 and 140
+    assert_eq!(pixel(&gb, 10), (BLACK, PixelSource::Obj0));
+    assert_eq!(pixel(&gb, 130).1, PixelSource::Background);
+    assert_eq!(pixel(&gb, 142).1, PixelSource::Background);
+}
+
+#[test]
+fn every_sprite_drawn_when_unlimited() {
+    let mut gb = boot("sprites_unlimited.gb", &PROGRAM, true);
+
+    assert_eq!(pixel(&gb, 10), (BLACK, PixelSource::Obj0));
+    assert_eq!(pixel(&gb, 130), (BLACK, PixelSource::Obj0));
+    assert_eq!(pixel(&gb, 142), (BLACK, PixelSource::Obj0));
+
+    // the oam scan still only picks 10, that's what the timing comes from
+    while gb.oam_scan().line != LINE as u8 {
+        gb.tick();
+    }
+    assert_eq!(gb.oam_scan().sprites, (0..10).collect::<Vec<usize>>());
+}
+
+#[test]
+fn mode3_timing_unchanged() {
+    let mut limited = boot("sprites_timing_limited.gb", &PROGRAM, false);
+    let mut unlimited = boot("sprites_timing_unlimited.gb", &PROGRAM, true);
+
+    let length = mode3_length(&mut limited);
+    assert!(length > 172);
+    assert_eq!(mode3_length(&mut unlimited), length);
+}
+
+#[test]
+fn extra_sprites_hidden_with_objects_off() {
+    let mut program = PROGRAM;
+    program[LCDC_VALUE] = 0x91;
+    let gb = boot("sprites_unlimited_obj_off.gb", &program, true);
+
+    assert_eq!(pixel(&gb, 10).1, PixelSource::Background);
+    assert_eq!(pixel(&gb, 130).1, PixelSource::Background);
+    assert_eq!(pixel(&gb, 142).1, PixelSource::Background);
+}
+
+#[test]
+fn extra_sprites_with_a_lower_x_go_on_top() {
+    let mut gb = boot("sprites_unlimited_priority.gb", &PROGRAM, true);
+
+    // x going down instead, so the two extra sprites are the leftmost ones.
+    // Sprite 10 (x 56) uses obp1 and overlaps sprite 9 (x 60) and sprite 11 (x 52)
+    for i in 0..12 {
+        gb.debug_write(0xFE01 + i * 4, MemoryBanks::default(), 96 - i as u8 * 4);
+    }
+    gb.debug_write(0xFE2B, MemoryBanks::default(), 0x10);
+    gb.debug_write(0xFF49, MemoryBanks::default(), 0xE4);
+    run_frames(&mut gb, 2);
+
+    assert_eq!(pixel(&gb, 53), (BLACK, PixelSource::Obj1));
+    assert_eq!(pixel(&gb, 49), (BLACK, PixelSource::Obj0));
+    assert_eq!(pixel(&gb, 57), (BLACK, PixelSource::Obj0));
+}
+
+#[test]
+fn sprite_height_kept_from_the_oam_scan() {
+    let mut gb = boot("sprites_unlimited_tall.gb", &PROGRAM, true);
+
+    // 8x16 and flipped, tile $21 under the solid $20 is empty so the flipped
+    // bottom half is the solid one
+    gb.debug_write(0xFF40, MemoryBanks::default(), 0x97);
+    for i in 0..12 {
+        gb.debug_write(0xFE03 + i * 4, MemoryBanks::default(), 0x40);
+    }
+    run_frames(&mut gb, 1);
+
+    // switch back to 8x8 after line 28 was scanned, row 12 is past the end of
+    // an 8x8 sprite
+    while !(gb.ppu_state().ly == 28 && gb.ppu_state().mode == PpuMode::VRAM) {
+        gb.tick();
+    }
+    gb.debug_write(0xFF40, MemoryBanks::default(), 0x93);
+    while gb.ppu_state().mode != PpuMode::HBlank {
+        gb.tick();
+    }
+
+    let index = 28 * 160;
+    assert_eq!(gb.get_pixel_sources()[index + 10], PixelSource::Obj0);
+    assert_eq!(gb.get_pixel_sources()[index + 142], PixelSource::Obj0);
+}
@@ -0,0 +1,70 @@
+use gameboy_rs::gameboy::{debugger::vram::{self, TILEMAP_SIZE, TILESET_WIDTH, TileMap}};
+use common::boot_rom;
+
+mod common;
+
+// halts once the boot rom is done
+const PROGRAM: [u8; 2] = [
+    0x18, 0xFE        // 0x150: jr -2
+];
+
+#[test]
+fn tile_data_addressing() {
+    assert_eq!(vram::tile_data_addr(0x00, false), 0x8000);
+    assert_eq!(vram::tile_data_addr(0xFF, false), 0x8FF0);
+    assert_eq!(vram::tile_data_addr(0x00, true), 0x9000);
+    assert_eq!(vram::tile_data_addr(0x7F, true), 0x97F0);
+    assert_eq!(vram::tile_data_addr(0x80, true), 0x8800);
+}
+
+#[test]
+fn render_tiles_and_maps() {
+    let mut vram = [0u8; 0x2000];
+
+    // tile 1: colour 1 on the top row, colour 3 on the left column
+    vram[0x10] = 0xFF;
+    for row in 0..8 {
+        vram[0x10 + row * 2] |= 0x80;
+        vram[0x10 + row * 2 + 1] = 0x80;
+    }
+
+    assert_eq!(vram::tile_pixel(&vram, 1, 0, 0), 3);
+    assert_eq!(vram::tile_pixel(&vram, 1, 1, 0), 1);
+    assert_eq!(vram::tile_pixel(&vram, 1, 1, 1), 0);
+    assert_eq!(vram::tile_pixel(&vram, 1, 0, 7), 3);
+
+    // colour 0 white, 1 light grey, 3 black
+    assert_eq!(vram::palette_shades(0xE4), [255, 192, 96, 0]);
+
+    let tileset = vram::render_tileset(&vram, 0xE4);
+    assert_eq!(tileset[9], 192);
+    assert_eq!(tileset[8], 0);
+    assert_eq!(tileset[TILESET_WIDTH + 9], 255);
+
+    // tile 1 at the top left of the 9C00 map
+    vram[0x1C00] = 0x01;
+    let map = vram::render_tilemap(&vram, TileMap::High, false, 0xE4);
+    assert_eq!(map[0], 0);
+    assert_eq!(map[1], 192);
+    assert_eq!(map[TILEMAP_SIZE + 1], 255);
+    assert_eq!(map[8], 255);
+
+    // in 8800 mode index 1 is tile 257, which is empty
+    let map = vram::render_tilemap(&vram, TileMap::High, true, 0xE4);
+    assert_eq!(map[0], 255);
+}
+
+#[test]
+fn boot_logo_in_the_tilemap() {
+    let gb = boot_rom("vram_viewer.gb", &PROGRAM);
+
+    // the boot rom puts the logo tiles 1-24 at 9904 and 9924, and the (R) at 9910
+    let vram = gb.vram();
+    let tile = vram::map_tile_at(&vram, TileMap::Low, false, 4, 8);
+    assert_eq!(tile.map_addr, 0x9904);
+    assert_eq!(tile.tile_index, 0x01);
+    assert_eq!(tile.tile_addr, 0x8010);
+
+    assert_eq!(vram::map_tile_at(&vram, TileMap::Low, false, 4, 9).tile_index, 0x0D);
+    assert_eq!(vram::map_tile_at(&vram, TileMap::Low, false, 16, 8).tile_index, 0x19);
+}
@@ -0,0 +1,86 @@
+use gameboy_rs::gameboy::{GameBoy, debugger::{BreakReason, watchpoint::{Access, ValueCondition, WatchKind, Watchpoint, WatchpointHit}}};
+use common::{CYCLES_PER_SCREEN_DRAW, create_gameboy};
+
+mod common;
+
+const PROGRAM: [u8; 18] = [
+    0x3E, 0x42,       // 0x150: ld a, 0x42
+    0xEA, 0x00, 0xC0, // 0x152: ld (0xC000), a
+    0x3E, 0x01,       // 0x155: ld a, 1
+    0xEA, 0x00, 0xC0, // 0x157: ld (0xC000), a
+    0xEA, 0x00, 0x20, // 0x15A: ld (0x2000), a
+    0xFA, 0x00, 0xC0, // 0x15D: ld a, (0xC000)
+    0x18, 0xFE        // 0x160: jr -2
+];
+
+fn run_until_watchpoint(gb: &mut GameBoy) -> WatchpointHit {
+    for _ in 0..CYCLES_PER_SCREEN_DRAW * 60 * 10 {
+        if gb.tick() {
+            match gb.debugger().break_reason() {
+                Some(BreakReason::Watchpoint(hit)) => return hit,
+                reason => panic!("Paused for the wrong reason: {:?}", reason)
+            }
+        }
+    }
+
+    panic!("The watchpoint was never hit");
+}
+
+#[test]
+fn write_watchpoint_reports_pc_and_cycle() {
+    let mut gb = create_gameboy("watch_write.gb", &PROGRAM);
+    gb.watchpoints_mut().add(Watchpoint::new(0xC000, 0xC000, WatchKind::Write));
+
+    let first = run_until_watchpoint(&mut gb);
+    assert_eq!(first.access, Access::Write);
+    assert_eq!(first.addr, 0xC000);
+    assert_eq!(first.value, 0x42);
+    assert_eq!(first.pc, 0x0152);
+    assert!(first.cycle > 0);
+
+    gb.debugger_mut().resume();
+    let second = run_until_watchpoint(&mut gb);
+    assert_eq!(second.value, 0x01);
+    assert_eq!(second.pc, 0x0157);
+    assert!(second.cycle > first.cycle);
+}
+
+#[test]
+fn value_condition() {
+    let mut gb = create_gameboy("watch_condition.gb", &PROGRAM);
+
+    let mut watchpoint = Watchpoint::new(0xC000, 0xC0FF, WatchKind::Write);
+    watchpoint.condition = ValueCondition::parse("=01");
+    gb.watchpoints_mut().add(watchpoint);
+
+    let hit = run_until_watchpoint(&mut gb);
+    assert_eq!(hit.value, 0x01);
+    assert_eq!(hit.pc, 0x0157);
+
+    assert_eq!(ValueCondition::parse("clear 80"), Some(ValueCondition::BitsClear(0x80)));
+    assert!(ValueCondition::BitsClear(0x80).matches(0x11));
+    assert!(!ValueCondition::BitsSet(0x81).matches(0x80));
+    assert_eq!(ValueCondition::parse("80"), None);
+}
+
+#[test]
+fn mbc_register_writes() {
+    let mut gb = create_gameboy("watch_mbc.gb", &PROGRAM);
+    gb.watchpoints_mut().add(Watchpoint::new(0x0000, 0x7FFF, WatchKind::Write));
+
+    let hit = run_until_watchpoint(&mut gb);
+    assert_eq!(hit.access, Access::MbcWrite);
+    assert_eq!(hit.addr, 0x2000);
+    assert_eq!(hit.pc, 0x015A);
+}
+
+#[test]
+fn read_watchpoint() {
+    let mut gb = create_gameboy("watch_read.gb", &PROGRAM);
+    gb.watchpoints_mut().add(Watchpoint::new(0xC000, 0xC000, WatchKind::Read));
+
+    let hit = run_until_watchpoint(&mut gb);
+    assert_eq!(hit.access, Access::Read);
+    assert_eq!(hit.value, 0x01);
+    assert_eq!(hit.pc, 0x015D);
+}